@@ -5,6 +5,11 @@ pub enum Argument {
     DbPath(String),
     FallbackInMemory,
     HistoryPath(String),
+    AutoSave,
+    Test(String),
+    Limit(String, u64),
+    Rational,
+    Precision(usize),
 }
 
 pub fn parse_args(
@@ -55,6 +60,52 @@ pub fn parse_args(
                 let path = args.next().context("history path missing!!")?;
                 arguments.push(Argument::HistoryPath(path));
             }
+            "--autosave" => {
+                anyhow::ensure!(
+                    !arguments.iter().any(|a| matches!(a, Argument::AutoSave)),
+                    "autosave should be specified only once!"
+                );
+                arguments.push(Argument::AutoSave);
+            }
+            "--limit" => {
+                let limit = args.next().context("limit missing!!")?;
+                let (name, value) = limit
+                    .split_once('=')
+                    .context("limit should be name=value e.g loop=1000")?;
+                let value = value
+                    .parse::<u64>()
+                    .context("limit value should be a positive number")?;
+                arguments.push(Argument::Limit(name.to_string(), value));
+            }
+            "--rational" => {
+                anyhow::ensure!(
+                    !arguments.iter().any(|a| matches!(a, Argument::Rational)),
+                    "rational should be specified only once!"
+                );
+                arguments.push(Argument::Rational);
+            }
+            "--precision" => {
+                anyhow::ensure!(
+                    !arguments
+                        .iter()
+                        .any(|a| matches!(a, Argument::Precision(_))),
+                    "precision should be specified only once!"
+                );
+                let precision = args
+                    .next()
+                    .context("precision missing!!")?
+                    .parse::<usize>()
+                    .context("precision should be a positive number")?;
+                arguments.push(Argument::Precision(precision));
+            }
+            "test" => {
+                anyhow::ensure!(
+                    !arguments.iter().any(|a| matches!(a, Argument::Test(_))),
+                    "test should be specified only once!"
+                );
+                let path = args.next().unwrap_or_else(|| ".".into());
+                arguments.push(Argument::Test(path));
+            }
 
             _ => (), // ignore unknown argument
         }
@@ -1,10 +1,19 @@
-use std::collections::hash_map::DefaultHasher;
+use std::collections::{hash_map::DefaultHasher, BTreeSet};
 use std::hash::Hasher;
 use std::path::Path;
+use std::time::SystemTime;
 
-use crate::db::{Batch, DbOp, Op, Tree, DEFAULT_TREE};
+use crate::db::{
+    Batch, Capacity, DbOp, EntryKind, Metadata, Op, Tree, DEFAULT_TREE,
+    SCRIPT_CONTEXT_TREE,
+};
+use crate::karshscript::Primitive;
 use crate::prelude::*;
 
+use regex::RegexBuilder;
+
+use super::PutOptions;
+
 const DEFAULT_CACHE_KEY: &str = "$___DEF_CACHE_KEY_LOC___$";
 
 fn calculate_hash<T: Hash>(t: &T) -> u64 {
@@ -19,72 +28,275 @@ pub fn get_value(
     key: &str,
 ) -> Option<String> {
     db.open_tree(namespace)?;
-    db.get_value(key)
+    db.use_value(key)
+}
+
+/// how a value is run
+pub fn get_kind(
+    db: &mut impl DbOp<String, String>,
+    namespace: &str,
+    key: &str,
+) -> Option<EntryKind> {
+    db.open_tree(namespace)?;
+    Some(db.metadata(&key.to_string())?.kind)
+}
+
+/// one more execution of a value
+pub fn record_exec(
+    db: &mut impl DbOp<String, String>,
+    namespace: &str,
+    key: &str,
+) -> Option<()> {
+    db.open_tree(namespace)?;
+    db.update_metadata(&key.to_string(), |m| m.exec_count += 1)
+}
+
+pub fn list_values_with_metadata(
+    db: &mut impl DbOp<String, String>,
+    namespace: &str,
+) -> Option<Vec<(String, String, Metadata)>> {
+    db.open_tree(namespace)?;
+    Some(
+        db.list_all()
+            .into_iter()
+            .map(|(k, v)| {
+                let metadata = db.metadata(&k).unwrap_or_default();
+                (k, v, metadata)
+            })
+            .collect(),
+    )
+}
+
+/// values of a cache executed at least once with their names,
+/// the most executed first
+pub fn most_used(
+    db: &mut impl DbOp<String, String>,
+    namespace: &str,
+) -> Option<Vec<(String, Vec<String>, u64)>> {
+    let mut values = list_values_with_metadata(db, namespace)?
+        .into_iter()
+        .filter(|(_, _, metadata)| metadata.exec_count > 0)
+        .map(|(key, value, metadata)| {
+            let names = db.names(&key).unwrap_or_else(|| vec![key]);
+            (value, names, metadata.exec_count)
+        })
+        .collect::<Vec<_>>();
+    values.sort_by(|(_, _, a), (_, _, b)| b.cmp(a));
+    Some(values)
+}
+
+/// every name of a value, its key then its aliases
+pub fn names(
+    db: &mut impl DbOp<String, String>,
+    namespace: &str,
+    key: &str,
+) -> Option<Vec<String>> {
+    db.open_tree(namespace)?;
+    db.names(&key.to_string())
+}
+
+pub fn add_alias(
+    db: &mut impl DbOp<String, String>,
+    namespace: &str,
+    key: &str,
+    alias: &str,
+) -> Option<()> {
+    db.open_tree(namespace)?;
+    db.add_alias(&key.to_string(), alias.to_string())
+}
+
+pub fn remove_alias(
+    db: &mut impl DbOp<String, String>,
+    namespace: &str,
+    alias: &str,
+) -> Option<()> {
+    db.open_tree(namespace)?;
+    db.remove_alias(&alias.to_string())
 }
 
+pub fn rename_alias(
+    db: &mut impl DbOp<String, String>,
+    namespace: &str,
+    alias: &str,
+    new_alias: &str,
+) -> Option<()> {
+    db.open_tree(namespace)?;
+    db.rename_alias(&alias.to_string(), new_alias.to_string())
+}
+
+/// a value of a cache, with all its names
+#[derive(Debug, PartialEq, Eq)]
+pub struct CacheValue {
+    pub cache: String,
+    pub keys: Vec<String>,
+    pub value: String,
+    pub tags: BTreeSet<String>,
+    pub description: Option<String>,
+}
+
+/// values of a cache with their names, tags and description
+pub fn list_by_value(
+    db: &mut impl DbOp<String, String>,
+    namespace: &str,
+) -> Option<Vec<CacheValue>> {
+    let mut values = list_values_with_metadata(db, namespace)?
+        .into_iter()
+        .map(|(key, value, metadata)| CacheValue {
+            cache: namespace.to_string(),
+            keys: db.names(&key).unwrap_or_else(|| vec![key]),
+            value,
+            tags: metadata.tags,
+            description: metadata.description,
+        })
+        .collect::<Vec<_>>();
+    values.sort_by(|a, b| a.value.cmp(&b.value));
+    Some(values)
+}
+
+/// values of every cache with a key, value, tag or description matching
+/// the query, a case insensitive regex or else a text
+pub fn search(
+    db: &mut impl DbOp<String, String>,
+    query: &str,
+) -> Vec<CacheValue> {
+    let regex = RegexBuilder::new(query).case_insensitive(true).build().ok();
+    let query = query.to_lowercase();
+    let matches = |text: &str| match &regex {
+        Some(regex) => regex.is_match(text),
+        None => text.to_lowercase().contains(&query),
+    };
+
+    get_cache_names(db)
+        .iter()
+        .flat_map(|cache| list_by_value(db, cache).unwrap_or_default())
+        .filter(|v| {
+            matches(&v.value)
+                || v.keys.iter().any(|k| matches(k))
+                || v.tags.iter().any(|t| matches(t))
+                || v.description.as_deref().filter(|d| matches(d)).is_some()
+        })
+        .collect()
+}
+
+/// change a value, for all its names.
+/// returns how many names were updated
+pub fn replace_value(
+    db: &mut impl DbOp<String, String>,
+    namespace: &str,
+    key: &str,
+    new_value: &str,
+) -> Option<usize> {
+    db.open_tree(namespace)?;
+    let names = db.names(&key.to_string())?;
+    db.replace(key, new_value)?;
+    Some(names.len())
+}
+
+/// values of a cache under each of their names
 pub fn list_values(
     db: &mut impl DbOp<String, String>,
     namespace: &str,
 ) -> Option<Vec<(String, String)>> {
     db.open_tree(namespace)?;
-    Some(db.list_all().into_iter().collect())
+    Some(
+        db.list_all()
+            .into_iter()
+            .flat_map(|(k, v)| {
+                let names = db.names(&k).unwrap_or_else(|| vec![k]);
+                names.into_iter().map(move |name| (name, v.clone()))
+            })
+            .collect::<BTreeMap<_, _>>()
+            .into_iter()
+            .collect(),
+    )
 }
 
+/// remove a value and all its names
 pub fn remove_value(
     db: &mut impl DbOp<String, String>,
     namespace: &str,
     key: &str,
 ) -> Option<String> {
-    let mut consumer = |tree: &mut Tree<String, String>| {
-        let value = tree.get_value(key)?;
-        let to_delete: Vec<String> = tree
-            .iter()
-            .filter_map(|(k, v)| if v == &value { Some(k) } else { None })
-            .cloned()
-            .collect();
-        for k in to_delete {
-            tree.remove(&*k)?;
-        }
-        Some(value)
-    };
-
-    db.apply_tree(namespace, &mut consumer)
+    db.open_tree(namespace)?;
+    db.remove(key)
 }
 
 pub fn insert_value(
     db: &mut impl DbOp<String, String>,
     namespace: &str,
-    aliases: Vec<&str>,
+    options: PutOptions,
     value: &str,
 ) -> Option<String> {
+    let PutOptions { aliases, ttl, description, tags, script } = options;
     db.open_tree(namespace)?;
-    let mut batch = crate::db::Batch::default();
-    let keys = db.keys();
 
-    let aliases: Vec<&str> = aliases
+    let mut aliases: Vec<String> = aliases
         .iter()
-        .filter_map(|alias| {
-            if keys.contains(&alias.to_string()) {
-                None
-            } else {
-                Some(*alias)
-            }
-        })
+        .map(|alias| alias.to_string())
+        .filter(|alias| db.contains(alias) != Some(true))
         .collect();
+    aliases.dedup();
 
-    for hash_alias in &aliases {
-        batch.add_insert(hash_alias.to_string(), value.to_string());
+    let key = if aliases.is_empty() {
+        calculate_hash(&value).to_string()
+    } else {
+        aliases.remove(0)
+    };
+    match ttl {
+        Some(ttl) => db.insert_with_ttl(key.as_str(), value, Some(ttl)),
+        None => db.insert(key.as_str(), value),
+    };
+    for alias in aliases {
+        db.add_alias(&key, alias);
     }
 
-    if aliases.is_empty() {
-        let uniq_id = calculate_hash(&value);
+    db.update_metadata(&key, |m| {
+        if let Some(description) = description {
+            m.description = Some(description.to_string());
+        }
+        m.tags.extend(tags.iter().map(|t| t.to_lowercase()));
+        m.kind = if script { EntryKind::Script } else { EntryKind::Command };
+    })?;
 
-        batch.add_insert(uniq_id.to_string(), value.to_string());
-    }
+    Some(db.names(&key)?.join(", "))
+}
 
-    db.apply_batch(batch)?;
+pub fn get_default_ttl(
+    db: &mut impl DbOp<String, String>,
+    namespace: &str,
+) -> Option<Duration> {
+    db.open_tree(namespace)?;
+    db.get_default_ttl(namespace)
+}
 
-    Some(aliases.join(", "))
+pub fn set_default_ttl(
+    db: &mut impl DbOp<String, String>,
+    namespace: &str,
+    ttl: Option<Duration>,
+) -> Option<()> {
+    check_cache_name(namespace)?;
+    db.open_tree(namespace)?;
+    db.set_default_ttl(namespace, ttl)
+}
+
+/// capacity, number of values and size in bytes of a cache
+pub fn get_capacity(
+    db: &mut impl DbOp<String, String>,
+    namespace: &str,
+) -> Option<(Option<Capacity>, usize, u64)> {
+    db.open_tree(namespace)?;
+    Some((db.get_capacity(namespace), db.len()?, db.size_in_bytes(namespace)?))
+}
+
+/// returns how many values were evicted
+pub fn set_capacity(
+    db: &mut impl DbOp<String, String>,
+    namespace: &str,
+    capacity: Capacity,
+) -> Option<usize> {
+    check_cache_name(namespace)?;
+    db.open_tree(namespace)?;
+    db.set_capacity(namespace, Some(capacity))
 }
 
 pub fn clear_values(db: &mut impl DbOp<String, String>, cache_name: &str) {
@@ -101,7 +313,10 @@ pub fn remove_cache(
 }
 
 pub fn get_cache_names(db: &mut impl DbOp<String, String>) -> Vec<String> {
-    db.tree_names().into_iter().filter(|v| v != DEFAULT_TREE).collect()
+    db.tree_names()
+        .into_iter()
+        .filter(|v| v != DEFAULT_TREE && v != SCRIPT_CONTEXT_TREE)
+        .collect()
 }
 
 pub fn merge(
@@ -136,22 +351,15 @@ pub fn dump(
 ) -> Option<String> {
     if let Some(ns) = namespace {
         db.apply_tree(ns, &mut move |t| {
-            serde_json::to_string_pretty(&CacheJson {
-                name: ns.to_string(),
-                values: t.list_all(),
-            })
-            .ok()
+            serde_json::to_string_pretty(&CacheJson::from_tree(ns, t)).ok()
         })
     } else {
         let caches: Vec<String> = get_cache_names(db)
             .iter()
             .filter_map(|ns| {
                 db.apply_tree(ns, &mut move |t| {
-                    serde_json::to_string_pretty(&CacheJson {
-                        name: ns.clone(),
-                        values: t.list_all(),
-                    })
-                    .ok()
+                    serde_json::to_string_pretty(&CacheJson::from_tree(ns, t))
+                        .ok()
                 })
             })
             .collect();
@@ -169,20 +377,73 @@ pub fn restore(db: &mut impl DbOp<String, String>, path: &Path) -> Option<()> {
     let file = File::open(path).ok()?;
     let buf_reader = BufReader::new(file);
     let caches: Vec<CacheJson> = serde_json::from_reader(buf_reader).ok()?;
+    let now = now_millis();
     for cache in caches {
         let mut batch = Batch::default();
+        let mut with_ttl = vec![];
         db.open_tree(&cache.name)?;
 
         for (key, value) in cache.values {
-            batch.add_insert(key, value);
+            match cache.entries.get(&key).and_then(|e| e.expires_at) {
+                // expired since the backup
+                Some(expires_at) if expires_at <= now => {}
+                Some(expires_at) => with_ttl.push((
+                    key,
+                    value,
+                    Duration::from_millis(expires_at - now),
+                )),
+                None => batch.add_insert(key, value),
+            }
         }
         db.apply_batch(batch)?;
+        for (key, value, ttl) in with_ttl {
+            db.insert_with_ttl(key, value, Some(ttl));
+        }
+        for (alias, key) in cache.aliases {
+            db.add_alias(&key, alias);
+        }
+        for (key, entry) in cache.entries {
+            db.update_metadata(&key, |m| {
+                m.kind = entry.kind;
+                m.description = entry.description;
+                m.tags = entry.tags;
+            });
+        }
     }
     Some(())
 }
 
+pub fn save_script_context(
+    db: &mut impl DbOp<String, String>,
+    script_context: &BTreeMap<String, Primitive>,
+) -> Option<()> {
+    let mut batch = Batch::default();
+    for (name, value) in script_context {
+        batch.add_insert(name.to_string(), serde_json::to_string(value).ok()?);
+    }
+    db.open_tree(SCRIPT_CONTEXT_TREE)?;
+    db.clear();
+    db.apply_batch(batch)
+}
+
+pub fn load_script_context(
+    db: &mut impl DbOp<String, String>,
+) -> Option<BTreeMap<String, Primitive>> {
+    db.open_tree(SCRIPT_CONTEXT_TREE)?;
+    let mut script_context = BTreeMap::new();
+    for (name, value) in db.list_all() {
+        match serde_json::from_str(&value) {
+            Ok(value) => {
+                script_context.insert(name, value);
+            }
+            Err(e) => warn!("could not load variable {name}. err: {e}"),
+        }
+    }
+    Some(script_context)
+}
+
 fn check_cache_name(cache_name: &str) -> Option<()> {
-    if cache_name != DEFAULT_TREE {
+    if cache_name != DEFAULT_TREE && cache_name != SCRIPT_CONTEXT_TREE {
         Some(())
     } else {
         println!("{} you cannot do this.", colors::Red.paint("Warning!"));
@@ -190,8 +451,411 @@ fn check_cache_name(cache_name: &str) -> Option<()> {
     }
 }
 
+/// milliseconds since the epoch
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+/// what a backup keeps of an entry besides its value and names
+#[derive(Serialize, Deserialize, Default, PartialEq)]
+struct EntryJson {
+    #[serde(default)]
+    kind: EntryKind,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    tags: BTreeSet<String>,
+    /// milliseconds since the epoch
+    #[serde(default)]
+    expires_at: Option<u64>,
+}
+
 #[derive(Serialize, Deserialize)]
 struct CacheJson {
     name: String,
     values: BTreeMap<String, String>,
+    /// alias -> key, missing in the backups of karsher < 0.10
+    #[serde(default)]
+    aliases: BTreeMap<String, String>,
+    /// key -> entry, only the entries with more than a value
+    #[serde(default)]
+    entries: BTreeMap<String, EntryJson>,
+}
+
+impl CacheJson {
+    fn from_tree(name: &str, tree: &Tree<String, String>) -> CacheJson {
+        let values = tree.list_all();
+        let aliases = values
+            .keys()
+            .flat_map(|key| {
+                tree.names(key)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .skip(1)
+                    .map(move |alias| (alias, key.clone()))
+            })
+            .collect();
+        let now = now_millis();
+        let entries = values
+            .keys()
+            .filter_map(|key| {
+                let m = tree.metadata(key).unwrap_or_default();
+                let entry = EntryJson {
+                    kind: m.kind,
+                    description: m.description,
+                    tags: m.tags,
+                    expires_at: tree
+                        .ttl(key)
+                        .map(|ttl| now + ttl.as_millis() as u64),
+                };
+                (entry != EntryJson::default()).then(|| (key.clone(), entry))
+            })
+            .collect();
+        CacheJson { name: name.to_string(), values, aliases, entries }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::db::{DbOp, EntryKind, InMemoryDb, Op};
+    use crate::karshscript::Primitive;
+    use crate::prelude::*;
+
+    use super::{
+        add_alias, backup, get_cache_names, get_default_ttl, get_kind,
+        get_value, insert_value, list_values, list_values_with_metadata,
+        load_script_context, most_used, names, record_exec, remove_alias,
+        remove_value, rename_alias, replace_value, restore,
+        save_script_context, search, set_default_ttl, PutOptions,
+    };
+
+    fn aliases<'a>(aliases: &[&'a str]) -> PutOptions<'a> {
+        PutOptions { aliases: aliases.to_vec(), ..Default::default() }
+    }
+
+    #[test]
+    fn test_backup_restore() {
+        let mut db: InMemoryDb<String, String> = InMemoryDb::default();
+        insert_value(
+            &mut db,
+            "math",
+            PutOptions {
+                aliases: vec!["fib", "f"],
+                ttl: Some(Duration::from_secs(3600)),
+                description: Some("fibonacci"),
+                tags: vec!["Math"],
+                script: true,
+            },
+            "a + b",
+        );
+        insert_value(&mut db, "math", aliases(&["calc"]), "bc -l");
+        let path = std::env::temp_dir()
+            .join(format!("karsher-backup-{}.json", std::process::id()));
+        backup(&mut db, &path).unwrap();
+
+        let mut db: InMemoryDb<String, String> = InMemoryDb::default();
+        restore(&mut db, &path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            Some(vec!["fib".to_string(), "f".to_string()]),
+            names(&mut db, "math", "f")
+        );
+        assert_eq!(Some(EntryKind::Script), get_kind(&mut db, "math", "fib"));
+        assert_eq!(Some(EntryKind::Command), get_kind(&mut db, "math", "calc"));
+        let metadata = db.metadata(&"fib".to_string()).unwrap();
+        assert_eq!(Some("fibonacci".to_string()), metadata.description);
+        assert_eq!(vec!["math".to_string()], Vec::from_iter(metadata.tags));
+        let ttl = db.ttl(&"fib".to_string()).unwrap();
+        assert!(ttl > Duration::from_secs(3590));
+        assert_eq!(None, db.ttl(&"calc".to_string()));
+    }
+
+    #[test]
+    fn test_save_load_script_context() {
+        let mut db: InMemoryDb<String, String> = InMemoryDb::default();
+        let ctx = BTreeMap::from([
+            ("a".to_string(), Primitive::Int(25)),
+            ("b".to_string(), Primitive::Double(1.5)),
+            (
+                "c".to_string(),
+                Primitive::Array(vec![
+                    Primitive::String("x".to_string()),
+                    Primitive::Bool(true),
+                ]),
+            ),
+            (
+                "d".to_string(),
+                Primitive::Map(BTreeMap::from([(
+                    "e".to_string(),
+                    Primitive::Unit,
+                )])),
+            ),
+        ]);
+        save_script_context(&mut db, &ctx).unwrap();
+        assert_eq!(Some(ctx), load_script_context(&mut db));
+
+        let ctx = BTreeMap::from([("z".to_string(), Primitive::Int(1))]);
+        save_script_context(&mut db, &ctx).unwrap();
+        assert_eq!(Some(ctx), load_script_context(&mut db));
+
+        assert!(get_cache_names(&mut db).is_empty());
+    }
+
+    #[test]
+    fn test_ttl() {
+        let mut db: InMemoryDb<String, String> = InMemoryDb::default();
+        insert_value(&mut db, "k8s", aliases(&["pods"]), "kubectl get pods");
+        insert_value(
+            &mut db,
+            "k8s",
+            PutOptions {
+                aliases: vec!["tok"],
+                ttl: Some(Duration::from_millis(1)),
+                ..Default::default()
+            },
+            "kubectl create token",
+        );
+        assert!(db.ttl(&"tok".to_string()).is_some());
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(None, get_value(&mut db, "k8s", "tok"));
+        // evicted on read
+        assert_eq!(
+            vec!["pods".to_string()],
+            db.list_all().into_keys().collect::<Vec<_>>()
+        );
+        assert_eq!(0, db.evict_expired());
+
+        assert_eq!(None, get_default_ttl(&mut db, "k8s"));
+        set_default_ttl(&mut db, "k8s", Some(Duration::from_secs(60)));
+        assert_eq!(
+            Some(Duration::from_secs(60)),
+            get_default_ttl(&mut db, "k8s")
+        );
+        insert_value(&mut db, "k8s", aliases(&["ns"]), "kubectl get ns");
+        assert!(db.ttl(&"ns".to_string()).is_some());
+        assert_eq!(None, db.ttl(&"pods".to_string()));
+    }
+
+    #[test]
+    fn test_most_used() {
+        let mut db: InMemoryDb<String, String> = InMemoryDb::default();
+        insert_value(
+            &mut db,
+            "k8s",
+            PutOptions {
+                aliases: vec!["pods", "kgp"],
+                description: Some("list the pods"),
+                ..Default::default()
+            },
+            "kubectl get pods",
+        );
+        insert_value(&mut db, "k8s", aliases(&["ns"]), "kubectl get ns");
+        insert_value(&mut db, "k8s", aliases(&["ctx"]), "kubectx");
+        record_exec(&mut db, "k8s", "pods");
+        record_exec(&mut db, "k8s", "kgp");
+        record_exec(&mut db, "k8s", "ns");
+        record_exec(&mut db, "k8s", "unknown");
+
+        assert_eq!(
+            Some(vec![
+                (
+                    "kubectl get pods".to_string(),
+                    vec!["pods".to_string(), "kgp".to_string()],
+                    2
+                ),
+                ("kubectl get ns".to_string(), vec!["ns".to_string()], 1),
+            ]),
+            most_used(&mut db, "k8s")
+        );
+        let entries = list_values_with_metadata(&mut db, "k8s").unwrap();
+        // one entry for the value and its aliases
+        assert_eq!(3, entries.len());
+        let (_, _, metadata) =
+            entries.iter().find(|(k, _, _)| k == "pods").unwrap();
+        assert_eq!(Some("list the pods".to_string()), metadata.description);
+        assert_eq!(2, metadata.exec_count);
+    }
+
+    #[test]
+    fn test_search() {
+        let mut db: InMemoryDb<String, String> = InMemoryDb::default();
+        insert_value(
+            &mut db,
+            "docker",
+            PutOptions {
+                aliases: vec!["dcu", "up"],
+                tags: vec!["Compose", "dev"],
+                ..Default::default()
+            },
+            "docker-compose up -d",
+        );
+        insert_value(
+            &mut db,
+            "docker",
+            PutOptions {
+                aliases: vec!["prune"],
+                description: Some("free some disk space (images)"),
+                ..Default::default()
+            },
+            "docker system prune",
+        );
+        insert_value(&mut db, "k8s", aliases(&["pods"]), "kubectl get pods");
+
+        let mut found = |query: &str| {
+            search(&mut db, query)
+                .into_iter()
+                .map(|r| (r.cache, r.keys.join(",")))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            vec![
+                ("docker".to_string(), "prune".to_string()),
+                ("docker".to_string(), "dcu,up".to_string())
+            ],
+            found("docker")
+        );
+        assert_eq!(
+            vec![("docker".to_string(), "dcu,up".to_string())],
+            found("compose")
+        );
+        assert_eq!(
+            vec![("docker".to_string(), "prune".to_string())],
+            found("DISK")
+        );
+        assert_eq!(vec![("k8s".to_string(), "pods".to_string())], found("^po"));
+        // not a valid regex
+        assert_eq!(
+            vec![("docker".to_string(), "prune".to_string())],
+            found("space (")
+        );
+        assert!(found("nothing").is_empty());
+
+        let result = search(&mut db, "dev").pop().unwrap();
+        assert_eq!(
+            vec!["compose".to_string(), "dev".to_string()],
+            result.tags.into_iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_replace_value() {
+        let mut db: InMemoryDb<String, String> = InMemoryDb::default();
+        insert_value(
+            &mut db,
+            "k8s",
+            PutOptions {
+                aliases: vec!["pods", "kgp"],
+                ttl: Some(Duration::from_secs(60)),
+                description: Some("list the pods"),
+                ..Default::default()
+            },
+            "kubectl get pods",
+        );
+        insert_value(&mut db, "k8s", aliases(&["ns"]), "kubectl get ns");
+        record_exec(&mut db, "k8s", "kgp");
+
+        assert_eq!(
+            Some(2),
+            replace_value(&mut db, "k8s", "pods", "kubectl get pods -A")
+        );
+        assert_eq!(None, replace_value(&mut db, "k8s", "nope", "x"));
+        let values = list_values_with_metadata(&mut db, "k8s").unwrap();
+        let (_, value, metadata) =
+            values.iter().find(|(k, _, _)| k == "pods").unwrap();
+        assert_eq!("kubectl get pods -A", value);
+        assert_eq!(1, metadata.exec_count);
+        assert_eq!(Some("list the pods".to_string()), metadata.description);
+        assert!(db.ttl(&"kgp".to_string()).is_some());
+        assert_eq!(
+            Some("kubectl get ns".to_string()),
+            get_value(&mut db, "k8s", "ns")
+        );
+    }
+
+    #[test]
+    fn test_aliases() {
+        let mut db: InMemoryDb<String, String> = InMemoryDb::default();
+        assert_eq!(
+            Some("drc, dcu".to_string()),
+            insert_value(
+                &mut db,
+                "docker",
+                aliases(&["drc", "dcu"]),
+                "docker-compose up"
+            )
+        );
+        // same text, another value
+        insert_value(&mut db, "docker", aliases(&["up"]), "docker-compose up");
+        let id =
+            insert_value(&mut db, "docker", aliases(&[]), "docker ps").unwrap();
+
+        assert_eq!(Some(()), add_alias(&mut db, "docker", "dcu", "dup"));
+        assert_eq!(None, add_alias(&mut db, "docker", "dcu", "up"));
+        assert_eq!(None, add_alias(&mut db, "docker", "nope", "x"));
+        assert_eq!(Some(()), add_alias(&mut db, "docker", &id, "ps"));
+        assert_eq!(
+            Some(vec!["drc".to_string(), "dcu".to_string(), "dup".to_string()]),
+            names(&mut db, "docker", "dup")
+        );
+
+        assert_eq!(Some(()), rename_alias(&mut db, "docker", "dup", "cu"));
+        assert_eq!(None, rename_alias(&mut db, "docker", "cu", "drc"));
+        // the key of the value can be renamed or removed too
+        assert_eq!(Some(()), rename_alias(&mut db, "docker", "drc", "compose"));
+        assert_eq!(Some(()), remove_alias(&mut db, "docker", "compose"));
+        assert_eq!(
+            Some(vec!["cu".to_string(), "dcu".to_string()]),
+            names(&mut db, "docker", "dcu")
+        );
+        assert_eq!(None, remove_alias(&mut db, "docker", "up"));
+        assert_eq!(
+            Some("docker-compose up".to_string()),
+            get_value(&mut db, "docker", "dcu")
+        );
+
+        assert_eq!(
+            Some(vec![
+                (id.clone(), "docker ps".to_string()),
+                ("cu".to_string(), "docker-compose up".to_string()),
+                ("dcu".to_string(), "docker-compose up".to_string()),
+                ("ps".to_string(), "docker ps".to_string()),
+                ("up".to_string(), "docker-compose up".to_string()),
+            ]),
+            list_values(&mut db, "docker")
+        );
+
+        // only the value named, not the ones with the same text
+        assert_eq!(
+            Some("docker-compose up".to_string()),
+            remove_value(&mut db, "docker", "cu")
+        );
+        assert_eq!(None, names(&mut db, "docker", "dcu"));
+        assert_eq!(
+            Some(vec!["up".to_string()]),
+            names(&mut db, "docker", "up")
+        );
+        insert_value(
+            &mut db,
+            "docker",
+            PutOptions {
+                aliases: vec!["count"],
+                script: true,
+                ..Default::default()
+            },
+            "length(cache_list())",
+        );
+        assert_eq!(
+            Some(EntryKind::Script),
+            get_kind(&mut db, "docker", "count")
+        );
+        assert_eq!(Some(EntryKind::Command), get_kind(&mut db, "docker", "up"));
+        // a removed alias can name another value
+        insert_value(&mut db, "docker", aliases(&["dcu"]), "docker compose");
+        assert_eq!(
+            Some("docker compose".to_string()),
+            get_value(&mut db, "docker", "dcu")
+        );
+    }
 }
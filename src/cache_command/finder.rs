@@ -0,0 +1,322 @@
+use std::io::{stdout, Stdout, Write};
+
+use crossterm::{
+    cursor::MoveTo,
+    event::{read, Event, KeyCode, KeyEvent, KeyModifiers},
+    execute, queue,
+    style::Print,
+    terminal::{
+        disable_raw_mode, enable_raw_mode, size, Clear, ClearType,
+        EnterAlternateScreen, LeaveAlternateScreen,
+    },
+};
+
+use crate::prelude::colors::*;
+
+use super::cache::CacheValue;
+
+const SCORE_MATCH: i64 = 16;
+const BONUS_BOUNDARY: i64 = 10;
+const BONUS_CONSECUTIVE: i64 = 8;
+const PENALTY_GAP_START: i64 = 3;
+const PENALTY_GAP_EXTENSION: i64 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FinderAction {
+    Exec,
+    CopyToPrompt,
+    Edit,
+    Delete,
+}
+
+fn is_boundary(previous: Option<char>, c: char) -> bool {
+    match previous {
+        None => true,
+        Some(p) => {
+            !p.is_alphanumeric() || (p.is_lowercase() && c.is_uppercase())
+        }
+    }
+}
+
+/// score of the chars of query found in order in text, like fzf.
+/// consecutive chars and chars starting a word score more, gaps cost.
+/// returns the best score and the positions (in chars) of the matches,
+/// None if query is not a subsequence of text. case insensitive
+pub fn fuzzy_match(query: &str, text: &str) -> Option<(i64, Vec<usize>)> {
+    let lower = |c: char| c.to_lowercase().next().unwrap_or(c);
+    let query = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(lower)
+        .collect::<Vec<_>>();
+    if query.is_empty() {
+        return Some((0, vec![]));
+    }
+    let text = text.chars().collect::<Vec<_>>();
+    let bonus = (0..text.len())
+        .map(|j| {
+            if is_boundary(j.checked_sub(1).map(|p| text[p]), text[j]) {
+                BONUS_BOUNDARY
+            } else {
+                0
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // best score with query[i] matched at text[j], the bonus of the
+    // consecutive matches it ends and where query[i - 1] was matched
+    #[derive(Clone, Copy)]
+    struct Cell {
+        score: i64,
+        chunk_bonus: i64,
+        previous: usize,
+    }
+    let mut cells: Vec<Vec<Option<Cell>>> =
+        vec![vec![None; text.len()]; query.len()];
+    for (i, q) in query.iter().enumerate() {
+        // best cell of the previous row before j - 1, for a gap
+        let mut best_gap: Option<(i64, usize)> = None;
+        for j in 0..text.len() {
+            if i > 0 && j >= 2 {
+                if let Some(cell) = cells[i - 1][j - 2] {
+                    let gap_score =
+                        cell.score + PENALTY_GAP_EXTENSION * (j - 2) as i64;
+                    if best_gap.filter(|(s, _)| *s >= gap_score).is_none() {
+                        best_gap = Some((gap_score, j - 2));
+                    }
+                }
+            }
+            if lower(text[j]) != *q {
+                continue;
+            }
+            if i == 0 {
+                // the first char of the query counts double
+                cells[i][j] = Some(Cell {
+                    score: SCORE_MATCH + bonus[j] * 2,
+                    chunk_bonus: bonus[j],
+                    previous: j,
+                });
+                continue;
+            }
+            let consecutive = j
+                .checked_sub(1)
+                .and_then(|p| cells[i - 1][p].map(|c| (p, c)))
+                .map(|(p, c)| {
+                    let chunk_bonus = c.chunk_bonus.max(bonus[j]);
+                    Cell {
+                        score: c.score
+                            + SCORE_MATCH
+                            + chunk_bonus.max(BONUS_CONSECUTIVE),
+                        chunk_bonus,
+                        previous: p,
+                    }
+                });
+            let gap = best_gap.map(|(gap_score, p)| Cell {
+                score: gap_score
+                    - PENALTY_GAP_EXTENSION * (j - 2) as i64
+                    - PENALTY_GAP_START
+                    + SCORE_MATCH
+                    + bonus[j],
+                chunk_bonus: bonus[j],
+                previous: p,
+            });
+            cells[i][j] = match (consecutive, gap) {
+                (Some(c), Some(g)) if g.score > c.score => Some(g),
+                (Some(c), _) => Some(c),
+                (None, g) => g,
+            };
+        }
+    }
+
+    let last = query.len() - 1;
+    let (mut j, best) = cells[last]
+        .iter()
+        .enumerate()
+        .filter_map(|(j, c)| c.map(|c| (j, c)))
+        .max_by_key(|(j, c)| (c.score, std::cmp::Reverse(*j)))?;
+    let mut positions = vec![j];
+    for i in (1..=last).rev() {
+        j = cells[i][j]?.previous;
+        positions.push(j);
+    }
+    positions.reverse();
+    Some((best.score, positions))
+}
+
+/// text matched and shown for a value, on one line
+fn line(value: &CacheValue) -> String {
+    format!("{}  {}", value.keys.join(", "), value.value.replace('\n', " ↵ "))
+}
+
+/// restore the terminal, even on error
+struct RawTerminal(Stdout);
+
+impl RawTerminal {
+    fn new() -> anyhow::Result<RawTerminal> {
+        let mut out = stdout();
+        enable_raw_mode()?;
+        execute!(out, EnterAlternateScreen)?;
+        Ok(RawTerminal(out))
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        let _ = execute!(self.0, LeaveAlternateScreen);
+        let _ = disable_raw_mode();
+    }
+}
+
+fn render(
+    out: &mut Stdout,
+    query: &str,
+    matches: &[(usize, Vec<usize>)],
+    values: &[CacheValue],
+    selected: usize,
+    offset: usize,
+) -> anyhow::Result<()> {
+    let (width, height) = size()?;
+    let width = width as usize;
+    queue!(out, Clear(ClearType::All), MoveTo(0, 1))?;
+    queue!(
+        out,
+        Print(DarkGray.paint(format!(
+            "  {}/{}  enter: exec, tab: copy to prompt, ctrl-e: edit, \
+                 ctrl-d: delete, esc: quit",
+            matches.len(),
+            values.len()
+        )))
+    )?;
+    let rows = height.saturating_sub(2) as usize;
+    for (row, (index, positions)) in
+        matches.iter().enumerate().skip(offset).take(rows)
+    {
+        let is_selected = row == selected;
+        let text = line(&values[*index])
+            .chars()
+            .take(width.saturating_sub(2))
+            .enumerate()
+            .map(|(i, c)| {
+                let style = if positions.contains(&i) {
+                    LightGreen.bold()
+                } else if is_selected {
+                    Style::new().bold()
+                } else {
+                    Style::new()
+                };
+                style.paint(c.to_string()).to_string()
+            })
+            .collect::<String>();
+        let marker = if is_selected { Red.paint("> ") } else { "  ".into() };
+        queue!(
+            out,
+            MoveTo(0, (row - offset + 2) as u16),
+            Print(marker),
+            Print(text)
+        )?;
+    }
+    queue!(
+        out,
+        MoveTo(0, 0),
+        Print(format!("{} {query}", LightCyan.bold().paint(">")))
+    )?;
+    out.flush()?;
+    Ok(())
+}
+
+/// full screen fuzzy picker over values, returns the action chosen and the
+/// index of the value, None if cancelled
+pub fn pick(
+    values: &[CacheValue],
+) -> anyhow::Result<Option<(FinderAction, usize)>> {
+    let mut terminal = RawTerminal::new()?;
+    let mut query = String::new();
+    let mut selected = 0;
+    let mut offset = 0;
+    loop {
+        let mut matches = values
+            .iter()
+            .enumerate()
+            .filter_map(|(i, v)| {
+                fuzzy_match(&query, &line(v)).map(|(s, p)| (s, i, p))
+            })
+            .collect::<Vec<_>>();
+        matches.sort_by(|(a, i, _), (b, j, _)| b.cmp(a).then(i.cmp(j)));
+        let matches =
+            matches.into_iter().map(|(_, i, p)| (i, p)).collect::<Vec<_>>();
+
+        selected = selected.min(matches.len().saturating_sub(1));
+        let rows = size()?.1.saturating_sub(2).max(1) as usize;
+        if selected < offset {
+            offset = selected;
+        } else if selected >= offset + rows {
+            offset = selected + 1 - rows;
+        }
+        render(&mut terminal.0, &query, &matches, values, selected, offset)?;
+
+        let chosen = |action| matches.get(selected).map(|(i, _)| (action, *i));
+        if let Event::Key(KeyEvent { code, modifiers, .. }) = read()? {
+            let ctrl = modifiers.contains(KeyModifiers::CONTROL);
+            match code {
+                KeyCode::Esc => return Ok(None),
+                KeyCode::Char('c') if ctrl => return Ok(None),
+                KeyCode::Enter => return Ok(chosen(FinderAction::Exec)),
+                KeyCode::Tab => return Ok(chosen(FinderAction::CopyToPrompt)),
+                KeyCode::Char('e') if ctrl => {
+                    return Ok(chosen(FinderAction::Edit))
+                }
+                KeyCode::Char('d') if ctrl => {
+                    return Ok(chosen(FinderAction::Delete))
+                }
+                KeyCode::Up => selected = selected.saturating_sub(1),
+                KeyCode::Char('p' | 'k') if ctrl => {
+                    selected = selected.saturating_sub(1)
+                }
+                KeyCode::Down => selected += 1,
+                KeyCode::Char('n' | 'j') if ctrl => selected += 1,
+                KeyCode::PageUp => selected = selected.saturating_sub(rows),
+                KeyCode::PageDown => selected += rows,
+                KeyCode::Backspace => {
+                    query.pop();
+                    selected = 0;
+                }
+                KeyCode::Char(c) if !ctrl => {
+                    query.push(c);
+                    selected = 0;
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::fuzzy_match;
+
+    #[test]
+    fn test_fuzzy_match() {
+        assert_eq!(Some((0, vec![])), fuzzy_match("", "anything"));
+        assert_eq!(None, fuzzy_match("dcx", "docker compose up"));
+        assert_eq!(
+            Some(vec![0, 7, 15]),
+            fuzzy_match("dcu", "docker compose up").map(|(_, p)| p)
+        );
+        // the best start is kept, not the first one
+        assert_eq!(
+            Some(vec![7, 8, 9]),
+            fuzzy_match("com", "docker compose").map(|(_, p)| p)
+        );
+        assert_eq!(
+            Some(vec![4, 5, 6]),
+            fuzzy_match("Pod", "get pods").map(|(_, p)| p)
+        );
+
+        let score = |q, t| fuzzy_match(q, t).unwrap().0;
+        // consecutive beats scattered
+        assert!(score("pods", "kubectl get pods") > score("pods", "p o d s"));
+        // word starts beat the middle of words
+        assert!(score("gp", "get pods") > score("gp", "target stop"));
+        assert!(score("ku", "kubectl") > score("ku", "sku"));
+    }
+}
@@ -0,0 +1,133 @@
+use crate::{
+    db::DbOp, karshscript::Host, reserved_keywords::check_reserved_keyword,
+};
+
+use super::{cache::*, PutOptions};
+
+/// Expose the cache database to karshscript
+pub struct CacheHost<'a, D: DbOp<String, String>> {
+    db: &'a mut D,
+    current_cache: &'a mut String,
+}
+
+impl<'a, D: DbOp<String, String>> CacheHost<'a, D> {
+    pub fn new(db: &'a mut D, current_cache: &'a mut String) -> Self {
+        CacheHost { db, current_cache }
+    }
+}
+
+impl<D: DbOp<String, String>> Host for CacheHost<'_, D> {
+    fn cache_get(&mut self, key: &str) -> Option<String> {
+        get_value(self.db, self.current_cache, key)
+    }
+
+    fn cache_put(&mut self, aliases: Vec<&str>, value: &str) -> Option<String> {
+        if check_reserved_keyword(&aliases) {
+            return None;
+        }
+        insert_value(
+            self.db,
+            self.current_cache,
+            PutOptions { aliases, ..Default::default() },
+            value,
+        )
+    }
+
+    fn cache_del(&mut self, key: &str) -> Option<String> {
+        remove_value(self.db, self.current_cache, key)
+    }
+
+    fn cache_list(
+        &mut self,
+        cache_name: Option<&str>,
+    ) -> Option<Vec<(String, String)>> {
+        let cache_name = cache_name.unwrap_or(self.current_cache);
+        if !get_cache_names(self.db).iter().any(|c| c == cache_name) {
+            return None;
+        }
+        let values = list_values(self.db, cache_name);
+        // list_values opens the tree, go back to the current cache
+        self.db.open_tree(self.current_cache)?;
+        values
+    }
+
+    fn cache_use(&mut self, cache_name: &str) -> Option<String> {
+        set_default_cache(self.db, cache_name)?;
+        Some(std::mem::replace(self.current_cache, cache_name.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use crate::{
+        db::InMemoryDb,
+        karshscript::{compute_with_host, Primitive},
+    };
+
+    use super::CacheHost;
+
+    #[test]
+    fn test_cache_from_script() {
+        let mut db: InMemoryDb<String, String> = InMemoryDb::default();
+        let mut current_cache = "DEFAULT".to_string();
+        let mut ctx = BTreeMap::new();
+        let mut host = CacheHost::new(&mut db, &mut current_cache);
+
+        let script = r#"
+        cache_put("drc", "docker-compose", ["drcomp"])
+        v = cache_get("drcomp")
+        previous = cache_use("linux")
+        cache_put("ll", "ls -la")
+        linux = cache_list()
+        ll = linux["ll"]
+        default = cache_list("DEFAULT")
+        removed = cache_del("ll")
+        "#;
+        compute_with_host(script, &mut ctx, &mut host).unwrap();
+
+        assert_eq!(
+            Some(&Primitive::String("docker-compose".to_string())),
+            ctx.get("v")
+        );
+        assert_eq!(
+            Some(&Primitive::String("DEFAULT".to_string())),
+            ctx.get("previous")
+        );
+        assert_eq!(
+            Some(&Primitive::Map(BTreeMap::from([(
+                "ll".to_string(),
+                Primitive::String("ls -la".to_string())
+            )]))),
+            ctx.get("linux")
+        );
+        assert_eq!(
+            Some(&Primitive::String("ls -la".to_string())),
+            ctx.get("ll")
+        );
+        assert_eq!(
+            Some(&Primitive::Map(BTreeMap::from([
+                (
+                    "drc".to_string(),
+                    Primitive::String("docker-compose".to_string())
+                ),
+                (
+                    "drcomp".to_string(),
+                    Primitive::String("docker-compose".to_string())
+                ),
+            ]))),
+            ctx.get("default")
+        );
+        assert_eq!(
+            Some(&Primitive::String("ls -la".to_string())),
+            ctx.get("removed")
+        );
+        assert_eq!(
+            Primitive::Error("key not found in cache"),
+            compute_with_host(r#"cache_get("ll")"#, &mut ctx, &mut host)
+                .unwrap()
+        );
+        assert_eq!("linux", current_cache);
+    }
+}
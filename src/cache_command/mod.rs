@@ -1,11 +1,22 @@
 mod cache;
+mod finder;
+mod host;
 mod os_command;
 mod parser;
 mod process;
-pub use cache::get_default_cache;
+mod script;
+mod template;
+pub use cache::{get_default_cache, load_script_context, save_script_context};
+pub use host::CacheHost;
+pub use os_command::{
+    capture_command, capture_shell, get_all_envs, get_env, set_session_env,
+    ExecOptions,
+};
 pub use process::process_command;
 use strum::EnumCount;
 
+use crate::{db::Capacity, prelude::Duration};
+
 pub mod constants {
     pub const PUT: &str = "put";
     pub const GET: &str = "get";
@@ -32,15 +43,65 @@ pub mod constants {
     pub const CLEAR_ALT: &str = "cls";
     pub const PRINT_SCRIPT_CONTEXT: &str = "print_script_ctx";
     pub const PRINT_SCRIPT_CONTEXT_ALT: &str = "script_ctx";
+    pub const SAVE_SCRIPT_CONTEXT: &str = "save_ctx";
+    pub const LOAD_SCRIPT_CONTEXT: &str = "load_ctx";
+    pub const EXPORT: &str = "export";
+    pub const TTL: &str = "ttl";
+    pub const CAPACITY: &str = "capacity";
+    pub const STATS: &str = "stats";
+    pub const SEARCH: &str = "search";
+    pub const FIND: &str = "find";
+    pub const ALIAS: &str = "alias";
+    pub const EDIT: &str = "edit";
     pub const HELP: &str = "help";
 }
 
 pub use constants::*;
 
+/// options of `put`
+#[derive(Debug, Default)]
+pub struct PutOptions<'a> {
+    pub aliases: Vec<&'a str>,
+    pub ttl: Option<Duration>,
+    pub description: Option<&'a str>,
+    pub tags: Vec<&'a str>,
+    /// a karshscript snippet instead of an os command
+    pub script: bool,
+}
+
+/// `alias add|rm|rename|ls`
+#[derive(Debug)]
+pub enum AliasCommand<'a> {
+    Add {
+        key: &'a str,
+        aliases: Vec<&'a str>,
+    },
+    Remove(&'a str),
+    Rename {
+        alias: &'a str,
+        new_alias: &'a str,
+    },
+    /// every value of the cache or one
+    List(Option<&'a str>),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum DescribeSort {
+    /// most executed first
+    Used,
+    /// last used first
+    Recent,
+}
+
 #[derive(Debug, EnumCount)]
 pub enum CacheCommand<'a> {
-    Put { aliases: Vec<&'a str>, value: &'a str },
-    Describe,
+    /// None to write the value in the editor
+    Put {
+        options: PutOptions<'a>,
+        value: Option<&'a str>,
+    },
+    Edit(&'a str),
+    Describe(Option<DescribeSort>),
     ListCache,
     CurrentCache,
     Backup,
@@ -49,37 +110,63 @@ pub enum CacheCommand<'a> {
     Merge(&'a str),
     Del(&'a str),
     Get(&'a str),
-    Exec { key: &'a str, args: Option<&'a str> },
+    Exec {
+        key: &'a str,
+        args: Option<&'a str>,
+        capture: Option<&'a str>,
+    },
     Cd(&'a str),
     Using(&'a str),
     Dump(Option<&'a str>),
     Clear,
     PrintScriptContext,
+    SaveScriptContext,
+    LoadScriptContext,
+    Export {
+        key: &'a str,
+        value: &'a str,
+    },
+    Ttl(Option<&'a str>),
+    Capacity(Option<Capacity>),
+    Stats,
+    Search(&'a str),
+    Find,
+    Alias(AliasCommand<'a>),
     Help,
 }
 
 impl CacheCommand<'_> {
     pub const fn doc() -> &'static [(&'static [&'static str], &'static str)] {
-        if CacheCommand::COUNT != 17 {
+        if CacheCommand::COUNT != 27 {
             panic!("CacheCommand::doc() no longer valid!");
         }
         &[
-            (&[PUT], "Put a new value to current cache. can have multiple aliases with option '-a'. e.g `put -a drc -a drcomp docker-compose`"),
-            (&[DESCRIBE,DESCRIBE_ALT], "List values within the cache."),
+            (&[PUT], "Put a new value to current cache. can have multiple aliases with option '-a', expire after a duration with option '-t', a description with option '-d' and tags with option '--tag'. Without a value, the editor is opened to write it on multiple lines. With option '--script', the value is a karshscript snippet run by `exec`. e.g `put -a drc -a drcomp --tag docker docker-compose` or `put -t 1h -d \"api token\" -a tok value`"),
+            (&[EDIT], "Edit a value in $EDITOR, its aliases are kept. Accept either a hashkey or an alias. e.g `edit drc`"),
+            (&[DESCRIBE,DESCRIBE_ALT], "List values within the cache. can be sorted by the most executed or the last used with option '--sort'. e.g `describe --sort used` or `ds --sort recent`"),
             (&[LIST_CACHE, LIST_CACHE_ALT], "List available caches."),
             (&[CURR_CACHE, CURR_CACHE_ALT], "Current cache."),
             (&[BACKUP, BACKUP_ALT], "Backup the database of caches to the current directory"),
             (&[RESTORE], "Restore the database from current directory"),
             (&[DEL_CACHE,DEL_CACHE_ALT], "Delete cache or clear current cache value."),
             (&[MERGE_CACHE,MERGE_CACHE_ALT], "Merge current with a given cache"),
-            (&[DEL,DEL_ALT], "Remove value from cache. Accept either a hashkey or an alias. e.g `del drc`"),
+            (&[DEL,DEL_ALT], "Remove value from cache with all its aliases. Accept either a hashkey or an alias. e.g `del drc`"),
             (&[GET], "Get value from cache. Accept either a hashkey or an alias. e.g `get drc`"),
-            (&[EXEC], "Run a value from the cache as an OS command. Accept either a hashkey or an alias. e.g `run drc`"),
+            (&[EXEC], "Run a value from the cache as an OS command. Accept either a hashkey or an alias. Placeholders `{1}`, `{host}` or `{port:22}` are filled from the arguments, missing ones are asked. e.g `exec ssh root host=10.0.0.1`. A script is evaluated in the script context, the arguments `name=value` are bound to `name` and the others to `arg1`, `arg2`... and `args`. e.g `exec fib n=10`. The exit code is kept in `$?` and `last_output.code`, a non-zero one is reported. The output of a command is kept in `last_output.stdout` / `last_output.stderr`. With `--capture var` it is also stored in `var` instead of printed e.g `exec --capture out ls -l`"),
             (&[CD], "Navigate to a directory"),
             (&[USE], "Use another cache context default cache is DEFAULT. e.g `use linux`"),
             (&[DUMP], "Dump cache(s) as json. Take an optional parameter, the cache name. e.g `dump linux`"),
             (&[CLEAR, CLEAR_ALT], "Clear the terminal."),
             (&[PRINT_SCRIPT_CONTEXT, PRINT_SCRIPT_CONTEXT_ALT], "Print script context"),
+            (&[SAVE_SCRIPT_CONTEXT], "Save script context to the database"),
+            (&[LOAD_SCRIPT_CONTEXT], "Load script context from the database. Variables with the same name are overwritten"),
+            (&[EXPORT], "Set an environment variable for every command executed in this session. e.g `export AWS_PROFILE=dev`"),
+            (&[TTL], "Show or set how long the values put in the current cache are kept. `off` keeps them forever. e.g `ttl 7d`"),
+            (&[FIND], "Fuzzy find a value of the current cache by its keys or value. Use the arrows to select it, enter to execute it, tab to copy it to the prompt, ctrl-e to edit it and ctrl-d to delete it."),
+            (&[STATS], "List the most executed values of each cache."),
+            (&[SEARCH], "Search a text or a regex in the keys, values, tags and descriptions of every cache, then select a result to execute it. e.g `search docker`"),
+            (&[ALIAS], "Manage the names of a value. `alias add <key> <alias>...` adds aliases, `alias rm <alias>` removes one, `alias rename <alias> <new>` renames one and `alias ls [key]` lists them. e.g `alias add drc dcu`"),
+            (&[CAPACITY], "Show or limit the size of the current cache with '-n' entries and/or '-b' bytes. When full, the value least recently used, least frequently used or put first is evicted with '-p lru|lfu|fifo'. `off` removes the limits. e.g `capacity -n 500 -b 1mb -p lfu`"),
 
             (&[HELP], "Display Help."),
         ]
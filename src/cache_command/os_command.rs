@@ -1,5 +1,50 @@
+use std::{
+    cell::RefCell,
+    io::{Read, Write},
+    process::Child,
+    thread::JoinHandle,
+    time::Instant,
+};
+
 use crate::prelude::*;
-fn extract_args<'a>(s: &'a str) -> Res<Vec<&'a str>> {
+
+thread_local! {
+    /// variables exported during the session, passed to every command
+    static SESSION_ENVS: RefCell<BTreeMap<String, String>> =
+        const { RefCell::new(BTreeMap::new()) };
+}
+
+/// export a variable for every command run afterwards in this session
+pub fn set_session_env(key: &str, value: &str) {
+    SESSION_ENVS.with(|envs| {
+        envs.borrow_mut().insert(key.to_string(), value.to_string())
+    });
+}
+
+/// value of a variable, exported in the session or inherited from the os
+pub fn get_env(key: &str) -> Option<String> {
+    SESSION_ENVS
+        .with(|envs| envs.borrow().get(key).cloned())
+        .or_else(|| std::env::var(key).ok())
+}
+
+/// os environment with the session overlay applied
+pub fn get_all_envs() -> BTreeMap<String, String> {
+    let mut all: BTreeMap<String, String> = std::env::vars().collect();
+    SESSION_ENVS.with(|envs| {
+        all.extend(envs.borrow().iter().map(|(k, v)| (k.clone(), v.clone())))
+    });
+    all
+}
+
+/// a command with the session variables set. variables prefixing the
+/// command e.g `FOO=bar cmd` take precedence
+fn new_command(program: &str) -> Command {
+    let mut cmd = Command::new(program);
+    SESSION_ENVS.with(|envs| cmd.envs(envs.borrow().iter()));
+    cmd
+}
+pub(super) fn extract_args<'a>(s: &'a str) -> Res<Vec<&'a str>> {
     preceded(
         multispace0,
         separated_list0(
@@ -28,10 +73,12 @@ fn extract_program<'a>(s: &'a str) -> Res<&'a str> {
     preceded(multispace0, take_while(|s| s != ' '))(s)
 }
 
+/// run a command in the terminal, returns its output or None if it could
+/// not start. stdout and stderr are printed as they come, and recorded
 pub fn exec_command<'a>(
     command: &'a str,
     extra_args: &'a Option<&'a str>,
-) -> Res<'a, ()> {
+) -> Res<'a, Option<ExecOutput>> {
     let (remaining, envs) = extract_envs(command)?;
     let (remaining, program) = extract_program(remaining)?;
 
@@ -42,32 +89,275 @@ pub fn exec_command<'a>(
         args.append(&mut extra_args);
     }
 
-    let handle = Command::new(program)
+    let handle = new_command(program)
         .envs(envs)
         .args(&args)
-        .stdout(Stdio::inherit())
-        .stderr(Stdio::inherit())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
         .spawn();
 
-    match handle.and_then(|mut h| h.wait()) {
-        Ok(status) => {
-            debug!("{status}");
-        }
+    let output = match handle.and_then(tee_output) {
+        Ok(output) => Some(output),
         Err(e) => {
-            eprintln!("{program} failed to start with args {args:?}. err: {e}")
+            eprintln!("{program} failed to start with args {args:?}. err: {e}");
+            None
         }
+    };
+
+    Ok((command, output))
+}
+
+/// `sh -c script`, the args are `$1`, `$2`...
+fn shell_command(script: &str, args: &[&str]) -> Command {
+    let mut cmd = new_command("sh");
+    cmd.arg("-c").arg(script).arg("sh").args(args);
+    cmd
+}
+
+/// run a multi-line value in the terminal, like exec_command
+pub fn exec_script(script: &str, args: &[&str]) -> anyhow::Result<ExecOutput> {
+    let child = shell_command(script, args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    Ok(tee_output(child)?)
+}
+
+/// copy a pipe to out as it comes, and keep what was read
+fn tee_in_background(
+    pipe: Option<impl Read + Send + 'static>,
+    mut out: impl Write + Send + 'static,
+) -> JoinHandle<String> {
+    std::thread::spawn(move || {
+        let mut buf = vec![];
+        if let Some(mut pipe) = pipe {
+            let mut chunk = [0; 4096];
+            loop {
+                match pipe.read(&mut chunk) {
+                    Ok(0) => break,
+                    Ok(n) => {
+                        let _ = out.write_all(&chunk[..n]);
+                        let _ = out.flush();
+                        buf.extend_from_slice(&chunk[..n]);
+                    }
+                    Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                    Err(_) => break,
+                }
+            }
+        }
+        String::from_utf8_lossy(&buf).into_owned()
+    })
+}
+
+/// wait for a child with piped stdout / stderr, printing them on the way
+fn tee_output(mut child: Child) -> std::io::Result<ExecOutput> {
+    let stdout = tee_in_background(child.stdout.take(), std::io::stdout());
+    let stderr = tee_in_background(child.stderr.take(), std::io::stderr());
+    let status = child.wait()?;
+    debug!("{status}");
+    Ok(ExecOutput {
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+        code: status.code().unwrap_or(-1),
+    })
+}
+
+#[derive(Debug, Default)]
+pub struct ExecOptions {
+    pub envs: Vec<(String, String)>,
+    pub cwd: Option<String>,
+    pub timeout: Option<Duration>,
+    pub stdin: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct ExecOutput {
+    pub stdout: String,
+    pub stderr: String,
+    pub code: i32,
+}
+
+/// run a command like exec_command, but capture its output instead of
+/// inheriting stdout / stderr
+pub fn capture_command(
+    command: &str,
+    options: &ExecOptions,
+) -> anyhow::Result<ExecOutput> {
+    let to_error = |e: nom::Err<nom::error::Error<&str>>| {
+        anyhow::Error::msg(format!("invalid command {command}. err: {e}"))
+    };
+    let (remaining, envs) = extract_envs(command).map_err(to_error)?;
+    let (remaining, program) = extract_program(remaining).map_err(to_error)?;
+    let (_, args) = extract_args(remaining).map_err(to_error)?;
+
+    let mut cmd = new_command(program);
+    cmd.envs(envs).args(&args);
+    spawn_and_capture(cmd, options)
+}
+
+/// run a script with `sh -c`, capturing its output
+pub fn capture_shell(
+    script: &str,
+    options: &ExecOptions,
+) -> anyhow::Result<ExecOutput> {
+    capture_script(script, &[], options)
+}
+
+/// capture_shell with the args of the script
+pub fn capture_script(
+    script: &str,
+    args: &[&str],
+    options: &ExecOptions,
+) -> anyhow::Result<ExecOutput> {
+    spawn_and_capture(shell_command(script, args), options)
+}
+
+fn spawn_and_capture(
+    mut cmd: Command,
+    options: &ExecOptions,
+) -> anyhow::Result<ExecOutput> {
+    cmd.envs(options.envs.iter().map(|(k, v)| (k, v)))
+        .stdin(if options.stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    if let Some(cwd) = &options.cwd {
+        cmd.current_dir(cwd);
     }
 
-    Ok((command, ()))
+    let mut child = cmd.spawn()?;
+
+    // read in the background so the child never blocks on a full pipe
+    let stdout = tee_in_background(child.stdout.take(), std::io::sink());
+    let stderr = tee_in_background(child.stderr.take(), std::io::sink());
+
+    if let (Some(input), Some(mut stdin)) = (&options.stdin, child.stdin.take())
+    {
+        stdin.write_all(input.as_bytes())?;
+    } // stdin is dropped here, so the child receives EOF
+
+    let status = if let Some(timeout) = options.timeout {
+        let start = Instant::now();
+        loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            if start.elapsed() > timeout {
+                child.kill()?;
+                child.wait()?;
+                anyhow::bail!(
+                    "command timed out after {}ms",
+                    timeout.as_millis()
+                );
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    } else {
+        child.wait()?
+    };
+
+    debug!("{status}");
+
+    Ok(ExecOutput {
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+        code: status.code().unwrap_or(-1),
+    })
 }
 
 #[cfg(test)]
 mod test {
-    use super::exec_command;
+    use std::time::Duration;
+
+    use super::{
+        capture_command, capture_script, capture_shell, exec_command,
+        exec_script, get_env, set_session_env, ExecOptions, ExecOutput,
+    };
 
     #[test]
     fn test_exec_command() {
-        exec_command("echo 'hello world'", &None).unwrap();
-        println!("bye")
+        let output = exec_command("echo hello world", &None).unwrap().1;
+        assert_eq!(Some("hello world\n"), output.map(|o| o.stdout).as_deref());
+        println!("bye");
+        let code = |output: Option<ExecOutput>| output.map(|o| o.code);
+        assert_eq!(Some(1), code(exec_command("false", &None).unwrap().1));
+        assert_eq!(
+            Some(2),
+            code(exec_command("sh -c", &Some(r#""exit 2""#)).unwrap().1)
+        );
+        assert!(exec_command("karsher-not-a-program", &None)
+            .unwrap()
+            .1
+            .is_none());
+        let output =
+            exec_script("echo out\necho err 1>&2\nexit $1", &["4"]).unwrap();
+        assert_eq!("out\n", output.stdout);
+        assert_eq!("err\n", output.stderr);
+        assert_eq!(4, output.code);
+    }
+
+    #[test]
+    fn test_capture_command() {
+        let output =
+            capture_command("echo hello world", &ExecOptions::default())
+                .unwrap();
+        assert_eq!("hello world\n", output.stdout);
+        assert_eq!(0, output.code);
+
+        let output = capture_shell(
+            "cat - && echo $FOO 1>&2 && pwd && exit 3",
+            &ExecOptions {
+                envs: vec![("FOO".into(), "bar".into())],
+                cwd: Some("/tmp".into()),
+                stdin: Some("from stdin ".into()),
+                timeout: None,
+            },
+        )
+        .unwrap();
+        assert_eq!("from stdin /tmp\n", output.stdout);
+        assert_eq!("bar\n", output.stderr);
+        assert_eq!(3, output.code);
+
+        let output = capture_script(
+            "echo $1\necho $2",
+            &["a", "b c"],
+            &ExecOptions::default(),
+        )
+        .unwrap();
+        assert_eq!("a\nb c\n", output.stdout);
+
+        let output = capture_shell(
+            "sleep 5",
+            &ExecOptions {
+                timeout: Some(Duration::from_millis(50)),
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            "command timed out after 50ms",
+            output.unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_session_envs() {
+        set_session_env("KARSHER_TEST_ENV", "session");
+        assert_eq!(Some("session".to_string()), get_env("KARSHER_TEST_ENV"));
+        let output =
+            capture_shell("echo $KARSHER_TEST_ENV", &ExecOptions::default())
+                .unwrap();
+        assert_eq!("session\n", output.stdout);
+
+        // a prefix on the command wins over the session
+        let output = capture_command(
+            "KARSHER_TEST_ENV=prefix printenv KARSHER_TEST_ENV",
+            &ExecOptions::default(),
+        )
+        .unwrap();
+        assert_eq!("prefix\n", output.stdout);
     }
 }
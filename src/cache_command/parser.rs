@@ -1,33 +1,103 @@
-use crate::prelude::*;
+use nom::character::complete::digit1;
 
-use super::{constants::*, CacheCommand};
+use crate::{
+    db::{Capacity, EvictionPolicy},
+    karshscript::duration_from_str,
+    prelude::*,
+};
 
-fn add_command(command: &str) -> Res<CacheCommand> {
-    map(
-        pair(
+use super::{
+    constants::*, AliasCommand, CacheCommand, DescribeSort, PutOptions,
+};
+
+enum PutOption<'a> {
+    Alias(&'a str),
+    Ttl(Duration),
+    Description(&'a str),
+    Tag(&'a str),
+    Script,
+}
+
+fn put_option(command: &str) -> Res<PutOption> {
+    preceded(
+        multispace1,
+        alt((
             preceded(
-                preceded(multispace0, tag_no_case(PUT)),
-                many0(preceded(
-                    preceded(multispace1, tag_no_case("-a")),
-                    preceded(
-                        multispace1,
-                        cut(verify(
-                            take_while(|c: char| {
-                                c.is_alphanumeric() || c == '_'
-                            }),
-                            |s: &str| !s.is_empty(),
-                        )),
+                pair(tag_no_case("-a"), multispace1),
+                cut(map(
+                    verify(
+                        take_while(|c: char| c.is_alphanumeric() || c == '_'),
+                        |s: &str| !s.is_empty(),
                     ),
+                    PutOption::Alias,
                 )),
             ),
             preceded(
-                multispace1,
-                cut(verify(rest.map(|s: &str| s.trim()), |s: &str| {
-                    !s.is_empty()
-                })),
+                pair(tag_no_case("-t"), multispace1),
+                cut(map(
+                    map_opt(take_till1(|c: char| c.is_whitespace()), |s| {
+                        duration_from_str(s).filter(|d| !d.is_zero())
+                    }),
+                    PutOption::Ttl,
+                )),
+            ),
+            preceded(
+                pair(tag_no_case("--tag"), multispace1),
+                cut(map(
+                    take_while1(|c: char| {
+                        c.is_alphanumeric() || c == '_' || c == '-'
+                    }),
+                    PutOption::Tag,
+                )),
+            ),
+            map(
+                terminated(
+                    tag_no_case("--script"),
+                    alt((peek(multispace1), eof)),
+                ),
+                |_| PutOption::Script,
             ),
+            preceded(
+                pair(tag_no_case("-d"), multispace1),
+                cut(map(
+                    alt((
+                        delimited(tag("\""), take_until("\""), tag("\"")),
+                        take_till1(|c: char| c.is_whitespace()),
+                    )),
+                    PutOption::Description,
+                )),
+            ),
+        )),
+    )(command)
+}
+
+fn add_command(command: &str) -> Res<CacheCommand> {
+    map(
+        pair(
+            preceded(
+                preceded(multispace0, tag_no_case(PUT)),
+                many0(put_option),
+            ),
+            alt((
+                map(verify(rest, |s: &str| s.trim().is_empty()), |_| None),
+                preceded(multispace1, rest.map(|s: &str| Some(s.trim()))),
+            )),
         ),
-        |(aliases, value)| CacheCommand::Put { aliases, value },
+        |(options, value)| {
+            let mut put_options = PutOptions::default();
+            for option in options {
+                match option {
+                    PutOption::Alias(alias) => put_options.aliases.push(alias),
+                    PutOption::Ttl(ttl) => put_options.ttl = Some(ttl),
+                    PutOption::Description(d) => {
+                        put_options.description = Some(d)
+                    }
+                    PutOption::Tag(tag) => put_options.tags.push(tag),
+                    PutOption::Script => put_options.script = true,
+                }
+            }
+            CacheCommand::Put { options: put_options, value }
+        },
     )(command)
 }
 
@@ -38,6 +108,10 @@ fn del_command(command: &str) -> Res<CacheCommand> {
     )(command)
 }
 
+fn edit_command(command: &str) -> Res<CacheCommand> {
+    map(extract_key(tag_no_case(EDIT)), CacheCommand::Edit)(command)
+}
+
 fn get_command(command: &str) -> Res<CacheCommand> {
     map(extract_key(tag_no_case(GET)), CacheCommand::Get)(command)
 }
@@ -73,16 +147,171 @@ fn print_script_context_command(command: &str) -> Res<CacheCommand> {
         |_| CacheCommand::PrintScriptContext,
     )(command)
 }
+fn save_script_context_command(command: &str) -> Res<CacheCommand> {
+    extract_no_args(tag_no_case(SAVE_SCRIPT_CONTEXT), |_| {
+        CacheCommand::SaveScriptContext
+    })(command)
+}
+fn load_script_context_command(command: &str) -> Res<CacheCommand> {
+    extract_no_args(tag_no_case(LOAD_SCRIPT_CONTEXT), |_| {
+        CacheCommand::LoadScriptContext
+    })(command)
+}
 fn exec_command(command: &str) -> Res<CacheCommand> {
+    let capture = delimited(
+        pair(tag_no_case("--capture"), multispace1),
+        verify(
+            take_while1(|c: char| c.is_alphanumeric() || c == '_'),
+            |s: &str| s.starts_with(|c: char| c.is_alphabetic()),
+        ),
+        multispace1,
+    );
     map(
-        pair(
+        tuple((
             alt((
-                extract_key(tag_no_case(EXEC)),
-                take_till1(|s: char| s.is_whitespace()),
+                preceded(
+                    pair(tag_no_case(EXEC), multispace1),
+                    pair(
+                        opt(capture),
+                        take_while1(|s: char| {
+                            s.is_alphanumeric() || s == '-' || s == '_'
+                        }),
+                    ),
+                ),
+                map(take_till1(|s: char| s.is_whitespace()), |key| (None, key)),
             )),
             opt(rest.map(|r: &str| r.trim())),
+        )),
+        |((capture, key), args)| CacheCommand::Exec { key, args, capture },
+    )(command)
+}
+
+fn export_command(command: &str) -> Res<CacheCommand> {
+    map(
+        preceded(
+            tag_no_case(EXPORT),
+            preceded(
+                multispace1,
+                separated_pair(
+                    take_while1(|c: char| c.is_alphanumeric() || c == '_'),
+                    tag("="),
+                    rest.map(|s: &str| {
+                        let s = s.trim();
+                        s.strip_prefix('"')
+                            .and_then(|s| s.strip_suffix('"'))
+                            .unwrap_or(s)
+                    }),
+                ),
+            ),
+        ),
+        |(key, value)| CacheCommand::Export { key, value },
+    )(command)
+}
+
+fn ttl_command(command: &str) -> Res<CacheCommand> {
+    map(
+        preceded(
+            tag_no_case(TTL),
+            cut(verify(rest, |s: &str| {
+                s.is_empty() || s.starts_with(' ') || s == "\n"
+            }))
+            .and_then(opt(preceded(
+                multispace1,
+                take_while1(|c: char| !c.is_whitespace()),
+            ))),
+        ),
+        CacheCommand::Ttl,
+    )(command)
+}
+
+enum CapacityOption {
+    MaxEntries(usize),
+    MaxBytes(u64),
+    Policy(EvictionPolicy),
+}
+
+/// e.g `512`, `64kb` or `1mb`
+fn parse_bytes(s: &str) -> Res<u64> {
+    map_opt(
+        pair(
+            digit1,
+            alt((
+                tag_no_case("kb"),
+                tag_no_case("mb"),
+                tag_no_case("gb"),
+                tag_no_case("b"),
+                rest.map(|_| ""),
+            )),
         ),
-        |(key, args)| CacheCommand::Exec { key, args },
+        |(n, unit): (&str, &str)| {
+            let unit: u64 = match unit.to_lowercase().as_str() {
+                "kb" => 1024,
+                "mb" => 1024 * 1024,
+                "gb" => 1024 * 1024 * 1024,
+                _ => 1,
+            };
+            n.parse::<u64>().ok()?.checked_mul(unit)
+        },
+    )(s)
+}
+
+fn capacity_option(command: &str) -> Res<CapacityOption> {
+    fn option<'a, O>(
+        name: &'static str,
+        value: impl FnMut(&'a str) -> Option<O>,
+    ) -> impl FnMut(&'a str) -> Res<'a, O> {
+        preceded(
+            pair(tag_no_case(name), multispace1),
+            cut(map_opt(take_till1(|c: char| c.is_whitespace()), value)),
+        )
+    }
+    preceded(
+        multispace1,
+        alt((
+            map(
+                option("-n", |s| s.parse().ok().filter(|n| *n > 0)),
+                CapacityOption::MaxEntries,
+            ),
+            map(
+                option("-b", |s| {
+                    all_consuming(parse_bytes)(s)
+                        .ok()
+                        .map(|(_, b)| b)
+                        .filter(|b| *b > 0)
+                }),
+                CapacityOption::MaxBytes,
+            ),
+            map(option("-p", |s| s.parse().ok()), CapacityOption::Policy),
+        )),
+    )(command)
+}
+
+fn capacity_command(command: &str) -> Res<CacheCommand> {
+    preceded(
+        tag_no_case(CAPACITY),
+        alt((
+            map(preceded(multispace1, tag_no_case("off")), |_| {
+                CacheCommand::Capacity(Some(Capacity::default()))
+            }),
+            map(many1(capacity_option), |options| {
+                let mut capacity = Capacity::default();
+                for option in options {
+                    match option {
+                        CapacityOption::MaxEntries(n) => {
+                            capacity.max_entries = Some(n)
+                        }
+                        CapacityOption::MaxBytes(b) => {
+                            capacity.max_bytes = Some(b)
+                        }
+                        CapacityOption::Policy(p) => capacity.policy = p,
+                    }
+                }
+                CacheCommand::Capacity(Some(capacity))
+            }),
+            map(verify(rest, |s: &str| s.trim().is_empty()), |_| {
+                CacheCommand::Capacity(None)
+            }),
+        )),
     )(command)
 }
 
@@ -97,12 +326,84 @@ fn cd_command(command: &str) -> Res<CacheCommand> {
 }
 
 fn list_command(command: &str) -> Res<CacheCommand> {
-    extract_no_args(
-        |s| alt((tag_no_case(DESCRIBE), tag_no_case(DESCRIBE_ALT)))(s),
-        |_| CacheCommand::Describe,
+    map(
+        terminated(
+            preceded(
+                alt((tag_no_case(DESCRIBE), tag_no_case(DESCRIBE_ALT))),
+                opt(preceded(
+                    tuple((multispace1, tag_no_case("--sort"), multispace1)),
+                    cut(alt((
+                        map(tag_no_case("used"), |_| DescribeSort::Used),
+                        map(tag_no_case("recent"), |_| DescribeSort::Recent),
+                    ))),
+                )),
+            ),
+            verify(rest, |s: &str| s.trim().is_empty()),
+        ),
+        CacheCommand::Describe,
+    )(command)
+}
+
+fn search_command(command: &str) -> Res<CacheCommand> {
+    map(
+        preceded(
+            tag_no_case(SEARCH),
+            preceded(
+                multispace1,
+                verify(rest.map(|s: &str| s.trim()), |s: &str| !s.is_empty()),
+            ),
+        ),
+        CacheCommand::Search,
+    )(command)
+}
+
+fn find_command(command: &str) -> Res<CacheCommand> {
+    extract_no_args(tag_no_case(FIND), |_| CacheCommand::Find)(command)
+}
+
+fn alias_command(command: &str) -> Res<CacheCommand> {
+    let name = |s| {
+        preceded(
+            multispace1,
+            take_while1(|c: char| c.is_alphanumeric() || c == '-' || c == '_'),
+        )(s)
+    };
+    map(
+        preceded(
+            pair(tag_no_case(ALIAS), multispace1),
+            cut(terminated(
+                alt((
+                    map(
+                        preceded(tag_no_case("add"), pair(name, many1(name))),
+                        |(key, aliases)| AliasCommand::Add { key, aliases },
+                    ),
+                    map(
+                        preceded(tag_no_case("rm"), name),
+                        AliasCommand::Remove,
+                    ),
+                    map(
+                        preceded(tag_no_case("rename"), pair(name, name)),
+                        |(alias, new_alias)| AliasCommand::Rename {
+                            alias,
+                            new_alias,
+                        },
+                    ),
+                    map(
+                        preceded(tag_no_case("ls"), opt(name)),
+                        AliasCommand::List,
+                    ),
+                )),
+                verify(rest, |s: &str| s.trim().is_empty()),
+            )),
+        ),
+        CacheCommand::Alias,
     )(command)
 }
 
+fn stats_command(command: &str) -> Res<CacheCommand> {
+    extract_no_args(tag_no_case(STATS), |_| CacheCommand::Stats)(command)
+}
+
 fn current_cache_command(command: &str) -> Res<CacheCommand> {
     extract_no_args(
         |s| alt((tag_no_case(CURR_CACHE_ALT), tag_no_case(CURR_CACHE)))(s),
@@ -215,6 +516,18 @@ pub fn parse_command(command: &str) -> Res<CacheCommand> {
             backup_command,
             restore_command,
             print_script_context_command,
+            save_script_context_command,
+            load_script_context_command,
+            alt((
+                export_command,
+                ttl_command,
+                capacity_command,
+                stats_command,
+                search_command,
+                find_command,
+                alias_command,
+                edit_command,
+            )),
             exec_command,
         )),
     )(command)
@@ -1,40 +1,354 @@
-use std::{collections::BTreeMap, path::Path};
+use std::{
+    cmp::Reverse,
+    collections::BTreeMap,
+    io::stdin,
+    path::Path,
+    time::{Duration, SystemTime},
+};
 
+use crossterm::tty::IsTty;
 use nom::error::ErrorKind;
 
 use crate::{
-    db::DbOp,
-    karshscript::Primitive,
+    db::{DbOp, EntryKind},
+    editor::{open_in_editor, set_next_input},
+    karshscript::{
+        constants::{LAST_OUTPUT, LAST_STATUS},
+        duration_from_str, duration_to_string, Primitive,
+    },
     prelude::colors::*,
     reserved_keywords::{check_reserved_keyword, CACHE_COMMAND_DOC},
 };
 
 use super::{
-    cache::*, clear_terminal, os_command::exec_command, parser::parse_command,
-    CacheCommand,
+    cache::*,
+    clear_terminal,
+    finder::{pick, FinderAction},
+    host::CacheHost,
+    os_command::{
+        capture_command, capture_script, exec_command, exec_script,
+        extract_args, set_session_env, ExecOptions, ExecOutput,
+    },
+    parser::parse_command,
+    script::run_script,
+    template::{fill, highlight, placeholders, Placeholder},
+    AliasCommand, CacheCommand, DescribeSort,
 };
 
 const BACKUP_FILE_NAME: &str = "karsherdb.json";
 
+/// e.g 1536 gives "1.5kb"
+fn format_bytes(bytes: u64) -> String {
+    let mut size = bytes as f64;
+    for unit in ["b", "kb", "mb"] {
+        if size < 1024. {
+            return format!("{}{unit}", (size * 10.).round() / 10.);
+        }
+        size /= 1024.;
+    }
+    format!("{}gb", (size * 10.).round() / 10.)
+}
+
+/// e.g "3h ago" for a time in milliseconds since the epoch
+fn ago(millis: u64) -> String {
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+    let elapsed = now.saturating_sub(Duration::from_millis(millis));
+    let elapsed = duration_to_string(Duration::from_secs(elapsed.as_secs()));
+    match elapsed.split_whitespace().next() {
+        Some("0ms") | None => "just now".to_string(),
+        Some(unit) => format!("{unit} ago"),
+    }
+}
+
+/// remaining lifetime, to the second
+fn expires_in(ttl: Option<Duration>) -> String {
+    ttl.map(|ttl| {
+        let ttl = Duration::from_secs(ttl.as_secs().max(1));
+        format!(" (expires in {})", LightMagenta.paint(duration_to_string(ttl)))
+    })
+    .unwrap_or_default()
+}
+
+fn prompt_placeholder(placeholder: &Placeholder) -> Option<String> {
+    crate::editor::prompt(&format!(
+        "{}: ",
+        LightCyan.bold().paint(placeholder.name)
+    ))
+}
+
+/// keep the exit code in the script context, with stdout and stderr when
+/// the command ran
+fn set_last_status(
+    script_context: &mut BTreeMap<String, Primitive>,
+    code: i32,
+    output: Option<&ExecOutput>,
+) {
+    if code != 0 {
+        eprintln!("{}", Red.bold().paint(format!("[exit {code}]")));
+    }
+    let mut last_output =
+        BTreeMap::from([("code".to_string(), Primitive::Int(code as i128))]);
+    if let Some(output) = output {
+        for (name, out) in
+            [("stdout", &output.stdout), ("stderr", &output.stderr)]
+        {
+            last_output
+                .insert(name.to_string(), Primitive::String(out.to_string()));
+        }
+    }
+    script_context
+        .insert(LAST_STATUS.to_string(), Primitive::Int(code as i128));
+    script_context.insert(LAST_OUTPUT.to_string(), Primitive::Map(last_output));
+}
+
+/// run a value as an OS command, its placeholders are filled from the args.
+/// a script is evaluated with the args bound as variables. with capture, the
+/// output is stored in that variable instead of printed.
+/// a script sees the current cache like the scripts typed in the repl
+fn exec_value(
+    db: &mut impl DbOp<String, String>,
+    script_context: &mut BTreeMap<String, Primitive>,
+    current_cache: &mut String,
+    cache: &str,
+    key: &str,
+    args: Option<&str>,
+    capture: Option<&str>,
+) -> anyhow::Result<()> {
+    if let Some(var) = capture {
+        if check_reserved_keyword(&[var]) {
+            eprintln!("{var} is a reserved keyword");
+            return Ok(());
+        }
+    }
+    if let Some(value) = get_value(db, cache, key) {
+        if get_kind(db, cache, key) == Some(EntryKind::Script) {
+            let args = args
+                .and_then(|a| extract_args(a).ok())
+                .map(|(_, a)| a)
+                .unwrap_or_default();
+            let mut host = CacheHost::new(db, current_cache);
+            let output =
+                match run_script(&value, &args, script_context, &mut host) {
+                    Ok(result) => {
+                        let stdout = format!("{result}\n");
+                        match capture {
+                            Some(var) => {
+                                script_context.insert(var.to_string(), result);
+                            }
+                            None => print!("{stdout}"),
+                        }
+                        ExecOutput { stdout, stderr: String::new(), code: 0 }
+                    }
+                    Err(e) => {
+                        eprintln!("{key} failed: {}", Red.paint(e.to_string()));
+                        ExecOutput {
+                            stdout: String::new(),
+                            stderr: format!("{e}\n"),
+                            code: 1,
+                        }
+                    }
+                };
+            set_last_status(script_context, output.code, Some(&output));
+            record_exec(db, cache, key);
+            return Ok(());
+        }
+        let (command, args) = if placeholders(&value).is_empty() {
+            (value, args.map(String::from))
+        } else {
+            let args = args
+                .and_then(|a| extract_args(a).ok())
+                .map(|(_, a)| a)
+                .unwrap_or_default();
+            match fill(&value, &args, prompt_placeholder) {
+                Some(filled) => filled,
+                None => {
+                    eprintln!("{key} cancelled");
+                    return Ok(());
+                }
+            }
+        };
+        let script_args = args
+            .as_deref()
+            .and_then(|a| extract_args(a).ok())
+            .map(|(_, a)| a)
+            .unwrap_or_default();
+        if let Some(var) = capture {
+            let output = if command.contains('\n') {
+                capture_script(&command, &script_args, &ExecOptions::default())
+            } else {
+                let command = match &args {
+                    Some(args) => format!("{command} {args}"),
+                    None => command,
+                };
+                capture_command(&command, &ExecOptions::default())
+            };
+            let output = match output {
+                Ok(output) => output,
+                Err(e) => {
+                    eprintln!("{key} failed: {}", Red.paint(e.to_string()));
+                    set_last_status(script_context, 127, None);
+                    return Ok(());
+                }
+            };
+            let stdout = output.stdout.trim_end_matches('\n');
+            println!(
+                "captured {} line(s) into {}",
+                stdout.lines().count(),
+                LightCyan.paint(var)
+            );
+            script_context
+                .insert(var.to_string(), Primitive::String(stdout.to_string()));
+            set_last_status(script_context, output.code, Some(&output));
+        } else {
+            let output = if command.contains('\n') {
+                Some(exec_script(&command, &script_args)?)
+            } else {
+                exec_command(&command, &args.as_deref())
+                    .map_err(|e| anyhow::Error::msg(e.to_string()))?
+                    .1
+            };
+            match output {
+                Some(output) => {
+                    set_last_status(script_context, output.code, Some(&output))
+                }
+                None => set_last_status(script_context, 127, None),
+            }
+        }
+        record_exec(db, cache, key);
+    } else if !key.trim().is_empty() {
+        println!("{key} not found");
+    }
+    Ok(())
+}
+
+/// change a value in the editor, keeping its names
+fn edit_value(
+    db: &mut impl DbOp<String, String>,
+    cache: &str,
+    key: &str,
+) -> anyhow::Result<()> {
+    let (keys, value) = match names(db, cache, key).zip(db.get_value(key)) {
+        Some(found) => found,
+        None => {
+            println!("{key} not found");
+            return Ok(());
+        }
+    };
+    let new_value = open_in_editor(&value)?;
+    if new_value.trim().is_empty() || new_value == value {
+        println!("{key} unchanged");
+    } else if replace_value(db, cache, key, &new_value).is_some() {
+        println!(
+            "updated {} to {}",
+            Red.paint(keys.join(", ")),
+            Yellow.paint(new_value)
+        );
+    }
+    Ok(())
+}
+
+/// search results as a table, the selected one is executed
+fn select_search_result(
+    db: &mut impl DbOp<String, String>,
+    script_context: &mut BTreeMap<String, Primitive>,
+    current_cache: &mut String,
+    results: Vec<CacheValue>,
+) -> anyhow::Result<()> {
+    let rows = results
+        .iter()
+        .enumerate()
+        .map(|(i, r)| {
+            [
+                (i + 1).to_string(),
+                r.cache.clone(),
+                r.keys.join(", "),
+                r.value.replace('\n', " ↵ "),
+                r.tags.iter().cloned().collect::<Vec<_>>().join(", "),
+                r.description.clone().unwrap_or_default(),
+            ]
+        })
+        .collect::<Vec<_>>();
+    let header = ["#", "cache", "keys", "value", "tags", "description"];
+    let mut widths = header.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let styles = [LightGreen, LightBlue, Red, Yellow, LightMagenta, DarkGray];
+    println!(
+        "{}",
+        header
+            .iter()
+            .zip(widths)
+            .map(|(h, w)| Style::new()
+                .bold()
+                .paint(format!("{h:<w$}"))
+                .to_string())
+            .collect::<Vec<_>>()
+            .join(" | ")
+    );
+    for row in &rows {
+        println!(
+            "{}",
+            row.iter()
+                .zip(widths)
+                .zip(styles)
+                .map(|((cell, w), color)| color
+                    .paint(format!("{cell:<w$}"))
+                    .to_string())
+                .collect::<Vec<_>>()
+                .join(" | ")
+        );
+    }
+    let selected = crate::editor::prompt("exec # (enter to skip): ")
+        .and_then(|s| s.trim().parse::<usize>().ok())
+        .and_then(|i| results.get(i.checked_sub(1)?));
+    match selected {
+        Some(CacheValue { cache, keys, .. }) => exec_value(
+            db,
+            script_context,
+            current_cache,
+            cache,
+            &keys[0],
+            None,
+            None,
+        ),
+        None => Ok(()),
+    }
+}
+
 pub fn process_command(
     db: &mut impl DbOp<String, String>,
-    script_context: &BTreeMap<String, Primitive>,
+    script_context: &mut BTreeMap<String, Primitive>,
     current_cache: &mut String,
     line: &str,
 ) -> anyhow::Result<()> {
     match parse_command(line) {
         Ok((_, command)) => {
             match command {
-                CacheCommand::Put { aliases, value } => {
-                    if check_reserved_keyword(&aliases) {
+                CacheCommand::Put { options, value } => {
+                    let ttl = options.ttl;
+                    if check_reserved_keyword(&options.aliases) {
                         eprintln!("You cannot use a reserved keyword name as an alias.");
+                        return Ok(());
+                    }
+                    let value = match value {
+                        Some(value) => value.to_string(),
+                        None => open_in_editor("")?,
+                    };
+                    if value.trim().is_empty() {
+                        println!("nothing to put");
                     } else if let Some(key) =
-                        insert_value(db, current_cache, aliases, value)
+                        insert_value(db, current_cache, options, &value)
                     {
                         println!(
-                            "added {} with hash keys {}",
+                            "added {} with hash keys {}{}",
                             Yellow.paint(value),
-                            Red.paint(key)
+                            Red.paint(key),
+                            expires_in(ttl)
                         );
                     } else {
                         eprintln!("could not insert!");
@@ -48,25 +362,34 @@ pub fn process_command(
                             Red.paint(key.to_string())
                         );
                     } else {
-                        println!(
-                        "key {key} not found in current cache {current_cache}"
-                    );
+                        println!("key {key} not found in current cache {current_cache}");
                     }
                 }
+                CacheCommand::Edit(key) => edit_value(db, current_cache, key)?,
                 CacheCommand::Get(key) => {
                     if let Some(value) = get_value(db, current_cache, key) {
-                        println!("found '{}'", Yellow.paint(value));
+                        match get_kind(db, current_cache, key) {
+                            Some(EntryKind::Script) => println!(
+                                "found script '{}'",
+                                LightCyan.paint(value)
+                            ),
+                            _ => println!("found '{}'", highlight(&value)),
+                        }
                     } else {
                         println!("{key} not found");
                     }
                 }
-                CacheCommand::Exec { key, args } => {
-                    if let Some(value) = get_value(db, current_cache, key) {
-                        let _ = exec_command(&value, &args)
-                            .map_err(|e| anyhow::Error::msg(e.to_string()))?;
-                    } else if !key.trim().is_empty() {
-                        println!("{key} not found");
-                    }
+                CacheCommand::Exec { key, args, capture } => {
+                    let cache = current_cache.clone();
+                    exec_value(
+                        db,
+                        script_context,
+                        current_cache,
+                        &cache,
+                        key,
+                        args,
+                        capture,
+                    )?;
                 }
                 CacheCommand::Using(key) => {
                     if set_default_cache(db, key).is_some() {
@@ -128,13 +451,218 @@ pub fn process_command(
                         }
                     }
                 }
-                CacheCommand::Describe => {
-                    if let Some(values) = list_values(db, current_cache) {
-                        for (key, value) in values {
+                CacheCommand::Describe(sort) => {
+                    if let Some(mut values) =
+                        list_values_with_metadata(db, current_cache)
+                    {
+                        match sort {
+                            Some(DescribeSort::Used) => {
+                                values.sort_by_key(|(_, _, m)| {
+                                    Reverse((m.exec_count, m.last_used))
+                                })
+                            }
+                            Some(DescribeSort::Recent) => values
+                                .sort_by_key(|(_, _, m)| Reverse(m.last_used)),
+                            None => {}
+                        }
+                        for (key, value, metadata) in values {
+                            let usage = match sort {
+                                Some(DescribeSort::Used) => {
+                                    format!(
+                                        " run {} time(s)",
+                                        metadata.exec_count
+                                    )
+                                }
+                                Some(DescribeSort::Recent) => format!(
+                                    " used {}",
+                                    metadata
+                                        .last_used
+                                        .map_or("never".into(), ago)
+                                ),
+                                None => String::new(),
+                            };
+                            let tags = if metadata.tags.is_empty() {
+                                String::new()
+                            } else {
+                                format!(
+                                    " [{}]",
+                                    metadata
+                                        .tags
+                                        .iter()
+                                        .cloned()
+                                        .collect::<Vec<_>>()
+                                        .join(", ")
+                                )
+                            };
+                            let keys = db
+                                .names(&key)
+                                .map_or(key.clone(), |names| names.join(", "));
+                            let kind = match metadata.kind {
+                                EntryKind::Script => " (script)",
+                                EntryKind::Command => "",
+                            };
                             println!(
-                                ">> Key: {} => Value: '{}'",
-                                Red.paint(key),
-                                LightCyan.paint(value)
+                                ">> Key: {} => Value: '{}'{}{}{}{}{}",
+                                Red.paint(keys),
+                                LightCyan.paint(value),
+                                LightBlue.paint(kind),
+                                expires_in(db.ttl(&key)),
+                                LightGreen.paint(usage),
+                                LightMagenta.paint(tags),
+                                metadata
+                                    .description
+                                    .map(|d| DarkGray
+                                        .paint(format!(" # {d}"))
+                                        .to_string())
+                                    .unwrap_or_default()
+                            );
+                        }
+                    }
+                }
+                CacheCommand::Search(query) => {
+                    let results = search(db, query);
+                    if results.is_empty() {
+                        println!("nothing found for {}", Red.paint(query));
+                    } else {
+                        select_search_result(
+                            db,
+                            script_context,
+                            current_cache,
+                            results,
+                        )?;
+                    }
+                }
+                CacheCommand::Find => {
+                    if !stdin().is_tty() {
+                        eprintln!("find needs a terminal");
+                        return Ok(());
+                    }
+                    let values =
+                        list_by_value(db, current_cache).unwrap_or_default();
+                    if let Some((action, index)) = pick(&values)? {
+                        let CacheValue { keys, value, .. } = &values[index];
+                        let key = &keys[0];
+                        match action {
+                            FinderAction::Exec => exec_value(
+                                db,
+                                script_context,
+                                current_cache,
+                                &current_cache.clone(),
+                                key,
+                                None,
+                                None,
+                            )?,
+                            FinderAction::CopyToPrompt => set_next_input(value),
+                            FinderAction::Edit => {
+                                edit_value(db, current_cache, key)?
+                            }
+                            FinderAction::Delete => {
+                                if remove_value(db, current_cache, key)
+                                    .is_some()
+                                {
+                                    println!(
+                                        "removed {} with hash key(s) {}",
+                                        Yellow.paint(value),
+                                        Red.paint(keys.join(", "))
+                                    );
+                                }
+                            }
+                        }
+                    }
+                }
+                CacheCommand::Alias(AliasCommand::Add { key, aliases }) => {
+                    if check_reserved_keyword(&aliases) {
+                        eprintln!("You cannot use a reserved keyword name as an alias.");
+                        return Ok(());
+                    }
+                    if names(db, current_cache, key).is_none() {
+                        println!("{key} not found");
+                        return Ok(());
+                    }
+                    for alias in aliases {
+                        if add_alias(db, current_cache, key, alias).is_none() {
+                            eprintln!("{} is already used", Red.paint(alias));
+                        }
+                    }
+                    if let Some(names) = names(db, current_cache, key) {
+                        println!(
+                            "keys of {key}: {}",
+                            Red.paint(names.join(", "))
+                        );
+                    }
+                }
+                CacheCommand::Alias(AliasCommand::Remove(alias)) => {
+                    match names(db, current_cache, alias) {
+                        None => println!("{alias} not found"),
+                        Some(names) if names.len() == 1 => eprintln!(
+                            "{alias} is the only key of its value, use `del {alias}` to remove it"
+                        ),
+                        Some(_) => {
+                            remove_alias(db, current_cache, alias);
+                            println!("removed alias {}", Red.paint(alias));
+                        }
+                    }
+                }
+                CacheCommand::Alias(AliasCommand::Rename {
+                    alias,
+                    new_alias,
+                }) => {
+                    if check_reserved_keyword(&[new_alias]) {
+                        eprintln!("You cannot use a reserved keyword name as an alias.");
+                    } else if names(db, current_cache, alias).is_none() {
+                        println!("{alias} not found");
+                    } else if rename_alias(db, current_cache, alias, new_alias)
+                        .is_none()
+                    {
+                        eprintln!("{} is already used", Red.paint(new_alias));
+                    } else {
+                        println!(
+                            "renamed {} to {}",
+                            Red.paint(alias),
+                            Red.paint(new_alias)
+                        );
+                    }
+                }
+                CacheCommand::Alias(AliasCommand::List(key)) => {
+                    let values = match key {
+                        // names opens the cache
+                        Some(key) => match names(db, current_cache, key)
+                            .zip(db.get_value(key))
+                        {
+                            Some(value) => vec![value],
+                            None => {
+                                println!("{key} not found");
+                                return Ok(());
+                            }
+                        },
+                        None => list_by_value(db, current_cache)
+                            .unwrap_or_default()
+                            .into_iter()
+                            .map(|v| (v.keys, v.value))
+                            .collect(),
+                    };
+                    for (names, value) in values {
+                        println!(
+                            ">> {} => '{}'",
+                            Red.paint(names.join(", ")),
+                            LightCyan.paint(value)
+                        );
+                    }
+                }
+                CacheCommand::Stats => {
+                    for cache in get_cache_names(db) {
+                        let values = most_used(db, &cache).unwrap_or_default();
+                        if values.is_empty() {
+                            continue;
+                        }
+                        println!(">> {}", LightBlue.bold().paint(&cache));
+                        for (value, keys, count) in values.into_iter().take(10)
+                        {
+                            println!(
+                                "   {} {} ({})",
+                                LightGreen.paint(format!("{count:>5}")),
+                                Yellow.paint(value),
+                                Red.paint(keys.join(", "))
                             );
                         }
                     }
@@ -193,6 +721,116 @@ pub fn process_command(
                     let json = serde_json::to_string_pretty(&script_context)?;
                     println!("{json}")
                 }
+                CacheCommand::SaveScriptContext => {
+                    if save_script_context(db, script_context).is_some() {
+                        println!(
+                            "saved {} variable(s)",
+                            Red.paint(script_context.len().to_string())
+                        );
+                    } else {
+                        eprintln!("could not save script context!");
+                    }
+                }
+                CacheCommand::Export { key, value } => {
+                    set_session_env(key, value);
+                    println!(
+                        "exported {}={}",
+                        Red.paint(key),
+                        Yellow.paint(value)
+                    );
+                }
+                CacheCommand::Ttl(None) => {
+                    match get_default_ttl(db, current_cache) {
+                        Some(ttl) => println!(
+                            "values put in {} expire after {}",
+                            LightCyan.paint(current_cache.as_str()),
+                            Yellow.paint(duration_to_string(ttl))
+                        ),
+                        None => println!(
+                            "values put in {} never expire",
+                            LightCyan.paint(current_cache.as_str())
+                        ),
+                    }
+                }
+                CacheCommand::Ttl(Some(ttl)) => {
+                    let ttl = match ttl {
+                        "off" | "0" => None,
+                        ttl => match duration_from_str(ttl) {
+                            Some(ttl) => Some(ttl),
+                            None => {
+                                eprintln!(
+                                    "invalid duration {}. e.g `ttl 1h30m`",
+                                    Red.paint(ttl)
+                                );
+                                return Ok(());
+                            }
+                        },
+                    };
+                    if set_default_ttl(db, current_cache, ttl).is_some() {
+                        println!(
+                            "default ttl of {}: {}",
+                            LightCyan.paint(current_cache.as_str()),
+                            Yellow.paint(
+                                ttl.map_or(
+                                    "off".to_string(),
+                                    duration_to_string
+                                )
+                            )
+                        );
+                    }
+                }
+                CacheCommand::Capacity(None) => {
+                    if let Some((capacity, len, size)) =
+                        get_capacity(db, current_cache)
+                    {
+                        let limit = |max: Option<String>| {
+                            max.map(|m| format!("/{m}")).unwrap_or_default()
+                        };
+                        println!(
+                            "{}: {}{} value(s), {}{}, {}",
+                            LightCyan.paint(current_cache.as_str()),
+                            len,
+                            limit(
+                                capacity
+                                    .and_then(|c| c.max_entries)
+                                    .map(|m| m.to_string())
+                            ),
+                            format_bytes(size),
+                            limit(
+                                capacity
+                                    .and_then(|c| c.max_bytes)
+                                    .map(format_bytes)
+                            ),
+                            Yellow.paint(
+                                capacity.map_or("no limit".to_string(), |c| {
+                                    format!("evict {}", c.policy)
+                                })
+                            )
+                        );
+                    }
+                }
+                CacheCommand::Capacity(Some(capacity)) => {
+                    if let Some(evicted) =
+                        set_capacity(db, current_cache, capacity)
+                    {
+                        println!(
+                            "capacity of {} updated, {} value(s) evicted",
+                            LightCyan.paint(current_cache.as_str()),
+                            Red.paint(evicted.to_string())
+                        );
+                    }
+                }
+                CacheCommand::LoadScriptContext => {
+                    if let Some(saved) = load_script_context(db) {
+                        println!(
+                            "loaded {} variable(s)",
+                            Red.paint(saved.len().to_string())
+                        );
+                        script_context.extend(saved);
+                    } else {
+                        eprintln!("could not load script context!");
+                    }
+                }
             }
         }
         Err(e) => match e {
@@ -207,3 +845,40 @@ pub fn process_command(
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        db::InMemoryDb,
+        karshscript::{
+            constants::{LAST_OUTPUT, LAST_STATUS},
+            Primitive,
+        },
+        prelude::BTreeMap,
+    };
+
+    use super::process_command;
+
+    #[test]
+    fn test_exec_last_output() {
+        let mut db: InMemoryDb<String, String> = InMemoryDb::default();
+        let mut ctx = BTreeMap::new();
+        let mut current_cache = "DEFAULT".to_string();
+        let mut run = |line: &str| {
+            process_command(&mut db, &mut ctx, &mut current_cache, line)
+                .unwrap()
+        };
+        run("put -a out echo hello");
+        run("exec out");
+        run("put -a err sh -c \"echo oops 1>&2; exit 3\"");
+        run("exec err");
+
+        assert_eq!(Some(&Primitive::Int(3)), ctx.get(LAST_STATUS));
+        let expected = BTreeMap::from([
+            ("code".to_string(), Primitive::Int(3)),
+            ("stdout".to_string(), Primitive::String(String::new())),
+            ("stderr".to_string(), Primitive::String("oops\n".to_string())),
+        ]);
+        assert_eq!(Some(&Primitive::Map(expected)), ctx.get(LAST_OUTPUT));
+    }
+}
@@ -0,0 +1,126 @@
+use crate::{
+    karshscript::{compute_with_host, Host, Primitive},
+    prelude::*,
+};
+
+/// e.g `10` is an int, `1.5` a double, `true` a bool, the rest a string
+fn to_primitive(arg: &str) -> Primitive {
+    if let Ok(i) = arg.parse::<i128>() {
+        Primitive::Int(i)
+    } else if let Ok(d) = arg.parse::<f64>() {
+        Primitive::Double(d)
+    } else if let Ok(b) = arg.parse::<bool>() {
+        Primitive::Bool(b)
+    } else {
+        Primitive::String(arg.to_string())
+    }
+}
+
+/// evaluate a script put with `--script` in the script context.
+/// `name=value` arguments are bound to `name`, the others to
+/// `arg1`, `arg2`... and all of them to `args`. the variables shadowed by
+/// the arguments are restored after
+pub fn run_script(
+    script: &str,
+    args: &[&str],
+    script_context: &mut BTreeMap<String, Primitive>,
+    host: &mut dyn Host,
+) -> anyhow::Result<Primitive> {
+    let mut bound = BTreeMap::new();
+    let mut positional = vec![];
+    for arg in args {
+        match arg.split_once('=') {
+            Some((name, value))
+                if !name.is_empty()
+                    && name
+                        .chars()
+                        .all(|c| c.is_alphanumeric() || c == '_') =>
+            {
+                bound.insert(name.to_string(), to_primitive(value));
+            }
+            _ => {
+                positional.push(to_primitive(arg));
+                bound.insert(
+                    format!("arg{}", positional.len()),
+                    positional.last().cloned().unwrap_or(Primitive::Unit),
+                );
+            }
+        }
+    }
+    bound.insert("args".to_string(), Primitive::Array(positional));
+
+    let shadowed = bound
+        .into_iter()
+        .map(|(name, value)| {
+            let previous = script_context.insert(name.clone(), value);
+            (name, previous)
+        })
+        .collect::<Vec<_>>();
+    let result = compute_with_host(script, script_context, host);
+    for (name, previous) in shadowed {
+        match previous {
+            Some(previous) => script_context.insert(name, previous),
+            None => script_context.remove(&name),
+        };
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cache_command::CacheHost;
+    use crate::db::InMemoryDb;
+    use crate::karshscript::Primitive;
+    use crate::prelude::*;
+
+    use super::run_script;
+
+    #[test]
+    fn test_run_script() {
+        let fib = r#"
+        a = 0
+        b = 1
+        while (n > 0) {
+            n = n - 1
+            c = a + b
+            a = b
+            b = c
+        }
+        a
+        "#;
+        let mut ctx = BTreeMap::from([("n".to_string(), Primitive::Int(3))]);
+        assert_eq!(
+            Primitive::Int(55),
+            run_script(fib, &["n=10"], &mut ctx, &mut ()).unwrap()
+        );
+        // the argument doesn't replace the variable of the context
+        assert_eq!(Some(&Primitive::Int(3)), ctx.get("n"));
+        // the variables of the script are kept
+        assert_eq!(Some(&Primitive::Int(55)), ctx.get("a"));
+
+        assert_eq!(
+            Primitive::String("hello world 2".to_string()),
+            run_script(
+                r#"arg1 + " " + who + " " + length(args)"#,
+                &["hello", "who=world", "2.5"],
+                &mut ctx,
+                &mut ()
+            )
+            .unwrap()
+        );
+        assert!(!ctx.contains_key("arg1") && !ctx.contains_key("args"));
+        assert!(run_script("x = (", &[], &mut ctx, &mut ()).is_err());
+
+        // the cache is reachable like in the repl
+        let mut db: InMemoryDb<String, String> = InMemoryDb::default();
+        let mut current_cache = "DEFAULT".to_string();
+        let mut host = CacheHost::new(&mut db, &mut current_cache);
+        run_script(r#"cache_put("ll", "ls -la")"#, &[], &mut ctx, &mut host)
+            .unwrap();
+        assert_eq!(
+            Primitive::String("ls -la".to_string()),
+            run_script(r#"cache_get(arg1)"#, &["ll"], &mut ctx, &mut host)
+                .unwrap()
+        );
+    }
+}
@@ -0,0 +1,228 @@
+use crate::prelude::{colors::*, *};
+
+/// e.g `{1}`, `{host}` or `{port:22}` in `ssh {1}@{host} -p {port:22}`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Placeholder<'a> {
+    /// position (starting at 1) or name
+    pub name: &'a str,
+    pub default: Option<&'a str>,
+}
+
+enum Part<'a> {
+    Text(&'a str),
+    Placeholder(Placeholder<'a>),
+}
+
+fn parse_placeholder(s: &str) -> Res<Placeholder> {
+    let name = alt((
+        take_while1(|c: char| c.is_ascii_digit()),
+        verify(
+            take_while1(|c: char| c.is_alphanumeric() || c == '_'),
+            |s: &str| s.starts_with(|c: char| c.is_alphabetic() || c == '_'),
+        ),
+    ));
+    map(
+        delimited(
+            tag("{"),
+            pair(
+                name,
+                opt(preceded(tag(":"), take_while(|c| c != '{' && c != '}'))),
+            ),
+            tag("}"),
+        ),
+        |(name, default)| Placeholder { name, default },
+    )(s)
+}
+
+/// anything between braces that isn't a placeholder is kept as is
+/// e.g `${HOME}` or `awk '{print $1}'`
+fn parts(template: &str) -> Vec<Part> {
+    let mut parts = vec![];
+    let mut text_start = 0;
+    let mut pos = 0;
+    while let Some(offset) = template[pos..].find('{') {
+        let start = pos + offset;
+        pos = start + 1;
+        if template[..start].ends_with('$') {
+            continue;
+        }
+        if let Ok((rest, placeholder)) = parse_placeholder(&template[start..]) {
+            if text_start < start {
+                parts.push(Part::Text(&template[text_start..start]));
+            }
+            parts.push(Part::Placeholder(placeholder));
+            pos = template.len() - rest.len();
+            text_start = pos;
+        }
+    }
+    if text_start < template.len() {
+        parts.push(Part::Text(&template[text_start..]));
+    }
+    parts
+}
+
+/// placeholders of a template, without duplicates
+pub fn placeholders(template: &str) -> Vec<Placeholder> {
+    let mut placeholders: Vec<Placeholder> = vec![];
+    for part in parts(template) {
+        if let Part::Placeholder(p) = part {
+            if !placeholders.iter().any(|other| other.name == p.name) {
+                placeholders.push(p);
+            }
+        }
+    }
+    placeholders
+}
+
+/// so a value with spaces stays one argument
+fn quote(arg: &str) -> String {
+    if arg.contains(char::is_whitespace) {
+        format!("\"{arg}\"")
+    } else {
+        arg.to_string()
+    }
+}
+
+/// fill the placeholders with the arguments of exec e.g
+/// `exec ssh root host=10.0.0.1`. `name=value` fills a named placeholder,
+/// the other arguments fill the positional ones in order.
+/// the placeholders left use their default or are asked with prompt.
+/// returns the command and the arguments that were not used,
+/// or None if the prompt was cancelled
+pub fn fill(
+    template: &str,
+    args: &[&str],
+    mut prompt: impl FnMut(&Placeholder) -> Option<String>,
+) -> Option<(String, Option<String>)> {
+    let placeholders = placeholders(template);
+    let mut values: BTreeMap<&str, String> = BTreeMap::new();
+    let mut positional = vec![];
+    let mut unused = vec![];
+    for arg in args {
+        match arg.split_once('=') {
+            Some((name, value))
+                if placeholders.iter().any(|p| p.name == name) =>
+            {
+                values.insert(name, value.to_string());
+            }
+            _ => positional.push(*arg),
+        }
+    }
+    let mut positional = positional.into_iter();
+    for position in 1.. {
+        let name = position.to_string();
+        match placeholders.iter().find(|p| p.name == name) {
+            Some(p) => match positional.next() {
+                Some(arg) => {
+                    values.entry(p.name).or_insert_with(|| arg.to_string());
+                }
+                None => break,
+            },
+            None => break,
+        }
+    }
+    unused.extend(positional);
+
+    for p in &placeholders {
+        if values.contains_key(p.name) {
+            continue;
+        }
+        let value = match p.default {
+            Some(default) => default.to_string(),
+            None => prompt(p)?,
+        };
+        values.insert(p.name, value);
+    }
+
+    // a value already quoted in the template e.g `echo "{1}"` is kept as is
+    let mut command = String::new();
+    for part in parts(template) {
+        match part {
+            Part::Text(text) => command.push_str(text),
+            Part::Placeholder(p) if command.ends_with(['"', '\'']) => {
+                command.push_str(&values[p.name])
+            }
+            Part::Placeholder(p) => command.push_str(&quote(&values[p.name])),
+        }
+    }
+    let unused = unused.into_iter().map(quote).collect::<Vec<_>>();
+    Some((command, Some(unused.join(" ")).filter(|u| !u.is_empty())))
+}
+
+/// template with its placeholders highlighted
+pub fn highlight(template: &str) -> String {
+    parts(template)
+        .into_iter()
+        .map(|part| match part {
+            Part::Text(text) => Yellow.paint(text).to_string(),
+            Part::Placeholder(Placeholder { name, default: None }) => {
+                LightCyan.bold().paint(format!("{{{name}}}")).to_string()
+            }
+            Part::Placeholder(Placeholder { name, default: Some(default) }) => {
+                LightCyan
+                    .bold()
+                    .paint(format!("{{{name}:{default}}}"))
+                    .to_string()
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{fill, placeholders, Placeholder};
+
+    #[test]
+    fn test_placeholders() {
+        assert_eq!(
+            vec![
+                Placeholder { name: "1", default: None },
+                Placeholder { name: "host", default: None },
+                Placeholder { name: "port", default: Some("22") },
+            ],
+            placeholders("ssh {1}@{host} -p {port:22} # {host}")
+        );
+        assert!(placeholders("echo ${HOME} && awk '{print $1}' {}").is_empty());
+    }
+
+    #[test]
+    fn test_fill() {
+        let no_prompt = |p: &Placeholder| panic!("unexpected prompt {p:?}");
+        assert_eq!(
+            Some(("ssh root@10.0.0.1 -p 22".to_string(), None)),
+            fill(
+                "ssh {1}@{host} -p {port:22}",
+                &["root", "host=10.0.0.1"],
+                no_prompt
+            )
+        );
+        assert_eq!(
+            Some((
+                "docker exec -it web bash".to_string(),
+                Some("-u \"a b\" FOO=bar".to_string())
+            )),
+            fill(
+                "docker exec -it {container} {1}",
+                &["container=web", "bash", "-u", "a b", "FOO=bar"],
+                no_prompt
+            )
+        );
+
+        let mut asked = vec![];
+        assert_eq!(
+            Some(("ssh admin@db -p 2222".to_string(), None)),
+            fill("ssh {user}@{host} -p {port:22}", &["port=2222"], |p| {
+                asked.push(p.name.to_string());
+                Some(if p.name == "user" { "admin" } else { "db" }.to_string())
+            })
+        );
+        assert_eq!(vec!["user", "host"], asked);
+
+        assert_eq!(None, fill("ssh {host}", &[], |_| None));
+
+        assert_eq!(
+            Some((r#"ls "a b" && echo "c d""#.to_string(), None)),
+            fill(r#"ls {dir} && echo "{1}""#, &["dir=a b", "c d"], no_prompt)
+        );
+    }
+}
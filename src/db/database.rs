@@ -1,11 +1,10 @@
-use std::path::Path;
+use std::{io::Read, path::Path};
 
 use crate::{db::FileDbConfig, prelude::*};
-use anyhow::Context;
 use log::debug;
 use serde::de::DeserializeOwned;
 
-use super::{FileDb, FileLock, InMemoryDb, Key, Value};
+use super::{storage, FileDb, FileLock, InMemoryDb, Key, Value};
 
 fn get_default_db_path() -> Option<Box<Path>> {
     let mut db_dir = dirs::data_dir()?;
@@ -85,7 +84,15 @@ where
         if config.in_memory {
             return Ok(Db::InMemory(Default::default()));
         }
-        let path = config.path.context("not in memory but path empty")?;
+        let path = match config.path {
+            Some(path) => path,
+            None if config.fall_back_in_memory => {
+                return Self::in_memory_fallback(anyhow::anyhow!(
+                    "no default db path"
+                ));
+            }
+            None => anyhow::bail!("not in memory but path empty"),
+        };
 
         let file_lock = FileLock::open(path.as_ref());
         match file_lock {
@@ -93,10 +100,13 @@ where
             Err(e) => Self::in_memory_fallback(e),
             Ok(file_lock) => {
                 let inner = match file_lock.read() {
-                    Ok(reader) => {
-                        match bincode::deserialize_from::<_, InMemoryDb<K, V>>(
-                            reader,
-                        ) {
+                    Ok(mut reader) => {
+                        let mut bytes = vec![];
+                        match reader
+                            .read_to_end(&mut bytes)
+                            .map_err(anyhow::Error::new)
+                            .and_then(|_| storage::decode(&bytes))
+                        {
                             Ok(inner_db) => Arc::new(Mutex::new(inner_db)),
                             Err(e) => {
                                 eprintln!(
@@ -1,5 +1,5 @@
 use std::{
-    sync::mpsc::{Receiver, Sender},
+    sync::mpsc::{Receiver, RecvTimeoutError, Sender},
     thread::JoinHandle,
     vec,
 };
@@ -8,7 +8,12 @@ use serde::de::DeserializeOwned;
 
 use crate::prelude::*;
 
-use super::{DbOp, FileLock, InMemoryDb, Key, Op, Value};
+use super::{
+    storage, Capacity, DbOp, FileLock, InMemoryDb, Key, Metadata, Op, Value,
+};
+
+/// how often the expired entries are removed
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
 
 pub(super) enum Notify {
     Update,
@@ -123,6 +128,41 @@ impl<K: Key, V: Value> DbOp<K, V> for FileDb<K, V> {
         self.update(|mut guard| guard.apply_batch(batch))
     }
 
+    fn get_default_ttl(&self, tree_name: &str) -> Option<Duration> {
+        let guard = self.get_guard()?;
+        guard.get_default_ttl(tree_name)
+    }
+
+    fn set_default_ttl(
+        &mut self,
+        tree_name: &str,
+        ttl: Option<Duration>,
+    ) -> Option<()> {
+        self.update(|mut guard| guard.set_default_ttl(tree_name, ttl))
+    }
+
+    fn evict_expired(&mut self) -> usize {
+        self.update(|mut guard| Some(guard.evict_expired())).unwrap_or(0)
+    }
+
+    fn get_capacity(&self, tree_name: &str) -> Option<Capacity> {
+        let guard = self.get_guard()?;
+        guard.get_capacity(tree_name)
+    }
+
+    fn set_capacity(
+        &mut self,
+        tree_name: &str,
+        capacity: Option<Capacity>,
+    ) -> Option<usize> {
+        self.update(|mut guard| guard.set_capacity(tree_name, capacity))
+    }
+
+    fn size_in_bytes(&self, tree_name: &str) -> Option<u64> {
+        let guard = self.get_guard()?;
+        guard.size_in_bytes(tree_name)
+    }
+
     fn apply_tree(
         &mut self,
         tree_name: &str,
@@ -142,6 +182,19 @@ impl<K: Key, V: Value> Op<K, V> for FileDb<K, V> {
         self.update(move |mut guard| guard.insert(k, v))
     }
 
+    fn insert_with_ttl(
+        &mut self,
+        k: impl Into<K>,
+        v: impl Into<V>,
+        ttl: Option<Duration>,
+    ) -> Option<V> {
+        self.update(move |mut guard| guard.insert_with_ttl(k, v, ttl))
+    }
+
+    fn replace(&mut self, k: impl Into<K>, v: impl Into<V>) -> Option<V> {
+        self.update(move |mut guard| guard.replace(k, v))
+    }
+
     fn remove(&mut self, k: impl Into<K>) -> Option<V> {
         self.update(move |mut guard| guard.remove(k))
     }
@@ -158,6 +211,52 @@ impl<K: Key, V: Value> Op<K, V> for FileDb<K, V> {
         guard.contains(k)
     }
 
+    fn is_expired(&self, k: &K) -> bool {
+        self.get_guard().filter(|guard| guard.is_expired(k)).is_some()
+    }
+
+    fn ttl(&self, k: &K) -> Option<Duration> {
+        let guard = self.get_guard()?;
+        guard.ttl(k)
+    }
+
+    /// not flushed, saved with the next update
+    fn record_use(&mut self, k: &K) {
+        if let Some(mut guard) = self.get_guard() {
+            guard.record_use(k);
+        }
+    }
+
+    fn metadata(&self, k: &K) -> Option<Metadata> {
+        let guard = self.get_guard()?;
+        guard.metadata(k)
+    }
+
+    fn update_metadata(
+        &mut self,
+        k: &K,
+        update: impl FnOnce(&mut Metadata),
+    ) -> Option<()> {
+        self.update(move |mut guard| guard.update_metadata(k, update))
+    }
+
+    fn names(&self, k: &K) -> Option<Vec<K>> {
+        let guard = self.get_guard()?;
+        guard.names(k)
+    }
+
+    fn add_alias(&mut self, k: &K, alias: K) -> Option<()> {
+        self.update(move |mut guard| guard.add_alias(k, alias))
+    }
+
+    fn remove_alias(&mut self, alias: &K) -> Option<()> {
+        self.update(move |mut guard| guard.remove_alias(alias))
+    }
+
+    fn rename_alias(&mut self, alias: &K, new_alias: K) -> Option<()> {
+        self.update(move |mut guard| guard.rename_alias(alias, new_alias))
+    }
+
     fn len(&self) -> Option<usize> {
         let guard = self.get_guard()?;
         guard.len()
@@ -192,7 +291,7 @@ where
         trace!("syncing");
         let db =
             inner_db.lock().map_err(|e| anyhow::Error::msg(e.to_string()))?;
-        let bytes = bincode::serialize(&*db)?;
+        let bytes = storage::encode(&*db)?;
         drop(db); // try to release the lock before writing to the file
         file_lock.write(&bytes)?;
         trace!("syncing done");
@@ -208,9 +307,9 @@ where
         let handle = std::thread::spawn(move || {
             debug!("start syncing");
 
-            for event in receiver.iter() {
-                match event {
-                    Notify::Update => {
+            loop {
+                match receiver.recv_timeout(SWEEP_INTERVAL) {
+                    Ok(Notify::Update) => {
                         debug!("receive update!");
                         if let Err(e) =
                             Self::__flush(Arc::clone(&clone), &file_lock)
@@ -220,10 +319,25 @@ where
                             trace!("sync done");
                         }
                     }
-                    Notify::Stop => {
+                    Ok(Notify::Stop) | Err(RecvTimeoutError::Disconnected) => {
                         debug!("receive stop!");
                         break;
                     }
+                    Err(RecvTimeoutError::Timeout) => {
+                        let evicted = clone
+                            .lock()
+                            .map(|mut db| db.evict_expired())
+                            .unwrap_or_default();
+                        if evicted == 0 {
+                            continue;
+                        }
+                        debug!("{evicted} expired entries evicted");
+                        if let Err(e) =
+                            Self::__flush(Arc::clone(&clone), &file_lock)
+                        {
+                            error!("could not flush db. Err: '{e}'.");
+                        }
+                    }
                 }
             }
 
@@ -1,16 +1,17 @@
 use crate::prelude::*;
 
 pub const DEFAULT_TREE: &str = "__karsher_default";
+pub const SCRIPT_CONTEXT_TREE: &str = "__karsher_script_ctx";
 
-use super::{tree::Tree, DbOp, Key, Op, Value};
+use super::{tree::Tree, Capacity, DbOp, Key, Metadata, Op, Value};
 
 type InnerMap<K, V> = BTreeMap<String, Tree<K, V>>;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct InMemoryDb<K: Key, V: Value> {
-    trees: InnerMap<K, V>,
-    default_tree: String,
-    current_tree: Option<String>,
+    pub(super) trees: InnerMap<K, V>,
+    pub(super) default_tree: String,
+    pub(super) current_tree: Option<String>,
 }
 
 impl<K: Key + Clone, V: Value + Clone> InMemoryDb<K, V> {
@@ -38,6 +39,19 @@ impl<K: Key + Clone, V: Value> Op<K, V> for InMemoryDb<K, V> {
         self.apply_to_current_tree(move |tree| tree.insert(k, v))
     }
 
+    fn insert_with_ttl(
+        &mut self,
+        k: impl Into<K>,
+        v: impl Into<V>,
+        ttl: Option<Duration>,
+    ) -> Option<V> {
+        self.apply_to_current_tree(move |tree| tree.insert_with_ttl(k, v, ttl))
+    }
+
+    fn replace(&mut self, k: impl Into<K>, v: impl Into<V>) -> Option<V> {
+        self.apply_to_current_tree(move |tree| tree.replace(k, v))
+    }
+
     fn remove(&mut self, k: impl Into<K>) -> Option<V> {
         self.apply_to_current_tree(move |tree| tree.remove(k))
     }
@@ -56,6 +70,63 @@ impl<K: Key + Clone, V: Value> Op<K, V> for InMemoryDb<K, V> {
         tree.contains(k)
     }
 
+    fn is_expired(&self, k: &K) -> bool {
+        self.get_current_tree()
+            .and_then(|current_tree| self.trees.get(&current_tree))
+            .filter(|tree| tree.is_expired(k))
+            .is_some()
+    }
+
+    fn ttl(&self, k: &K) -> Option<Duration> {
+        let current_tree = self.get_current_tree()?;
+
+        let tree = self.trees.get(&current_tree)?;
+        tree.ttl(k)
+    }
+
+    fn record_use(&mut self, k: &K) {
+        self.apply_to_current_tree(|tree| {
+            tree.record_use(k);
+            Some(())
+        });
+    }
+
+    fn metadata(&self, k: &K) -> Option<Metadata> {
+        let current_tree = self.get_current_tree()?;
+
+        let tree = self.trees.get(&current_tree)?;
+        tree.metadata(k)
+    }
+
+    fn update_metadata(
+        &mut self,
+        k: &K,
+        update: impl FnOnce(&mut Metadata),
+    ) -> Option<()> {
+        self.apply_to_current_tree(move |tree| tree.update_metadata(k, update))
+    }
+
+    fn names(&self, k: &K) -> Option<Vec<K>> {
+        let current_tree = self.get_current_tree()?;
+
+        let tree = self.trees.get(&current_tree)?;
+        tree.names(k)
+    }
+
+    fn add_alias(&mut self, k: &K, alias: K) -> Option<()> {
+        self.apply_to_current_tree(move |tree| tree.add_alias(k, alias))
+    }
+
+    fn remove_alias(&mut self, alias: &K) -> Option<()> {
+        self.apply_to_current_tree(move |tree| tree.remove_alias(alias))
+    }
+
+    fn rename_alias(&mut self, alias: &K, new_alias: K) -> Option<()> {
+        self.apply_to_current_tree(move |tree| {
+            tree.rename_alias(alias, new_alias)
+        })
+    }
+
     fn len(&self) -> Option<usize> {
         let current_tree = self.get_current_tree()?;
 
@@ -147,7 +218,7 @@ impl<K: Key + Clone, V: Value + Clone> DbOp<K, V> for InMemoryDb<K, V> {
     ) -> Option<()> {
         let source = self.trees.remove(tree_name_source)?;
         let dest = self.trees.get_mut(tree_name_dest)?;
-        dest.extend(source.iter().map(|(k, v)| (k.clone(), v.clone())));
+        dest.merge(&source);
         let _ = self.trees.insert(tree_name_source.to_string(), source);
 
         Some(())
@@ -173,6 +244,39 @@ impl<K: Key + Clone, V: Value + Clone> DbOp<K, V> for InMemoryDb<K, V> {
         Some(())
     }
 
+    fn get_default_ttl(&self, tree_name: &str) -> Option<Duration> {
+        self.trees.get(tree_name)?.default_ttl()
+    }
+
+    fn set_default_ttl(
+        &mut self,
+        tree_name: &str,
+        ttl: Option<Duration>,
+    ) -> Option<()> {
+        self.trees.get_mut(tree_name)?.set_default_ttl(ttl);
+        Some(())
+    }
+
+    fn evict_expired(&mut self) -> usize {
+        self.trees.values_mut().map(|tree| tree.evict_expired()).sum()
+    }
+
+    fn get_capacity(&self, tree_name: &str) -> Option<Capacity> {
+        self.trees.get(tree_name)?.capacity()
+    }
+
+    fn set_capacity(
+        &mut self,
+        tree_name: &str,
+        capacity: Option<Capacity>,
+    ) -> Option<usize> {
+        Some(self.trees.get_mut(tree_name)?.set_capacity(capacity))
+    }
+
+    fn size_in_bytes(&self, tree_name: &str) -> Option<u64> {
+        Some(self.trees.get(tree_name)?.size_in_bytes())
+    }
+
     fn apply_tree(
         &mut self,
         tree_name: &str,
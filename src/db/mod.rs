@@ -3,6 +3,7 @@ mod database;
 mod file_db;
 mod file_lock;
 mod in_memory;
+mod storage;
 mod tree;
 
 pub use batch::*;
@@ -10,7 +11,7 @@ pub use database::*;
 pub use file_db::*;
 pub use file_lock::*;
 pub use in_memory::*;
-pub use tree::Tree;
+pub use tree::{Capacity, EntryKind, EvictionPolicy, Metadata, Tree};
 
 use crate::prelude::*;
 use std::fmt::Debug;
@@ -43,9 +44,38 @@ pub trait Op<K: Key, V: Value> {
     }
     fn keys(&self) -> Vec<K>;
     fn insert(&mut self, k: impl Into<K>, v: impl Into<V>) -> Option<V>;
+    /// None to keep the entry forever
+    fn insert_with_ttl(
+        &mut self,
+        k: impl Into<K>,
+        v: impl Into<V>,
+        ttl: Option<Duration>,
+    ) -> Option<V>;
+    /// change the value of an entry, keeping its expiry and metadata.
+    /// None if there's no such entry
+    fn replace(&mut self, k: impl Into<K>, v: impl Into<V>) -> Option<V>;
     fn remove(&mut self, k: impl Into<K>) -> Option<V>;
     fn clear(&mut self);
     fn contains(&self, k: &K) -> Option<bool>;
+    fn is_expired(&self, k: &K) -> bool;
+    /// remaining lifetime of an entry, None if it never expires
+    fn ttl(&self, k: &K) -> Option<Duration>;
+    /// a hit, used to choose the entry evicted when a tree is full
+    fn record_use(&mut self, k: &K);
+    fn metadata(&self, k: &K) -> Option<Metadata>;
+    fn update_metadata(
+        &mut self,
+        k: &K,
+        update: impl FnOnce(&mut Metadata),
+    ) -> Option<()>;
+    /// every name of an entry, its key then its aliases
+    fn names(&self, k: &K) -> Option<Vec<K>>;
+    /// None if there's no such entry or the alias is already a name
+    fn add_alias(&mut self, k: &K, alias: K) -> Option<()>;
+    /// None if it's the only name of the entry
+    fn remove_alias(&mut self, alias: &K) -> Option<()>;
+    /// None if there's no such name or the new one is already used
+    fn rename_alias(&mut self, alias: &K, new_alias: K) -> Option<()>;
     fn len(&self) -> Option<usize>;
 }
 pub trait DbOp<K: Key, V: Value>: Op<K, V> {
@@ -70,6 +100,43 @@ pub trait DbOp<K: Key, V: Value>: Op<K, V> {
 
     fn apply_batch(&mut self, batch: Batch<K, V>) -> Option<()>;
 
+    /// ttl of the entries inserted without one
+    fn get_default_ttl(&self, tree_name: &str) -> Option<Duration>;
+
+    fn set_default_ttl(
+        &mut self,
+        tree_name: &str,
+        ttl: Option<Duration>,
+    ) -> Option<()>;
+
+    /// remove the expired entries of every tree, returns how many were removed
+    fn evict_expired(&mut self) -> usize;
+
+    fn get_capacity(&self, tree_name: &str) -> Option<Capacity>;
+
+    /// None to remove the limits. returns how many entries were evicted
+    fn set_capacity(
+        &mut self,
+        tree_name: &str,
+        capacity: Option<Capacity>,
+    ) -> Option<usize>;
+
+    /// size of the entries of a tree once serialized
+    fn size_in_bytes(&self, tree_name: &str) -> Option<u64>;
+
+    /// get_value as a cache: an expired entry is removed,
+    /// otherwise the hit is recorded
+    fn use_value(&mut self, k: impl Into<K>) -> Option<V> {
+        let k = k.into();
+        if self.is_expired(&k) {
+            self.remove(k);
+            return None;
+        }
+        let value = self.get_value(k.clone())?;
+        self.record_use(&k);
+        Some(value)
+    }
+
     fn apply_tree(
         &mut self,
         tree_name: &str,
@@ -0,0 +1,541 @@
+use std::collections::BTreeSet;
+
+use serde::de::DeserializeOwned;
+
+use crate::prelude::*;
+
+use super::{
+    tree::Usage, Capacity, InMemoryDb, Key, Metadata, Tree, Value,
+    SCRIPT_CONTEXT_TREE,
+};
+
+/// the db file starts with MAGIC and the version, then the db in bincode.
+/// a file without it was written by karsher < 0.10 (version 1)
+const MAGIC: &[u8; 4] = b"KRSH";
+const VERSION: u32 = 7;
+
+/// version 5 and 6, the metadata without the kind of the entry
+#[derive(Deserialize)]
+struct MetadataV6 {
+    created_at: u64,
+    updated_at: u64,
+    last_used: Option<u64>,
+    exec_count: u64,
+    description: Option<String>,
+    tags: BTreeSet<String>,
+}
+
+impl From<MetadataV6> for Metadata {
+    fn from(m: MetadataV6) -> Self {
+        Metadata {
+            created_at: m.created_at,
+            updated_at: m.updated_at,
+            last_used: m.last_used,
+            exec_count: m.exec_count,
+            description: m.description,
+            tags: m.tags,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TreeV6<K: Key, V: Value> {
+    entries: BTreeMap<K, V>,
+    expires_at: BTreeMap<K, u64>,
+    default_ttl: Option<Duration>,
+    usage: BTreeMap<K, Usage>,
+    clock: u64,
+    capacity: Option<Capacity>,
+    metadata: BTreeMap<K, MetadataV6>,
+    aliases: BTreeMap<K, K>,
+}
+
+#[derive(Deserialize)]
+struct InMemoryDbV6<K: Key, V: Value> {
+    trees: BTreeMap<String, TreeV6<K, V>>,
+    default_tree: String,
+    current_tree: Option<String>,
+}
+
+impl<K: Key, V: Value> From<InMemoryDbV6<K, V>> for InMemoryDb<K, V> {
+    fn from(db: InMemoryDbV6<K, V>) -> Self {
+        InMemoryDb {
+            trees: db
+                .trees
+                .into_iter()
+                .map(|(name, tree)| {
+                    let tree = Tree {
+                        entries: tree.entries,
+                        expires_at: tree.expires_at,
+                        default_ttl: tree.default_ttl,
+                        usage: tree.usage,
+                        clock: tree.clock,
+                        capacity: tree.capacity,
+                        metadata: tree
+                            .metadata
+                            .into_iter()
+                            .map(|(k, m)| (k, m.into()))
+                            .collect(),
+                        aliases: tree.aliases,
+                    };
+                    (name, tree)
+                })
+                .collect(),
+            default_tree: db.default_tree,
+            current_tree: db.current_tree,
+        }
+    }
+}
+
+/// version 5, the aliases were entries with the same value
+#[derive(Deserialize)]
+struct TreeV5<K: Key, V: Value> {
+    entries: BTreeMap<K, V>,
+    expires_at: BTreeMap<K, u64>,
+    default_ttl: Option<Duration>,
+    usage: BTreeMap<K, Usage>,
+    clock: u64,
+    capacity: Option<Capacity>,
+    metadata: BTreeMap<K, MetadataV6>,
+}
+
+#[derive(Deserialize)]
+struct InMemoryDbV5<K: Key, V: Value> {
+    trees: BTreeMap<String, TreeV5<K, V>>,
+    default_tree: String,
+    current_tree: Option<String>,
+}
+
+impl<K: Key, V: Value> From<InMemoryDbV5<K, V>> for InMemoryDb<K, V> {
+    fn from(db: InMemoryDbV5<K, V>) -> Self {
+        InMemoryDb {
+            trees: db
+                .trees
+                .into_iter()
+                .map(|(name, tree)| {
+                    let tree = Tree {
+                        entries: tree.entries,
+                        expires_at: tree.expires_at,
+                        default_ttl: tree.default_ttl,
+                        usage: tree.usage,
+                        clock: tree.clock,
+                        capacity: tree.capacity,
+                        metadata: tree
+                            .metadata
+                            .into_iter()
+                            .map(|(k, m)| (k, m.into()))
+                            .collect(),
+                        ..Default::default()
+                    };
+                    (name, tree)
+                })
+                .collect(),
+            default_tree: db.default_tree,
+            current_tree: db.current_tree,
+        }
+    }
+}
+
+/// version 4, the metadata without tags
+#[derive(Deserialize)]
+struct MetadataV4 {
+    created_at: u64,
+    updated_at: u64,
+    last_used: Option<u64>,
+    exec_count: u64,
+    description: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TreeV4<K: Key, V: Value> {
+    entries: BTreeMap<K, V>,
+    expires_at: BTreeMap<K, u64>,
+    default_ttl: Option<Duration>,
+    usage: BTreeMap<K, Usage>,
+    clock: u64,
+    capacity: Option<Capacity>,
+    metadata: BTreeMap<K, MetadataV4>,
+}
+
+#[derive(Deserialize)]
+struct InMemoryDbV4<K: Key, V: Value> {
+    trees: BTreeMap<String, TreeV4<K, V>>,
+    default_tree: String,
+    current_tree: Option<String>,
+}
+
+impl<K: Key, V: Value> From<InMemoryDbV4<K, V>> for InMemoryDb<K, V> {
+    fn from(db: InMemoryDbV4<K, V>) -> Self {
+        InMemoryDb {
+            trees: db
+                .trees
+                .into_iter()
+                .map(|(name, tree)| {
+                    let metadata = tree
+                        .metadata
+                        .into_iter()
+                        .map(|(k, m)| {
+                            let metadata = Metadata {
+                                created_at: m.created_at,
+                                updated_at: m.updated_at,
+                                last_used: m.last_used,
+                                exec_count: m.exec_count,
+                                description: m.description,
+                                ..Default::default()
+                            };
+                            (k, metadata)
+                        })
+                        .collect();
+                    let tree = Tree {
+                        entries: tree.entries,
+                        expires_at: tree.expires_at,
+                        default_ttl: tree.default_ttl,
+                        usage: tree.usage,
+                        clock: tree.clock,
+                        capacity: tree.capacity,
+                        metadata,
+                        ..Default::default()
+                    };
+                    (name, tree)
+                })
+                .collect(),
+            default_tree: db.default_tree,
+            current_tree: db.current_tree,
+        }
+    }
+}
+
+/// version 3, without the metadata of the entries
+#[derive(Deserialize)]
+struct TreeV3<K: Key, V: Value> {
+    entries: BTreeMap<K, V>,
+    expires_at: BTreeMap<K, u64>,
+    default_ttl: Option<Duration>,
+    usage: BTreeMap<K, Usage>,
+    clock: u64,
+    capacity: Option<Capacity>,
+}
+
+#[derive(Deserialize)]
+struct InMemoryDbV3<K: Key, V: Value> {
+    trees: BTreeMap<String, TreeV3<K, V>>,
+    default_tree: String,
+    current_tree: Option<String>,
+}
+
+impl<K: Key, V: Value> From<InMemoryDbV3<K, V>> for InMemoryDb<K, V> {
+    fn from(db: InMemoryDbV3<K, V>) -> Self {
+        InMemoryDb {
+            trees: db
+                .trees
+                .into_iter()
+                .map(|(name, tree)| {
+                    let tree = Tree {
+                        entries: tree.entries,
+                        expires_at: tree.expires_at,
+                        default_ttl: tree.default_ttl,
+                        usage: tree.usage,
+                        clock: tree.clock,
+                        capacity: tree.capacity,
+                        ..Default::default()
+                    };
+                    (name, tree)
+                })
+                .collect(),
+            default_tree: db.default_tree,
+            current_tree: db.current_tree,
+        }
+    }
+}
+
+/// version 2, without the capacity of the trees
+#[derive(Deserialize)]
+struct TreeV2<K: Key, V: Value> {
+    entries: BTreeMap<K, V>,
+    expires_at: BTreeMap<K, u64>,
+    default_ttl: Option<Duration>,
+}
+
+#[derive(Deserialize)]
+struct InMemoryDbV2<K: Key, V: Value> {
+    trees: BTreeMap<String, TreeV2<K, V>>,
+    default_tree: String,
+    current_tree: Option<String>,
+}
+
+impl<K: Key, V: Value> From<InMemoryDbV2<K, V>> for InMemoryDb<K, V> {
+    fn from(db: InMemoryDbV2<K, V>) -> Self {
+        InMemoryDb {
+            trees: db
+                .trees
+                .into_iter()
+                .map(|(name, tree)| {
+                    let tree = Tree {
+                        entries: tree.entries,
+                        expires_at: tree.expires_at,
+                        default_ttl: tree.default_ttl,
+                        ..Default::default()
+                    };
+                    (name, tree)
+                })
+                .collect(),
+            default_tree: db.default_tree,
+            current_tree: db.current_tree,
+        }
+    }
+}
+
+/// version 1, each tree was a plain map
+#[derive(Deserialize)]
+struct InMemoryDbV1<K: Key, V: Value> {
+    trees: BTreeMap<String, BTreeMap<K, V>>,
+    default_tree: String,
+    current_tree: Option<String>,
+}
+
+impl<K: Key, V: Value> From<InMemoryDbV1<K, V>> for InMemoryDb<K, V> {
+    fn from(db: InMemoryDbV1<K, V>) -> Self {
+        InMemoryDb {
+            trees: db
+                .trees
+                .into_iter()
+                .map(|(name, entries)| (name, Tree::from(entries)))
+                .collect(),
+            default_tree: db.default_tree,
+            current_tree: db.current_tree,
+        }
+    }
+}
+
+/// before version 6, put stored each alias as an entry with the same value.
+/// the entries of a tree with the same value become one entry, the first key
+/// is kept and the others are its aliases
+fn group_aliases<K: Key, V: Value>(tree: &mut Tree<K, V>) {
+    let mut keys_by_value: BTreeMap<Vec<u8>, K> = BTreeMap::new();
+    let keys = tree.entries.keys().cloned().collect::<Vec<_>>();
+    for k in keys {
+        let value = match bincode::serialize(&tree.entries[&k]) {
+            Ok(value) => value,
+            Err(_) => continue,
+        };
+        let first = match keys_by_value.get(&value) {
+            Some(first) => first,
+            None => {
+                keys_by_value.insert(value, k);
+                continue;
+            }
+        };
+        tree.entries.remove(&k);
+        tree.expires_at.remove(&k);
+        tree.usage.remove(&k);
+        if let Some(m) = tree.metadata.remove(&k) {
+            let first = tree.metadata.entry(first.clone()).or_default();
+            first.exec_count += m.exec_count;
+            first.last_used = first.last_used.max(m.last_used);
+            first.description = first.description.take().or(m.description);
+            first.tags.extend(m.tags);
+        }
+        tree.aliases.insert(k, first.clone());
+    }
+}
+
+/// the script context is skipped, its variables can share a value
+fn group_all_aliases<K: Key, V: Value>(db: &mut InMemoryDb<K, V>) {
+    db.trees
+        .iter_mut()
+        .filter(|(name, _)| name.as_str() != SCRIPT_CONTEXT_TREE)
+        .for_each(|(_, tree)| group_aliases(tree));
+}
+
+pub(super) fn encode<K: Key, V: Value>(
+    db: &InMemoryDb<K, V>,
+) -> anyhow::Result<Vec<u8>> {
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend(VERSION.to_le_bytes());
+    bincode::serialize_into(&mut bytes, db)?;
+    Ok(bytes)
+}
+
+pub(super) fn decode<K, V>(bytes: &[u8]) -> anyhow::Result<InMemoryDb<K, V>>
+where
+    K: Key + DeserializeOwned,
+    V: Value + DeserializeOwned,
+{
+    let bytes = match bytes.strip_prefix(MAGIC) {
+        Some(bytes) => bytes,
+        None => {
+            debug!("db without header, migrating from version 1");
+            let db: InMemoryDbV1<K, V> = bincode::deserialize(bytes)?;
+            let mut db: InMemoryDb<K, V> = db.into();
+            group_all_aliases(&mut db);
+            return Ok(db);
+        }
+    };
+    let (version, bytes) = bytes.split_at(bytes.len().min(4));
+    let version = u32::from_le_bytes(
+        version.try_into().context("db version is missing")?,
+    );
+    let mut db: InMemoryDb<K, V> = match version {
+        2 => bincode::deserialize::<InMemoryDbV2<K, V>>(bytes)?.into(),
+        3 => bincode::deserialize::<InMemoryDbV3<K, V>>(bytes)?.into(),
+        4 => bincode::deserialize::<InMemoryDbV4<K, V>>(bytes)?.into(),
+        5 => bincode::deserialize::<InMemoryDbV5<K, V>>(bytes)?.into(),
+        6 => {
+            return Ok(bincode::deserialize::<InMemoryDbV6<K, V>>(bytes)?.into())
+        }
+        VERSION => return Ok(bincode::deserialize(bytes)?),
+        v => anyhow::bail!("unsupported db version {v}"),
+    };
+    group_all_aliases(&mut db);
+    Ok(db)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::db::{
+        Capacity, DbOp, EvictionPolicy, InMemoryDb, Op, SCRIPT_CONTEXT_TREE,
+    };
+    use crate::prelude::*;
+
+    use super::{decode, encode};
+
+    #[test]
+    fn test_encode_decode() {
+        let mut db: InMemoryDb<String, String> = InMemoryDb::default();
+        db.open_tree("rust");
+        db.insert_with_ttl("fmt", "cargo fmt", Some(Duration::from_secs(60)));
+        db.set_default_ttl("rust", Some(Duration::from_secs(3600)));
+        let capacity = Capacity {
+            max_entries: Some(10),
+            max_bytes: None,
+            policy: EvictionPolicy::Lfu,
+        };
+        db.set_capacity("rust", Some(capacity));
+
+        let mut db: InMemoryDb<String, String> =
+            decode(&encode(&db).unwrap()).unwrap();
+        db.open_tree("rust");
+        assert_eq!(Some("cargo fmt".to_string()), db.get_value("fmt"));
+        assert!(db.ttl(&"fmt".to_string()).is_some());
+        assert_eq!(Some(Duration::from_secs(3600)), db.get_default_ttl("rust"));
+    }
+
+    #[test]
+    fn test_decode_v1() {
+        // a db written before the ttl, each tree is a map
+        let v1 = (
+            BTreeMap::from([(
+                "rust".to_string(),
+                BTreeMap::from([("fmt".to_string(), "cargo fmt".to_string())]),
+            )]),
+            "__karsher_default".to_string(),
+            Some("rust".to_string()),
+        );
+        let bytes = bincode::serialize(&v1).unwrap();
+        let db: InMemoryDb<String, String> = decode(&bytes).unwrap();
+        assert_eq!(Some("rust".to_string()), db.get_current_tree());
+        assert_eq!(Some("cargo fmt".to_string()), db.get_value("fmt"));
+        assert_eq!(None, db.ttl(&"fmt".to_string()));
+
+        assert!(decode::<String, String>(b"KRSH\x63\0\0\0").is_err());
+    }
+
+    #[test]
+    fn test_decode_v2() {
+        // a db written before the capacity, a tree has a ttl
+        let v2 = (
+            BTreeMap::from([(
+                "rust".to_string(),
+                (
+                    BTreeMap::from([(
+                        "fmt".to_string(),
+                        "cargo fmt".to_string(),
+                    )]),
+                    BTreeMap::<String, u64>::new(),
+                    Some(Duration::from_secs(60)),
+                ),
+            )]),
+            "__karsher_default".to_string(),
+            Some("rust".to_string()),
+        );
+        let mut bytes = b"KRSH\x02\0\0\0".to_vec();
+        bytes.extend(bincode::serialize(&v2).unwrap());
+        let db: InMemoryDb<String, String> = decode(&bytes).unwrap();
+        assert_eq!(Some("cargo fmt".to_string()), db.get_value("fmt"));
+        assert_eq!(Some(Duration::from_secs(60)), db.get_default_ttl("rust"));
+        assert_eq!(None, db.get_capacity("rust"));
+    }
+
+    #[test]
+    fn test_decode_v5() {
+        // a db written before the aliases, put stored one entry per alias
+        // created_at, updated_at, last_used, exec_count, description, tags
+        let metadata = |exec_count: u64| {
+            (
+                0u64,
+                0u64,
+                None::<u64>,
+                exec_count,
+                None::<String>,
+                Vec::<String>::new(),
+            )
+        };
+        let context_tree = (
+            BTreeMap::from([
+                ("x".to_string(), "1".to_string()),
+                ("y".to_string(), "1".to_string()),
+            ]),
+            BTreeMap::<String, u64>::new(),
+            None::<Duration>,
+            BTreeMap::<String, (u64, u64, u64)>::new(),
+            0u64,
+            None::<Capacity>,
+            BTreeMap::from([("x".to_string(), metadata(0))]),
+        );
+        let v5 = (
+            BTreeMap::from([
+                (SCRIPT_CONTEXT_TREE.to_string(), context_tree),
+                (
+                    "docker".to_string(),
+                    (
+                        BTreeMap::from([
+                            ("drc".to_string(), "docker-compose".to_string()),
+                            ("dcu".to_string(), "docker-compose".to_string()),
+                            ("ps".to_string(), "docker ps".to_string()),
+                        ]),
+                        BTreeMap::<String, u64>::new(),
+                        None::<Duration>,
+                        BTreeMap::<String, (u64, u64, u64)>::new(),
+                        0u64,
+                        None::<Capacity>,
+                        BTreeMap::from([
+                            ("drc".to_string(), metadata(2)),
+                            ("dcu".to_string(), metadata(1)),
+                        ]),
+                    ),
+                ),
+            ]),
+            "__karsher_default".to_string(),
+            Some("docker".to_string()),
+        );
+        let mut bytes = b"KRSH\x05\0\0\0".to_vec();
+        bytes.extend(bincode::serialize(&v5).unwrap());
+        let mut db: InMemoryDb<String, String> = decode(&bytes).unwrap();
+        assert_eq!(Some(2), db.len());
+        assert_eq!(
+            Some(vec!["dcu".to_string(), "drc".to_string()]),
+            db.names(&"drc".to_string())
+        );
+        assert_eq!(
+            Some(3),
+            db.metadata(&"drc".to_string()).map(|m| m.exec_count)
+        );
+        assert_eq!(Some(vec!["ps".to_string()]), db.names(&"ps".to_string()));
+
+        // variables with the same value are not aliases
+        db.open_tree(SCRIPT_CONTEXT_TREE);
+        assert_eq!(Some(2), db.len());
+        assert_eq!(Some(vec!["y".to_string()]), db.names(&"y".to_string()));
+    }
+}
@@ -1,16 +1,134 @@
-use std::ops::DerefMut;
+use std::{collections::BTreeSet, ops::DerefMut, time::SystemTime};
 
 use crate::prelude::*;
 
 use super::{Key, Op, Value};
 
 type InnerMap<K, V> = BTreeMap<K, V>;
+
+/// milliseconds since the epoch
+pub(super) fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+/// which entry is evicted when a tree is full
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    Deserialize,
+    Serialize,
+    strum::Display,
+    strum::EnumString,
+)]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+pub enum EvictionPolicy {
+    /// least recently used
+    #[default]
+    Lru,
+    /// least frequently used
+    Lfu,
+    /// first inserted
+    Fifo,
+}
+
+/// limits of a tree, None for no limit
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize,
+)]
+pub struct Capacity {
+    pub max_entries: Option<usize>,
+    /// size of the keys and values once serialized
+    pub max_bytes: Option<u64>,
+    pub policy: EvictionPolicy,
+}
+
+impl Capacity {
+    pub fn is_unbounded(&self) -> bool {
+        self.max_entries.is_none() && self.max_bytes.is_none()
+    }
+}
+
+/// ticks of the tree clock when an entry was inserted and last used
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
+pub(super) struct Usage {
+    inserted: u64,
+    last_used: u64,
+    hits: u64,
+}
+
+/// how a value is run
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    Deserialize,
+    Serialize,
+    strum::Display,
+)]
+#[strum(serialize_all = "lowercase")]
+pub enum EntryKind {
+    /// an os command
+    #[default]
+    Command,
+    /// a karshscript snippet
+    Script,
+}
+
+/// what we know about an entry, the times are in milliseconds since the epoch
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Metadata {
+    pub created_at: u64,
+    pub updated_at: u64,
+    pub last_used: Option<u64>,
+    pub exec_count: u64,
+    pub description: Option<String>,
+    pub tags: BTreeSet<String>,
+    pub kind: EntryKind,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
-pub struct Tree<K: Key, V: Value>(InnerMap<K, V>);
+pub struct Tree<K: Key, V: Value> {
+    pub(super) entries: InnerMap<K, V>,
+    /// when an entry expires, in milliseconds since the epoch
+    pub(super) expires_at: BTreeMap<K, u64>,
+    /// ttl of an entry inserted without one
+    pub(super) default_ttl: Option<Duration>,
+    pub(super) usage: BTreeMap<K, Usage>,
+    /// incremented on every insert or use, orders the entries
+    pub(super) clock: u64,
+    pub(super) capacity: Option<Capacity>,
+    pub(super) metadata: BTreeMap<K, Metadata>,
+    /// other names of an entry, alias -> key of the entry
+    pub(super) aliases: BTreeMap<K, K>,
+}
 
 impl<K: Key, V: Value> Default for Tree<K, V> {
     fn default() -> Tree<K, V> {
-        Tree(BTreeMap::new())
+        Tree {
+            entries: BTreeMap::new(),
+            expires_at: BTreeMap::new(),
+            default_ttl: None,
+            usage: BTreeMap::new(),
+            clock: 0,
+            capacity: None,
+            metadata: BTreeMap::new(),
+            aliases: BTreeMap::new(),
+        }
+    }
+}
+
+impl<K: Key, V: Value> From<InnerMap<K, V>> for Tree<K, V> {
+    fn from(entries: InnerMap<K, V>) -> Self {
+        Tree { entries, ..Default::default() }
     }
 }
 
@@ -18,50 +136,541 @@ impl<K: Key, V: Value> Deref for Tree<K, V> {
     type Target = InnerMap<K, V>;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.entries
     }
 }
 impl<K: Key, V: Value> DerefMut for Tree<K, V> {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+        &mut self.entries
+    }
+}
+
+impl<K: Key, V: Value> Tree<K, V> {
+    /// key of the entry named k, k itself if it's not an alias
+    fn resolve<'a>(&'a self, k: &'a K) -> &'a K {
+        self.aliases.get(k).unwrap_or(k)
+    }
+
+    fn is_name(&self, k: &K) -> bool {
+        self.entries.contains_key(k) || self.aliases.contains_key(k)
+    }
+
+    fn is_expired_at(&self, k: &K, now: u64) -> bool {
+        self.expires_at.get(self.resolve(k)).filter(|e| **e <= now).is_some()
+    }
+
+    fn live_entries(&self) -> impl Iterator<Item = (&K, &V)> {
+        let now = now_millis();
+        self.entries.iter().filter(move |(k, _)| !self.is_expired_at(k, now))
+    }
+
+    pub fn default_ttl(&self) -> Option<Duration> {
+        self.default_ttl
+    }
+
+    /// None to keep the entries forever
+    pub fn set_default_ttl(&mut self, ttl: Option<Duration>) {
+        self.default_ttl = ttl.filter(|ttl| !ttl.is_zero());
+    }
+
+    /// remove the expired entries, returns how many were removed
+    pub fn evict_expired(&mut self) -> usize {
+        let now = now_millis();
+        let expired = self
+            .expires_at
+            .iter()
+            .filter(|(_, e)| **e <= now)
+            .map(|(k, _)| k.clone())
+            .collect::<Vec<_>>();
+        for k in &expired {
+            self.remove(k.clone());
+        }
+        expired.len()
+    }
+
+    /// copy the entries of another tree, with their expiry
+    pub fn merge(&mut self, other: &Tree<K, V>) {
+        let now = now_millis();
+        for (k, v) in &other.entries {
+            if other.is_expired_at(k, now) {
+                continue;
+            }
+            self.touch_inserted(k);
+            if let Some(metadata) = other.metadata.get(k) {
+                self.metadata.insert(k.clone(), metadata.clone());
+            }
+            self.aliases.remove(k);
+            self.entries.insert(k.clone(), v.clone());
+            match other.expires_at.get(k) {
+                Some(e) => self.expires_at.insert(k.clone(), *e),
+                None => self.expires_at.remove(k),
+            };
+        }
+        for (alias, k) in &other.aliases {
+            if self.entries.contains_key(k) && !self.is_name(alias) {
+                self.aliases.insert(alias.clone(), k.clone());
+            }
+        }
+        self.evict_to_capacity(None);
+    }
+
+    pub fn capacity(&self) -> Option<Capacity> {
+        self.capacity
+    }
+
+    /// None to remove the limits. returns how many entries were evicted
+    pub fn set_capacity(&mut self, capacity: Option<Capacity>) -> usize {
+        self.capacity = capacity.filter(|c| !c.is_unbounded());
+        self.evict_to_capacity(None)
+    }
+
+    /// size of the entries once serialized
+    pub fn size_in_bytes(&self) -> u64 {
+        self.entries.iter().map(|(k, v)| entry_size(k, v)).sum()
+    }
+
+    /// move an entry to another key, its aliases follow
+    fn rename_entry(&mut self, k: &K, new_k: K) {
+        if let Some(v) = self.entries.remove(k) {
+            self.entries.insert(new_k.clone(), v);
+        }
+        if let Some(e) = self.expires_at.remove(k) {
+            self.expires_at.insert(new_k.clone(), e);
+        }
+        if let Some(u) = self.usage.remove(k) {
+            self.usage.insert(new_k.clone(), u);
+        }
+        if let Some(m) = self.metadata.remove(k) {
+            self.metadata.insert(new_k.clone(), m);
+        }
+        for target in self.aliases.values_mut() {
+            if target == k {
+                *target = new_k.clone();
+            }
+        }
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    fn touch_inserted(&mut self, k: &K) {
+        let tick = self.tick();
+        let now = now_millis();
+        let is_new = self.contains(k) != Some(true);
+        let usage = self.usage.entry(k.clone()).or_default();
+        let metadata = self.metadata.entry(k.clone()).or_default();
+        if is_new {
+            *usage = Usage { inserted: tick, ..Default::default() };
+            *metadata = Metadata { created_at: now, ..Default::default() };
+        }
+        usage.last_used = tick;
+        metadata.updated_at = now;
+    }
+
+    fn is_over_capacity(&self) -> bool {
+        match self.capacity {
+            Some(Capacity { max_entries, max_bytes, .. }) => {
+                max_entries.filter(|max| self.entries.len() > *max).is_some()
+                    || max_bytes
+                        .filter(|max| self.size_in_bytes() > *max)
+                        .is_some()
+            }
+            None => false,
+        }
+    }
+
+    /// the entry to evict next following the policy
+    fn victim(&self, keep: Option<&K>) -> Option<K> {
+        let policy = self.capacity?.policy;
+        self.entries
+            .keys()
+            .filter(|k| Some(*k) != keep)
+            .min_by_key(|k| {
+                let usage = self.usage.get(k).copied().unwrap_or_default();
+                match policy {
+                    EvictionPolicy::Lru => (usage.last_used, 0),
+                    EvictionPolicy::Lfu => (usage.hits, usage.last_used),
+                    EvictionPolicy::Fifo => (usage.inserted, 0),
+                }
+            })
+            .cloned()
+    }
+
+    /// evict until the limits are respected, the expired entries first.
+    /// keep is the entry just inserted, it is never evicted
+    fn evict_to_capacity(&mut self, keep: Option<&K>) -> usize {
+        if !self.is_over_capacity() {
+            return 0;
+        }
+        let mut evicted = self.evict_expired();
+        while self.is_over_capacity() {
+            match self.victim(keep) {
+                Some(k) => {
+                    debug!("evict {k:?}");
+                    self.remove(k);
+                    evicted += 1;
+                }
+                None => break,
+            }
+        }
+        evicted
     }
 }
 
+fn entry_size<K: Key, V: Value>(k: &K, v: &V) -> u64 {
+    bincode::serialized_size(k).unwrap_or_default()
+        + bincode::serialized_size(v).unwrap_or_default()
+}
+
 impl<K: Key + Clone, V: Value> Op<K, V> for Tree<K, V> {
     fn read(
         &self,
         k: impl Into<K>,
         mapper: impl Fn(&V) -> Option<V>,
     ) -> Option<V> {
-        let v = self.get(&k.into())?;
+        let k = k.into();
+        if self.is_expired_at(&k, now_millis()) {
+            return None;
+        }
+        let v = self.get(self.resolve(&k))?;
         mapper(v)
     }
 
     fn insert(&mut self, k: impl Into<K>, v: impl Into<V>) -> Option<V> {
-        self.0.insert(k.into(), v.into())
+        self.insert_with_ttl(k, v, self.default_ttl)
+    }
+
+    fn insert_with_ttl(
+        &mut self,
+        k: impl Into<K>,
+        v: impl Into<V>,
+        ttl: Option<Duration>,
+    ) -> Option<V> {
+        let mut k = k.into();
+        if self.is_expired_at(&k, now_millis()) {
+            // the aliases of an expired entry don't name the new value
+            self.remove(k.clone());
+        }
+        k = self.resolve(&k).clone();
+        match ttl {
+            Some(ttl) => self
+                .expires_at
+                .insert(k.clone(), now_millis() + ttl.as_millis() as u64),
+            None => self.expires_at.remove(&k),
+        };
+        self.touch_inserted(&k);
+        let previous = self.entries.insert(k.clone(), v.into());
+        self.evict_to_capacity(Some(&k));
+        previous
+    }
+
+    fn replace(&mut self, k: impl Into<K>, v: impl Into<V>) -> Option<V> {
+        let k = k.into();
+        if self.contains(&k) != Some(true) {
+            return None;
+        }
+        let k = self.resolve(&k).clone();
+        self.touch_inserted(&k);
+        let previous = self.entries.insert(k.clone(), v.into());
+        self.evict_to_capacity(Some(&k));
+        previous
     }
 
     fn remove(&mut self, k: impl Into<K>) -> Option<V> {
-        self.0.remove(&k.into())
+        let k = k.into();
+        let k = self.resolve(&k).clone();
+        self.aliases.retain(|_, target| target != &k);
+        self.expires_at.remove(&k);
+        self.usage.remove(&k);
+        self.metadata.remove(&k);
+        self.entries.remove(&k)
     }
 
     fn clear(&mut self) {
-        self.0.clear();
+        self.entries.clear();
+        self.expires_at.clear();
+        self.usage.clear();
+        self.metadata.clear();
+        self.aliases.clear();
     }
 
     fn contains(&self, k: &K) -> Option<bool> {
-        Some(self.contains_key(k))
+        Some(
+            self.contains_key(self.resolve(k))
+                && !self.is_expired_at(k, now_millis()),
+        )
+    }
+
+    fn is_expired(&self, k: &K) -> bool {
+        self.is_expired_at(k, now_millis())
+    }
+
+    fn ttl(&self, k: &K) -> Option<Duration> {
+        let expires_at = self.expires_at.get(self.resolve(k))?;
+        Some(Duration::from_millis(expires_at.saturating_sub(now_millis())))
+    }
+
+    fn record_use(&mut self, k: &K) {
+        let k = &self.resolve(k).clone();
+        if !self.entries.contains_key(k) {
+            return;
+        }
+        let tick = self.tick();
+        let usage = self.usage.entry(k.clone()).or_default();
+        usage.last_used = tick;
+        usage.hits += 1;
+        self.metadata.entry(k.clone()).or_default().last_used =
+            Some(now_millis());
+    }
+
+    fn metadata(&self, k: &K) -> Option<Metadata> {
+        let k = self.resolve(k);
+        self.entries.get(k)?;
+        Some(self.metadata.get(k).cloned().unwrap_or_default())
+    }
+
+    fn update_metadata(
+        &mut self,
+        k: &K,
+        update: impl FnOnce(&mut Metadata),
+    ) -> Option<()> {
+        let k = self.resolve(k).clone();
+        self.entries.get(&k)?;
+        update(self.metadata.entry(k).or_default());
+        Some(())
+    }
+
+    fn names(&self, k: &K) -> Option<Vec<K>> {
+        if self.contains(k) != Some(true) {
+            return None;
+        }
+        let k = self.resolve(k);
+        let aliases = self.aliases.iter().filter(|(_, target)| *target == k);
+        Some(
+            std::iter::once(k.clone())
+                .chain(aliases.map(|(alias, _)| alias.clone()))
+                .collect(),
+        )
+    }
+
+    fn add_alias(&mut self, k: &K, alias: K) -> Option<()> {
+        if self.contains(k) != Some(true) || self.is_name(&alias) {
+            return None;
+        }
+        let k = self.resolve(k).clone();
+        self.aliases.insert(alias, k);
+        Some(())
+    }
+
+    fn remove_alias(&mut self, alias: &K) -> Option<()> {
+        if self.aliases.remove(alias).is_some() {
+            return Some(());
+        }
+        // the key of the entry, the next alias takes its place
+        let next = self
+            .aliases
+            .iter()
+            .find(|(_, target)| *target == alias)
+            .map(|(next, _)| next.clone())?;
+        self.aliases.remove(&next);
+        self.rename_entry(alias, next);
+        Some(())
+    }
+
+    fn rename_alias(&mut self, alias: &K, new_alias: K) -> Option<()> {
+        if !self.is_name(alias) || self.is_name(&new_alias) {
+            return None;
+        }
+        match self.aliases.remove(alias) {
+            Some(k) => {
+                self.aliases.insert(new_alias, k);
+            }
+            None => self.rename_entry(alias, new_alias),
+        }
+        Some(())
     }
 
     fn len(&self) -> Option<usize> {
-        Some(self.0.len())
+        Some(self.live_entries().count())
     }
 
     fn keys(&self) -> Vec<K> {
-        self.0.keys().into_iter().cloned().collect()
+        self.live_entries().map(|(k, _)| k.clone()).collect()
     }
 
     fn list_all(&self) -> BTreeMap<K, V> {
-        self.0.clone()
+        self.live_entries().map(|(k, v)| (k.clone(), v.clone())).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::db::Op;
+    use crate::prelude::*;
+
+    use super::{Capacity, EvictionPolicy, Metadata, Tree};
+
+    #[test]
+    fn test_ttl() {
+        let mut tree: Tree<String, String> = Tree::default();
+        tree.insert("forever", "a");
+        tree.insert_with_ttl("later", "b", Some(Duration::from_secs(3600)));
+        tree.insert_with_ttl("expired", "c", Some(Duration::ZERO));
+
+        assert_eq!(None, tree.get_value("expired"));
+        assert_eq!(Some(false), tree.contains(&"expired".to_string()));
+        assert!(tree.is_expired(&"expired".to_string()));
+        assert_eq!(vec!["forever", "later"], tree.keys());
+        assert_eq!(Some(2), tree.len());
+        assert_eq!(None, tree.ttl(&"forever".to_string()));
+        let ttl = tree.ttl(&"later".to_string()).unwrap();
+        assert!(
+            ttl > Duration::from_secs(3590) && ttl <= Duration::from_secs(3600)
+        );
+
+        // still stored until evicted
+        assert_eq!(3, tree.entries.len());
+        assert_eq!(1, tree.evict_expired());
+        assert_eq!(2, tree.entries.len());
+        assert_eq!(0, tree.evict_expired());
+
+        // a value put again without ttl is kept forever
+        tree.insert("later", "d");
+        assert_eq!(None, tree.ttl(&"later".to_string()));
+
+        tree.set_default_ttl(Some(Duration::ZERO));
+        assert_eq!(None, tree.default_ttl());
+        tree.set_default_ttl(Some(Duration::from_secs(60)));
+        tree.insert("tok", "e");
+        assert!(tree.ttl(&"tok".to_string()).is_some());
+    }
+
+    fn bounded(policy: EvictionPolicy) -> Tree<String, String> {
+        let mut tree = Tree::default();
+        tree.set_capacity(Some(Capacity {
+            max_entries: Some(2),
+            max_bytes: None,
+            policy,
+        }));
+        tree
+    }
+
+    #[test]
+    fn test_capacity() {
+        let mut tree = bounded(EvictionPolicy::Lru);
+        tree.insert("a", "1");
+        tree.insert("b", "2");
+        tree.record_use(&"a".to_string());
+        tree.insert("c", "3");
+        assert_eq!(vec!["a", "c"], tree.keys());
+
+        let mut tree = bounded(EvictionPolicy::Lfu);
+        tree.insert("a", "1");
+        tree.insert("b", "2");
+        tree.record_use(&"a".to_string());
+        tree.record_use(&"a".to_string());
+        tree.record_use(&"b".to_string());
+        tree.insert("c", "3");
+        assert_eq!(vec!["a", "c"], tree.keys());
+
+        let mut tree = bounded(EvictionPolicy::Fifo);
+        tree.insert("a", "1");
+        tree.insert("b", "2");
+        tree.record_use(&"a".to_string());
+        // an update doesn't change the order
+        tree.insert("a", "4");
+        tree.insert("c", "3");
+        assert_eq!(vec!["b", "c"], tree.keys());
+
+        // expired entries go first
+        let mut tree = bounded(EvictionPolicy::Lru);
+        tree.insert_with_ttl("a", "1", Some(Duration::ZERO));
+        tree.insert("b", "2");
+        tree.record_use(&"a".to_string());
+        tree.insert("c", "3");
+        assert_eq!(vec!["b", "c"], tree.keys());
+
+        let size = tree.size_in_bytes();
+        assert_eq!(
+            1,
+            tree.set_capacity(Some(Capacity {
+                max_entries: None,
+                max_bytes: Some(size - 1),
+                policy: EvictionPolicy::Lru,
+            }))
+        );
+        assert_eq!(vec!["c"], tree.keys());
+        // the entry just inserted is kept even if too big
+        tree.insert("d", "a value bigger than the limit");
+        assert_eq!(vec!["d"], tree.keys());
+
+        assert_eq!(0, tree.set_capacity(Some(Capacity::default())));
+        assert_eq!(None, tree.capacity());
+        assert_eq!(Ok(EvictionPolicy::Lfu), "LFU".parse());
+        assert_eq!("fifo", EvictionPolicy::Fifo.to_string());
+    }
+
+    #[test]
+    fn test_metadata() {
+        let mut tree: Tree<String, String> = Tree::default();
+        tree.insert("pods", "kubectl get pods");
+        let created = tree.metadata(&"pods".to_string()).unwrap();
+        assert!(created.created_at > 0);
+        assert_eq!(created.created_at, created.updated_at);
+        assert_eq!(None, created.last_used);
+
+        tree.update_metadata(&"pods".to_string(), |m| {
+            m.exec_count += 1;
+            m.description = Some("list the pods".to_string());
+        });
+        tree.record_use(&"pods".to_string());
+        tree.insert("pods", "kubectl get pods -A");
+        let Metadata { created_at, last_used, exec_count, description, .. } =
+            tree.metadata(&"pods".to_string()).unwrap();
+        assert_eq!(created.created_at, created_at);
+        assert!(last_used.is_some());
+        assert_eq!(1, exec_count);
+        assert_eq!(Some("list the pods".to_string()), description);
+
+        tree.remove("pods");
+        assert_eq!(None, tree.metadata(&"pods".to_string()));
+        assert_eq!(None, tree.update_metadata(&"pods".to_string(), |_| {}));
+    }
+
+    #[test]
+    fn test_aliases() {
+        let mut tree: Tree<String, String> = Tree::default();
+        let name = |s: &str| s.to_string();
+        tree.insert("drc", "docker-compose");
+        assert_eq!(Some(()), tree.add_alias(&name("drc"), name("dcu")));
+        assert_eq!(None, tree.add_alias(&name("dcu"), name("drc")));
+        assert_eq!(Some("docker-compose".to_string()), tree.get_value("dcu"));
+        assert_eq!(vec!["drc"], tree.keys());
+
+        // an alias updates its value
+        tree.insert("dcu", "docker compose");
+        tree.record_use(&name("dcu"));
+        assert_eq!(Some(1), tree.len());
+        assert_eq!(Some("docker compose".to_string()), tree.get_value("drc"));
+
+        // the key moves to the next alias, with the metadata
+        assert_eq!(Some(()), tree.remove_alias(&name("drc")));
+        assert_eq!(None, tree.remove_alias(&name("dcu")));
+        assert_eq!(vec!["dcu"], tree.keys());
+        assert!(tree.metadata(&name("dcu")).unwrap().last_used.is_some());
+
+        // the aliases of an evicted entry are removed with it
+        tree.insert("tok", "token");
+        tree.add_alias(&name("tok"), name("t"));
+        tree.insert_with_ttl("t", "token", Some(Duration::ZERO));
+        assert_eq!(None, tree.names(&name("t")));
+        assert_eq!(1, tree.evict_expired());
+        assert!(tree.aliases.is_empty());
+        tree.add_alias(&name("dcu"), name("up"));
+        tree.remove("up");
+        assert_eq!(Some(0), tree.len());
+        assert!(tree.aliases.is_empty());
     }
 }
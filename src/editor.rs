@@ -10,7 +10,13 @@ use rustyline::{
 };
 use rustyline_derive::Helper;
 use std::borrow::Cow::{self, Borrowed, Owned};
-use std::path::Path;
+use std::cell::RefCell;
+use std::fs::OpenOptions;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::cache_command::get_env;
 
 fn get_default_history_path() -> Option<Box<Path>> {
     let mut home_dir = dirs::home_dir()?;
@@ -30,6 +36,15 @@ pub fn save_history(
         .map_err(|e| anyhow::Error::msg(format!("{e}")))
 }
 
+thread_local! {
+    static NEXT_INPUT: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// text already typed at the next prompt
+pub fn set_next_input(input: &str) {
+    NEXT_INPUT.with(|next| next.replace(Some(input.to_string())));
+}
+
 pub fn read_line(
     rl: &mut Editor<CustomHelper>,
     curr_cache: &str,
@@ -37,7 +52,61 @@ pub fn read_line(
     let p = format!("[{curr_cache}] >> ");
     rl.helper_mut().expect("No helper").colored_prompt =
         format!("\x1b[1;32m{}\x1b[0m", p);
-    rl.readline(&p)
+    match NEXT_INPUT.with(|next| next.take()) {
+        Some(input) => rl.readline_with_initial(&p, (&input, "")),
+        None => rl.readline(&p),
+    }
+}
+
+/// ask for a single value, None if cancelled
+pub fn prompt(message: &str) -> Option<String> {
+    let mut rl = Editor::<()>::new();
+    rl.readline(message).ok()
+}
+
+/// new temp file with a random name, only readable by the user as the
+/// values are often secrets
+fn create_temp_file(initial: &str) -> anyhow::Result<PathBuf> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    for _ in 0..10 {
+        let path = std::env::temp_dir()
+            .join(format!("karsher-{:016x}.sh", rand::random::<u64>()));
+        match options.open(&path) {
+            Ok(mut file) => {
+                if let Err(e) = file.write_all(initial.as_bytes()) {
+                    let _ = std::fs::remove_file(&path);
+                    return Err(e.into());
+                }
+                return Ok(path);
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    anyhow::bail!("could not create a temp file")
+}
+
+/// edit a text in $VISUAL, $EDITOR or vi through a temp file.
+/// returns the text saved, without the trailing newlines
+pub fn open_in_editor(initial: &str) -> anyhow::Result<String> {
+    let editor = ["VISUAL", "EDITOR"]
+        .iter()
+        .find_map(|var| get_env(var).filter(|e| !e.trim().is_empty()))
+        .unwrap_or_else(|| "vi".to_string());
+    let mut editor = editor.split_whitespace();
+    let program = editor.next().context("no editor")?;
+
+    let path = create_temp_file(initial)?;
+    let status = Command::new(program).args(editor).arg(&path).status();
+    let text = std::fs::read_to_string(&path);
+    let _ = std::fs::remove_file(&path);
+    let status =
+        status.with_context(|| format!("could not start {program}"))?;
+    anyhow::ensure!(status.success(), "{program} exited with {status}");
+    Ok(text?.trim_end_matches(['\n', '\r']).to_string())
 }
 
 pub fn build_editor(
@@ -145,3 +214,35 @@ impl Hinter for CustomHelper {
         self.hinter.hint(line, pos, ctx)
     }
 }
+
+#[cfg(test)]
+mod test {
+    #[cfg(unix)]
+    use std::os::unix::fs::PermissionsExt;
+
+    use crate::cache_command::set_session_env;
+
+    use super::open_in_editor;
+
+    #[test]
+    #[cfg(unix)]
+    fn test_open_in_editor() {
+        let editor = std::env::temp_dir()
+            .join(format!("karsher-test-editor-{}", std::process::id()));
+        // appends a line and checks the file is private
+        let script = r#"#!/bin/sh
+case "$(ls -l "$1")" in -rw-------*) ;; *) exit 1 ;; esac
+printf 'echo b\n\n' >> "$1"
+"#;
+        std::fs::write(&editor, script).unwrap();
+        std::fs::set_permissions(&editor, PermissionsExt::from_mode(0o755))
+            .unwrap();
+        // the session variables are per thread
+        set_session_env("VISUAL", &editor.to_string_lossy());
+
+        assert_eq!("echo a\necho b", open_in_editor("echo a\n").unwrap());
+        set_session_env("VISUAL", "false");
+        assert!(open_in_editor("echo a").is_err());
+        std::fs::remove_file(editor).unwrap();
+    }
+}
@@ -4,7 +4,11 @@ use slab_tree::{NodeId, Tree};
 
 use crate::prelude::{BTreeMap, Context};
 
-use super::{MathConstants, Operator, Primitive, TreeNodeValue, Value};
+use super::{
+    number::{from_big_int, from_decimal},
+    BuiltInFunctionType, MathConstants, NotAScript, Operator, Primitive,
+    TreeNodeValue, Value,
+};
 
 fn variable_from_ctx(
     name: &str,
@@ -13,7 +17,7 @@ fn variable_from_ctx(
 ) -> anyhow::Result<Primitive> {
     let value = ctx
         .get(name)
-        .context(format!("variable {name} not found in ctx"))?
+        .ok_or_else(|| NotAScript(format!("variable {name} not found in ctx")))?
         .as_ref_ok()?
         .clone();
 
@@ -75,6 +79,7 @@ pub(super) fn to_ast(
             }
 
             let op_pos = None
+                .or_else(filter_op(Operator::NullCoalesce, &operations))
                 .or_else(filter_op(Operator::Or, &operations))
                 .or_else(filter_op(Operator::And, &operations))
                 .or_else(filter_op(Operator::GreaterOrEqual, &operations))
@@ -83,13 +88,20 @@ pub(super) fn to_ast(
                 .or_else(filter_op(Operator::Less, &operations))
                 .or_else(filter_op(Operator::Equal, &operations))
                 .or_else(filter_op(Operator::NotEqual, &operations))
+                .or_else(filter_op(Operator::RegexMatch, &operations))
+                .or_else(filter_op(Operator::BitOr, &operations))
+                .or_else(filter_op(Operator::BitXor, &operations))
+                .or_else(filter_op(Operator::BitAnd, &operations))
+                .or_else(filter_op(Operator::ShiftLeft, &operations))
+                .or_else(filter_op(Operator::ShiftRight, &operations))
                 .or_else(filter_op(Operator::Add, &operations))
                 .or_else(filter_op(Operator::Subtr, &operations))
                 .or_else(filter_op(Operator::Mult, &operations))
                 .or_else(filter_op(Operator::Mod, &operations))
                 .or_else(filter_op(Operator::Div, &operations))
                 .or_else(filter_op(Operator::Pow, &operations))
-                .or_else(filter_op(Operator::Not, &operations));
+                .or_else(filter_op(Operator::Not, &operations))
+                .or_else(filter_op(Operator::BitNot, &operations));
 
             if let Some(op_pos) = op_pos {
                 let mut left: Vec<Value> =
@@ -97,6 +109,15 @@ pub(super) fn to_ast(
 
                 let operation = operations.remove(0);
 
+                // e.g `port ?? 22` when port is not defined
+                if operation == Value::Operation(Operator::NullCoalesce) {
+                    if let [Value::Variable(name)] = &left[..] {
+                        if !ctx.contains_key(name) {
+                            left = vec![Value::Null];
+                        }
+                    }
+                }
+
                 // handle negation
                 if operation == Value::Operation(Operator::Subtr)
                     && matches!(left.last(), Some(Value::Operation(_)))
@@ -104,6 +125,9 @@ pub(super) fn to_ast(
                     let right_first = match operations.first() {
                         Some(Value::Decimal(d)) => Some(Value::Decimal(-d)),
                         Some(Value::Integer(d)) => Some(Value::Integer(-d)),
+                        Some(Value::BigInteger(d)) => {
+                            Some(Value::BigInteger(-d))
+                        }
                         Some(Value::Variable(d)) => {
                             Some(Value::VariableNegate(d.to_string()))
                         }
@@ -163,7 +187,12 @@ pub(super) fn to_ast(
         }
 
         Value::Decimal(num) => append_to_current_and_return(
-            TreeNodeValue::Primitive(Primitive::Double(num)),
+            TreeNodeValue::Primitive(from_decimal(num)),
+            tree,
+            curr_node_id,
+        ),
+        Value::BigInteger(num) => append_to_current_and_return(
+            TreeNodeValue::Primitive(from_big_int(num)),
             tree,
             curr_node_id,
         ),
@@ -182,6 +211,11 @@ pub(super) fn to_ast(
             tree,
             curr_node_id,
         ),
+        Value::Null => append_to_current_and_return(
+            TreeNodeValue::Primitive(Primitive::Null),
+            tree,
+            curr_node_id,
+        ),
         Value::Variable(name) => {
             let value = variable_from_ctx(name.as_str(), false, ctx)?;
             append_to_current_and_return(
@@ -198,35 +232,55 @@ pub(super) fn to_ast(
                 curr_node_id,
             )
         }
-        Value::VariableExpr { name, expr } => {
+        Value::VariableExpr { name, expr, operator } => {
             anyhow::ensure!(
                 tree.root().is_none(),
                 "invalid variable assignment "
             );
 
-            let variable_assign_node = if let Value::Variable(n) = *name {
-                Ok(TreeNodeValue::VariableAssign(n))
-            } else if let Value::ArrayAccess { arr, index } = *name {
-                if let (Value::Variable(n), Value::Integer(index)) =
-                    (*arr, *index)
-                {
-                    Ok(TreeNodeValue::VariableArrayAssign {
-                        name: n,
-                        index: Primitive::Int(index),
-                    })
-                } else {
-                    Err(anyhow::Error::msg("invalid variable expression"))
+            // m[i][j] gives m and [i, j]
+            let mut target = *name;
+            let mut indexes = vec![];
+            while let Value::ArrayAccess { safe, arr, index } = target {
+                anyhow::ensure!(
+                    !safe,
+                    "cannot assign to a safe access. e.g m?[\"a\"] = 1"
+                );
+                indexes.push(*index);
+                target = *arr;
+            }
+            indexes.reverse();
+
+            let (variable_assign_node, value) = match target {
+                // x += 1 is x = x + (1)
+                Value::Variable(n) if indexes.is_empty() => {
+                    let value = match operator {
+                        Some(operator) => Value::Expression(vec![
+                            Value::Variable(n.clone()),
+                            Value::Operation(operator),
+                            Value::BlockParen(vec![*expr]),
+                        ]),
+                        None => *expr,
+                    };
+                    (TreeNodeValue::VariableAssign(n), value)
                 }
-            } else {
-                Err(anyhow::Error::msg("invalid variable expression"))
-            }?;
+                // the indexes are evaluated once, m[i] += 1 is not
+                // m[i] = m[i] + (1)
+                Value::Variable(name) => (
+                    TreeNodeValue::VariableArrayAssign { name, operator },
+                    *expr,
+                ),
+                _ => anyhow::bail!("invalid variable expression"),
+            };
 
             let node_id = Some(tree.set_root(variable_assign_node));
 
-            let value = *expr;
-
             let _ = to_ast(ctx, value, tree, &node_id)?
                 .context(format!("invalid variable expr {node_id:?}"))?;
+            for index in indexes {
+                to_ast(ctx, index, tree, &node_id)?
+                    .context("invalid index expression")?;
+            }
             Ok(node_id)
         }
         Value::Const(c) => match c {
@@ -250,6 +304,48 @@ pub(super) fn to_ast(
             ),
             _ => unreachable!("should never happen or it's a bug"),
         },
+        Value::BuiltInFunction { fn_type, expr } if fn_type.mutates() => {
+            // the variable is updated in place, e.g push(arr, 1)
+            let mut args = match *expr {
+                Value::Array(args) if !args.is_empty() => args,
+                _ => anyhow::bail!("invalid arguments. e.g push(arr, 1)"),
+            };
+            let name = match args.remove(0) {
+                Value::Variable(name) => name,
+                _ => anyhow::bail!(
+                    "first argument must be a variable. e.g push(arr, 1)"
+                ),
+            };
+            let fn_node = TreeNodeValue::MutatingFunction { fn_type, name };
+            let node_id = if let Some(node_id) = curr_node_id {
+                let mut node = tree
+                    .get_mut(*node_id)
+                    .context("node id does not exist!")?;
+                Some(node.append(fn_node).node_id())
+            } else if let Some(mut root_node) = tree.root_mut() {
+                Some(root_node.append(fn_node).node_id())
+            } else {
+                Some(tree.set_root(fn_node))
+            };
+            to_ast(ctx, Value::Array(args), tree, &node_id)?;
+            Ok(node_id)
+        }
+        Value::BuiltInFunction {
+            fn_type: BuiltInFunctionType::Defined,
+            expr,
+        } if matches!(*expr, Value::Variable(_)) => {
+            let defined = match *expr {
+                Value::Variable(name) => {
+                    !matches!(ctx.get(&name), None | Some(Primitive::Null))
+                }
+                _ => unreachable!("checked by the guard"),
+            };
+            append_to_current_and_return(
+                TreeNodeValue::Primitive(Primitive::Bool(defined)),
+                tree,
+                curr_node_id,
+            )
+        }
         Value::BuiltInFunction { fn_type, expr } => {
             let fn_node = TreeNodeValue::BuiltInFunction(fn_type);
             let node_id = if let Some(node_id) = curr_node_id {
@@ -276,34 +372,108 @@ pub(super) fn to_ast(
             let while_node = TreeNodeValue::WhileExpr(v);
             append_to_current_and_return(while_node, tree, curr_node_id)
         }
+        v @ Value::MatchExpr { value: _, arms: _ } => {
+            let match_node = TreeNodeValue::MatchExpr(v);
+            append_to_current_and_return(match_node, tree, curr_node_id)
+        }
         Value::Array(arr) => append_to_current_and_return(
             TreeNodeValue::Array(arr),
             tree,
             curr_node_id,
         ),
-        Value::ArrayAccess { arr, index } => match (*arr, *index) {
-            (v, Value::Integer(idx)) => append_to_current_and_return(
-                TreeNodeValue::ArrayAccess {
-                    index: Primitive::Int(idx),
-                    array: v,
-                },
+        Value::Map(map) => append_to_current_and_return(
+            TreeNodeValue::Map(map),
+            tree,
+            curr_node_id,
+        ),
+        Value::StructDef { name, fields } => {
+            for (i, field) in fields.iter().enumerate() {
+                anyhow::ensure!(
+                    !fields[i + 1..].contains(field),
+                    "field {field} declared twice in struct {name}"
+                );
+            }
+            append_to_current_and_return(
+                TreeNodeValue::Primitive(Primitive::StructDef { name, fields }),
                 tree,
                 curr_node_id,
-            ),
-            (v, Value::Variable(idx_var)) => {
-                let idx = variable_from_ctx(&idx_var, false, ctx)?;
-                append_to_current_and_return(
-                    TreeNodeValue::ArrayAccess { index: idx, array: v },
-                    tree,
-                    curr_node_id,
-                )
+            )
+        }
+        Value::Struct { name, mut fields } => {
+            let declared = match ctx.get(&name) {
+                Some(Primitive::StructDef { fields, .. }) => fields,
+                _ => anyhow::bail!("struct {name} not found in ctx"),
+            };
+            if let Some((field, _)) =
+                fields.iter().find(|(f, _)| !declared.contains(f))
+            {
+                anyhow::bail!("struct {name} has no field {field}");
             }
-
-            (arr, index) => {
-                return Err(anyhow::Error::msg(format!(
-                    "illegal array access! array => {arr:?}, index=> {index:?}"
-                )))
+            // every declared field, null when not specified
+            let mut all_fields = vec![];
+            for field in declared {
+                let value = match fields.iter().position(|(f, _)| f == field) {
+                    Some(pos) => fields.remove(pos).1,
+                    None => Value::Null,
+                };
+                all_fields.push((field.clone(), value));
             }
-        },
+            if let Some((field, _)) = fields.first() {
+                anyhow::bail!("field {field} specified twice");
+            }
+            append_to_current_and_return(
+                TreeNodeValue::Struct { name, fields: all_fields },
+                tree,
+                curr_node_id,
+            )
+        }
+        Value::Slice { .. } => {
+            anyhow::bail!("a slice can only be used as an index. e.g arr[1:3]")
+        }
+        Value::ArrayAccess { safe, arr, index } => {
+            let access_node = if matches!(*index, Value::Slice { .. }) {
+                TreeNodeValue::ArraySlice
+            } else if safe {
+                TreeNodeValue::SafeArrayAccess
+            } else {
+                TreeNodeValue::ArrayAccess
+            };
+            // e.g `config?.port` when config is not defined
+            let arr = match *arr {
+                Value::Variable(name) if safe && !ctx.contains_key(&name) => {
+                    Value::Null
+                }
+                arr => arr,
+            };
+            let node_id = if let Some(node_id) = curr_node_id {
+                let mut node = tree
+                    .get_mut(*node_id)
+                    .context("node id does not exist!")?;
+                Some(node.append(access_node).node_id())
+            } else if let Some(mut root_node) = tree.root_mut() {
+                Some(root_node.append(access_node).node_id())
+            } else {
+                Some(tree.set_root(access_node))
+            };
+            to_ast(ctx, arr, tree, &node_id)?
+                .context("illegal array access! missing array")?;
+            if let Value::Slice { start, end } = *index {
+                for bound in [start, end] {
+                    let bound = bound.map_or(Value::Expression(vec![]), |b| *b);
+                    if to_ast(ctx, bound, tree, &node_id)?.is_none() {
+                        // not specified, e.g arr[:2]
+                        append_to_current_and_return(
+                            TreeNodeValue::Primitive(Primitive::Unit),
+                            tree,
+                            &node_id,
+                        )?;
+                    }
+                }
+            } else {
+                to_ast(ctx, *index, tree, &node_id)?
+                    .context("illegal array access! missing index")?;
+            }
+            Ok(node_id)
+        }
     }
 }
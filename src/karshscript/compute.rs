@@ -1,165 +1,42 @@
 use std::{
     fs::read_to_string,
-    ops::{Neg, Not},
+    ops::{BitAnd, BitOr, BitXor, Neg, Not, Shl, Shr},
     path::{Path, PathBuf},
 };
 
 use anyhow::{Context, Error};
 use slab_tree::{NodeRef, Tree};
 
-use crate::{karshscript::parser::parse_instructions, prelude::BTreeMap};
+use crate::{
+    cache_command::{
+        capture_command, capture_shell, get_all_envs, get_env, set_session_env,
+        ExecOptions,
+    },
+    karshscript::parser::parse_instructions,
+    prelude::{BTreeMap, Duration},
+};
 
 use super::{
     ast::to_ast,
+    date, limits,
+    number::{number_mode, to_f64, to_radix, NumberMode},
+    pattern,
     primitive::{
-        Abs, And, Array, Cos, Logarithm, Or, Pow, Primitive, Sin, Sqrt, Tan,
+        normalize_index, Abs, And, Array, BitNot, Cos, Logarithm, Or, Pow,
+        Primitive, Sin, Sqrt, Tan,
     },
-    Operator, TreeNodeValue, Value,
+    random, BuiltInFunctionType, Host, MatchArm, NotAScript, Operator,
+    TreeNodeValue, Value,
 };
 
 fn compute_recur(
     node: Option<NodeRef<TreeNodeValue>>,
     ctx: &mut BTreeMap<String, Primitive>,
+    host: &mut dyn Host,
 ) -> anyhow::Result<Primitive> {
     if let Some(node) = node {
         match node.data() {
-            TreeNodeValue::Ops(Operator::Not) => {
-                if node.children().count() != 1 {
-                    return Err(Error::msg(
-                        "only one value allowed, no '!' possible",
-                    ));
-                }
-                let left = compute_recur(node.first_child(), ctx)?;
-                Ok(!left)
-            }
-            TreeNodeValue::Ops(Operator::Add) => {
-                if node.children().count() == 1 {
-                    return compute_recur(node.first_child(), ctx);
-                }
-                let left = compute_recur(node.first_child(), ctx)?;
-                let right = compute_recur(node.last_child(), ctx)?;
-                Ok(left + right)
-            }
-            TreeNodeValue::Ops(Operator::Mult) => {
-                if node.children().count() == 1 {
-                    return compute_recur(node.first_child(), ctx);
-                }
-                let left = compute_recur(node.first_child(), ctx)?;
-                let right = compute_recur(node.last_child(), ctx)?;
-                Ok(left * right)
-            }
-            TreeNodeValue::Ops(Operator::Mod) => {
-                if node.children().count() == 1 {
-                    return compute_recur(node.first_child(), ctx);
-                }
-                let left = compute_recur(node.first_child(), ctx)?;
-                let right = compute_recur(node.last_child(), ctx)?;
-                Ok(left % right)
-            }
-            TreeNodeValue::Ops(Operator::Subtr) => {
-                if node.children().count() == 1 {
-                    return Ok(compute_recur(node.first_child(), ctx)?.neg());
-                }
-                let left = compute_recur(node.first_child(), ctx)?;
-                let right = compute_recur(node.last_child(), ctx)?;
-                Ok(left - right)
-            }
-            TreeNodeValue::Ops(Operator::Pow) => {
-                if node.children().count() == 1 {
-                    return compute_recur(node.first_child(), ctx);
-                }
-                let left = compute_recur(node.first_child(), ctx)?;
-                let right = compute_recur(node.last_child(), ctx)?;
-                Ok(left.pow(right))
-            }
-            TreeNodeValue::Ops(Operator::Div) => {
-                if node.children().count() == 1 {
-                    return compute_recur(node.first_child(), ctx);
-                }
-                let left = compute_recur(node.first_child(), ctx)?;
-                let right = compute_recur(node.last_child(), ctx)?;
-                Ok(left / right)
-            }
-            TreeNodeValue::Ops(Operator::Equal) => {
-                if node.children().count() == 1 {
-                    return Err(Error::msg(
-                        "only one value, no '==' comparison possible",
-                    ));
-                }
-                let left = compute_recur(node.first_child(), ctx)?;
-                let right = compute_recur(node.last_child(), ctx)?;
-                Ok(left.is_equal(&right))
-            }
-            TreeNodeValue::Ops(Operator::And) => {
-                if node.children().count() == 1 {
-                    return Err(Error::msg(
-                        "only one value, no '&&' comparison possible",
-                    ));
-                }
-                let left = compute_recur(node.first_child(), ctx)?;
-                let right = compute_recur(node.last_child(), ctx)?;
-                Ok(left.and(right))
-            }
-            TreeNodeValue::Ops(Operator::Or) => {
-                if node.children().count() == 1 {
-                    return Err(Error::msg(
-                        "only one value, no '||' comparison possible",
-                    ));
-                }
-                let left = compute_recur(node.first_child(), ctx)?;
-                let right = compute_recur(node.last_child(), ctx)?;
-                Ok(left.or(right))
-            }
-            TreeNodeValue::Ops(Operator::NotEqual) => {
-                if node.children().count() == 1 {
-                    return Err(Error::msg(
-                        "only one value, no '!=' comparison possible",
-                    ));
-                }
-                let left = compute_recur(node.first_child(), ctx)?;
-                let right = compute_recur(node.last_child(), ctx)?;
-                Ok(left.is_equal(&right).not())
-            }
-            TreeNodeValue::Ops(Operator::Less) => {
-                if node.children().count() == 1 {
-                    return Err(Error::msg(
-                        "only one value, no '<' comparison possible",
-                    ));
-                }
-                let left = compute_recur(node.first_child(), ctx)?;
-                let right = compute_recur(node.last_child(), ctx)?;
-                Ok(left.is_less_than(&right))
-            }
-            TreeNodeValue::Ops(Operator::Greater) => {
-                if node.children().count() == 1 {
-                    return Err(Error::msg(
-                        "only one value, no '>' comparison possible",
-                    ));
-                }
-                let left = compute_recur(node.first_child(), ctx)?;
-                let right = compute_recur(node.last_child(), ctx)?;
-                Ok(left.is_greater_than(&right))
-            }
-            TreeNodeValue::Ops(Operator::GreaterOrEqual) => {
-                if node.children().count() == 1 {
-                    return Err(Error::msg(
-                        "only one value, no '>=' comparison possible",
-                    ));
-                }
-                let left = compute_recur(node.first_child(), ctx)?;
-                let right = compute_recur(node.last_child(), ctx)?;
-                Ok(left.is_greater_or_equal(&right))
-            }
-            TreeNodeValue::Ops(Operator::LessOrEqual) => {
-                if node.children().count() == 1 {
-                    return Err(Error::msg(
-                        "only one value, no '<=' comparison possible",
-                    ));
-                }
-                let left = compute_recur(node.first_child(), ctx)?;
-                let right = compute_recur(node.last_child(), ctx)?;
-                Ok(left.is_less_or_equal(&right))
-            }
+            TreeNodeValue::Ops(op) => compute_ops(op, node, ctx, host),
             TreeNodeValue::Primitive(Primitive::Bool(b)) => {
                 Ok(Primitive::Bool(*b))
             }
@@ -168,71 +45,33 @@ fn compute_recur(
             }
             TreeNodeValue::Primitive(p) => Ok(p.clone()),
             TreeNodeValue::VariableAssign(name) => {
-                let v = compute_recur(node.first_child(), ctx)?;
+                let v = compute_recur(node.first_child(), ctx, host)?;
                 if !matches!(v, Primitive::Error(_)) {
                     ctx.insert(name.to_owned(), v.clone());
                 }
                 Ok(v)
             }
             TreeNodeValue::BuiltInFunction(fn_type) => {
-                let v = compute_recur(node.first_child(), ctx)?;
-                match fn_type {
-                    super::BuiltInFunctionType::Sqrt => Ok(v.sqrt()),
-                    super::BuiltInFunctionType::Abs => Ok(v.abs()),
-                    super::BuiltInFunctionType::Log => Ok(v.log()),
-                    super::BuiltInFunctionType::Ln => Ok(v.ln()),
-                    super::BuiltInFunctionType::Sin => Ok(v.sin()),
-                    super::BuiltInFunctionType::Cos => Ok(v.cos()),
-                    super::BuiltInFunctionType::Tan => Ok(v.tan()),
-                    super::BuiltInFunctionType::Length => Ok(v.len()),
-                    super::BuiltInFunctionType::Println => {
-                        println!("{v}");
-                        Ok(Primitive::Unit)
-                    }
-                    super::BuiltInFunctionType::Print => {
-                        print!("{v}");
-                        Ok(Primitive::Unit)
-                    }
-                    super::BuiltInFunctionType::Include => match v {
-                        Primitive::String(file_path) => {
-                            let curr_path = std::env::current_dir()
-                                .context("no current dir! wasn't expected")?;
-                            let temp_path = Path::new(&file_path);
-                            if temp_path.is_absolute() || temp_path.exists() {
-                                let parent = temp_path
-                                    .parent()
-                                    .context("parent doesn't exist")?;
-
-                                std::env::set_current_dir(PathBuf::from(
-                                    &parent,
-                                ))?;
-                            }
-
-                            let res = temp_path
-                                .file_name()
-                                .context("file name not found")
-                                .and_then(|p| {
-                                    read_to_string(p)
-                                        .map_err(anyhow::Error::new)
-                                })
-                                .and_then(move |file| compute(&file, ctx));
-                            std::env::set_current_dir(curr_path)?; // todo this might be quiet fragile
-                            res
-                        }
-                        _ => Ok(Primitive::Error("wrong include statement")),
-                    },
-                }
+                let v = compute_recur(node.first_child(), ctx, host)?;
+                compute_builtin_fn(fn_type, v, ctx, host)
             }
             TreeNodeValue::IfExpr(v) => {
-                compute_instructions(vec![v.clone()], ctx)
+                compute_instructions(vec![v.clone()], ctx, host)
             }
             TreeNodeValue::WhileExpr(v) => {
-                compute_instructions(vec![v.clone()], ctx)
+                compute_instructions(vec![v.clone()], ctx, host)
+            }
+            TreeNodeValue::MatchExpr(v) => {
+                compute_instructions(vec![v.clone()], ctx, host)
             }
             TreeNodeValue::Array(arr) => {
+                if limits::exceeds_array_len(arr.len()) {
+                    return Ok(Primitive::Error(limits::ARRAY_TOO_LONG));
+                }
                 let mut primitives = vec![];
                 for v in arr {
-                    let primitive = compute_instructions(vec![v.clone()], ctx)?;
+                    let primitive =
+                        compute_instructions(vec![v.clone()], ctx, host)?;
                     match primitive {
                         v @ Primitive::Error(_) => return Ok(v),
                         Primitive::Unit => {
@@ -245,39 +84,123 @@ fn compute_recur(
                 }
                 Ok(Primitive::Array(primitives))
             }
-            TreeNodeValue::ArrayAccess { index, array } => {
-                let error_message = || {
-                    format!("illegal index {index} for array access {array:?}")
-                };
-                match array {
-                    Value::Variable(v) => {
-                        let array =
-                            ctx.get(v).context("array not found in context")?;
-                        Ok(array.index_at(index.clone()))
+            TreeNodeValue::Map(map) => {
+                let mut primitives = BTreeMap::new();
+                for (k, v) in map {
+                    let primitive =
+                        compute_instructions(vec![v.clone()], ctx, host)?;
+                    match primitive {
+                        v @ Primitive::Error(_) => return Ok(v),
+                        Primitive::Unit => {
+                            return Ok(Primitive::Error(
+                                "cannot put unit () in a map",
+                            ))
+                        }
+                        _ => {
+                            primitives.insert(k.clone(), primitive);
+                        }
                     }
-                    Value::Array(array) => {
-                        if let Primitive::Int(index) = index {
-                            let index = *index as usize;
-                            let value =
-                                array.get(index).context(error_message())?;
-                            if index < array.len() {
-                                let primitive = compute_instructions(
-                                    vec![value.clone()],
-                                    ctx,
-                                )?;
-                                return Ok(primitive);
-                            }
+                }
+                Ok(Primitive::Map(primitives))
+            }
+            TreeNodeValue::Struct { name, fields } => {
+                let mut primitives = BTreeMap::new();
+                for (k, v) in fields {
+                    let primitive =
+                        compute_instructions(vec![v.clone()], ctx, host)?;
+                    match primitive {
+                        v @ Primitive::Error(_) => return Ok(v),
+                        Primitive::Unit => {
+                            return Ok(Primitive::Error(
+                                "cannot put unit () in a struct",
+                            ))
+                        }
+                        _ => {
+                            primitives.insert(k.clone(), primitive);
                         }
-                        Err(anyhow::Error::msg(error_message()))
                     }
-                    _ => Err(anyhow::Error::msg(error_message())),
+                }
+                Ok(Primitive::Struct { name: name.clone(), fields: primitives })
+            }
+            TreeNodeValue::ArrayAccess => {
+                let array = compute_recur(node.first_child(), ctx, host)?;
+                let index = compute_recur(node.last_child(), ctx, host)?;
+                Ok(array.index_at(index))
+            }
+            TreeNodeValue::SafeArrayAccess => {
+                let array = compute_recur(node.first_child(), ctx, host)?;
+                if matches!(array, Primitive::Null) {
+                    return Ok(Primitive::Null);
+                }
+                let index = compute_recur(node.last_child(), ctx, host)?;
+                match array.index_at(index) {
+                    Primitive::Error(_) => Ok(Primitive::Null),
+                    v => Ok(v),
                 }
             }
-            TreeNodeValue::VariableArrayAssign { name, index } => {
-                let mut v = compute_recur(node.first_child(), ctx)?;
+            TreeNodeValue::ArraySlice => {
+                let mut children = node.children();
+                let array = compute_recur(children.next(), ctx, host)?;
+                let start = compute_recur(children.next(), ctx, host)?;
+                let end = compute_recur(children.next(), ctx, host)?;
+                Ok(array.slice(&start, &end))
+            }
+            TreeNodeValue::MutatingFunction { fn_type, name } => {
+                let args = compute_recur(node.first_child(), ctx, host)?;
                 let array =
                     ctx.get_mut(name).context("array not found in context")?;
-                Ok(array.swap_mem(&mut v, index))
+                Ok(compute_array_mut_fn(fn_type, array, args))
+            }
+            TreeNodeValue::VariableArrayAssign { name, operator: None } => {
+                let mut children = node.children();
+                let v = compute_recur(children.next(), ctx, host)?;
+                let indexes = children
+                    .map(|c| compute_recur(Some(c), ctx, host))
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                let array =
+                    ctx.get_mut(name).context("array not found in context")?;
+                Ok(assign_at(array, &indexes, v))
+            }
+            TreeNodeValue::VariableArrayAssign {
+                name,
+                operator: Some(operator),
+            } => {
+                let indexes = node
+                    .children()
+                    .skip(1)
+                    .map(|c| compute_recur(Some(c), ctx, host))
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                let array =
+                    ctx.get(name).context("array not found in context")?;
+                let current = match indexes.split_first() {
+                    Some((first, rest)) => rest.iter().fold(
+                        array.index_at(first.clone()),
+                        |current, index| current.index_at(index.clone()),
+                    ),
+                    None => Primitive::Error("missing index"),
+                };
+                if let Primitive::Error(_) = current {
+                    return Ok(current);
+                }
+                let v = compute_recur(node.first_child(), ctx, host)?;
+                let v = match (operator, v) {
+                    (_, v @ (Primitive::Error(_) | Primitive::Unit)) => {
+                        return Ok(v)
+                    }
+                    (Operator::Add, v) => current + v,
+                    (Operator::Subtr, v) => current - v,
+                    (Operator::Mult, v) => current * v,
+                    (Operator::Div, v) => current / v,
+                    (Operator::Mod, v) => current % v,
+                    (Operator::Pow, v) => current.pow(v),
+                    _ => Primitive::Error("invalid assignment operator"),
+                };
+                if let Primitive::Error(_) = v {
+                    return Ok(v);
+                }
+                let array =
+                    ctx.get_mut(name).context("array not found in context")?;
+                Ok(assign_at(array, &indexes, v))
             }
         }
     } else {
@@ -285,6 +208,967 @@ fn compute_recur(
     }
 }
 
+fn compute_ops(
+    op: &Operator,
+    node: NodeRef<TreeNodeValue>,
+    ctx: &mut BTreeMap<String, Primitive>,
+    host: &mut dyn Host,
+) -> anyhow::Result<Primitive> {
+    match op {
+        Operator::Not => {
+            if node.children().count() != 1 {
+                return Err(Error::msg(
+                    "only one value allowed, no '!' possible",
+                ));
+            }
+            let left = compute_recur(node.first_child(), ctx, host)?;
+            Ok(!left)
+        }
+        Operator::Add => {
+            if node.children().count() == 1 {
+                return compute_recur(node.first_child(), ctx, host);
+            }
+            let left = compute_recur(node.first_child(), ctx, host)?;
+            let right = compute_recur(node.last_child(), ctx, host)?;
+            Ok(left + right)
+        }
+        Operator::Mult => {
+            if node.children().count() == 1 {
+                return compute_recur(node.first_child(), ctx, host);
+            }
+            let left = compute_recur(node.first_child(), ctx, host)?;
+            let right = compute_recur(node.last_child(), ctx, host)?;
+            Ok(left * right)
+        }
+        Operator::Mod => {
+            if node.children().count() == 1 {
+                return compute_recur(node.first_child(), ctx, host);
+            }
+            let left = compute_recur(node.first_child(), ctx, host)?;
+            let right = compute_recur(node.last_child(), ctx, host)?;
+            Ok(left % right)
+        }
+        Operator::Subtr => {
+            if node.children().count() == 1 {
+                return Ok(compute_recur(node.first_child(), ctx, host)?.neg());
+            }
+            let left = compute_recur(node.first_child(), ctx, host)?;
+            let right = compute_recur(node.last_child(), ctx, host)?;
+            Ok(left - right)
+        }
+        Operator::Pow => {
+            if node.children().count() == 1 {
+                return compute_recur(node.first_child(), ctx, host);
+            }
+            let left = compute_recur(node.first_child(), ctx, host)?;
+            let right = compute_recur(node.last_child(), ctx, host)?;
+            Ok(left.pow(right))
+        }
+        Operator::Div => {
+            if node.children().count() == 1 {
+                return compute_recur(node.first_child(), ctx, host);
+            }
+            let left = compute_recur(node.first_child(), ctx, host)?;
+            let right = compute_recur(node.last_child(), ctx, host)?;
+            Ok(left / right)
+        }
+        Operator::Equal => {
+            if node.children().count() == 1 {
+                return Err(Error::msg(
+                    "only one value, no '==' comparison possible",
+                ));
+            }
+            let left = compute_recur(node.first_child(), ctx, host)?;
+            let right = compute_recur(node.last_child(), ctx, host)?;
+            Ok(left.is_equal(&right))
+        }
+        Operator::And => {
+            if node.children().count() == 1 {
+                return Err(Error::msg(
+                    "only one value, no '&&' comparison possible",
+                ));
+            }
+            let left = compute_recur(node.first_child(), ctx, host)?;
+            let right = compute_recur(node.last_child(), ctx, host)?;
+            Ok(left.and(right))
+        }
+        Operator::Or => {
+            if node.children().count() == 1 {
+                return Err(Error::msg(
+                    "only one value, no '||' comparison possible",
+                ));
+            }
+            let left = compute_recur(node.first_child(), ctx, host)?;
+            let right = compute_recur(node.last_child(), ctx, host)?;
+            Ok(left.or(right))
+        }
+        Operator::NotEqual => {
+            if node.children().count() == 1 {
+                return Err(Error::msg(
+                    "only one value, no '!=' comparison possible",
+                ));
+            }
+            let left = compute_recur(node.first_child(), ctx, host)?;
+            let right = compute_recur(node.last_child(), ctx, host)?;
+            Ok(left.is_equal(&right).not())
+        }
+        Operator::Less => {
+            if node.children().count() == 1 {
+                return Err(Error::msg(
+                    "only one value, no '<' comparison possible",
+                ));
+            }
+            let left = compute_recur(node.first_child(), ctx, host)?;
+            let right = compute_recur(node.last_child(), ctx, host)?;
+            Ok(left.is_less_than(&right))
+        }
+        Operator::Greater => {
+            if node.children().count() == 1 {
+                return Err(Error::msg(
+                    "only one value, no '>' comparison possible",
+                ));
+            }
+            let left = compute_recur(node.first_child(), ctx, host)?;
+            let right = compute_recur(node.last_child(), ctx, host)?;
+            Ok(left.is_greater_than(&right))
+        }
+        Operator::GreaterOrEqual => {
+            if node.children().count() == 1 {
+                return Err(Error::msg(
+                    "only one value, no '>=' comparison possible",
+                ));
+            }
+            let left = compute_recur(node.first_child(), ctx, host)?;
+            let right = compute_recur(node.last_child(), ctx, host)?;
+            Ok(left.is_greater_or_equal(&right))
+        }
+        Operator::LessOrEqual => {
+            if node.children().count() == 1 {
+                return Err(Error::msg(
+                    "only one value, no '<=' comparison possible",
+                ));
+            }
+            let left = compute_recur(node.first_child(), ctx, host)?;
+            let right = compute_recur(node.last_child(), ctx, host)?;
+            Ok(left.is_less_or_equal(&right))
+        }
+        Operator::BitAnd => {
+            if node.children().count() == 1 {
+                return Err(Error::msg(
+                    "only one value, no '&' operation possible",
+                ));
+            }
+            let left = compute_recur(node.first_child(), ctx, host)?;
+            let right = compute_recur(node.last_child(), ctx, host)?;
+            Ok(left.bitand(right))
+        }
+        Operator::BitOr => {
+            if node.children().count() == 1 {
+                return Err(Error::msg(
+                    "only one value, no '|' operation possible",
+                ));
+            }
+            let left = compute_recur(node.first_child(), ctx, host)?;
+            let right = compute_recur(node.last_child(), ctx, host)?;
+            Ok(left.bitor(right))
+        }
+        Operator::BitXor => {
+            if node.children().count() == 1 {
+                return Err(Error::msg(
+                    "only one value, no 'xor' operation possible",
+                ));
+            }
+            let left = compute_recur(node.first_child(), ctx, host)?;
+            let right = compute_recur(node.last_child(), ctx, host)?;
+            Ok(left.bitxor(right))
+        }
+        Operator::ShiftLeft => {
+            if node.children().count() == 1 {
+                return Err(Error::msg(
+                    "only one value, no '<<' operation possible",
+                ));
+            }
+            let left = compute_recur(node.first_child(), ctx, host)?;
+            let right = compute_recur(node.last_child(), ctx, host)?;
+            Ok(left.shl(right))
+        }
+        Operator::ShiftRight => {
+            if node.children().count() == 1 {
+                return Err(Error::msg(
+                    "only one value, no '>>' operation possible",
+                ));
+            }
+            let left = compute_recur(node.first_child(), ctx, host)?;
+            let right = compute_recur(node.last_child(), ctx, host)?;
+            Ok(left.shr(right))
+        }
+        Operator::BitNot => {
+            if node.children().count() != 1 {
+                return Err(Error::msg(
+                    "only one value allowed, no '~' possible",
+                ));
+            }
+            let left = compute_recur(node.first_child(), ctx, host)?;
+            Ok(left.bit_not())
+        }
+        Operator::RegexMatch => {
+            if node.children().count() == 1 {
+                return Err(Error::msg(
+                    "only one value, no '=~' comparison possible",
+                ));
+            }
+            let left = compute_recur(node.first_child(), ctx, host)?;
+            let right = compute_recur(node.last_child(), ctx, host)?;
+            match (left, right) {
+                (Primitive::String(s), Primitive::String(re)) => {
+                    Ok(pattern::is_match(&s, &re))
+                }
+                _ => Ok(Primitive::Error(
+                    "'=~' expects a string and a regex e.g s =~ \"[0-9]+\"",
+                )),
+            }
+        }
+        Operator::NullCoalesce => {
+            if node.children().count() == 1 {
+                return Err(Error::msg(
+                    "only one value, no '??' operation possible",
+                ));
+            }
+            let left = compute_recur(node.first_child(), ctx, host)?;
+            if matches!(left, Primitive::Null) {
+                compute_recur(node.last_child(), ctx, host)
+            } else {
+                Ok(left)
+            }
+        }
+    }
+}
+
+fn compute_builtin_fn(
+    fn_type: &BuiltInFunctionType,
+    v: Primitive,
+    ctx: &mut BTreeMap<String, Primitive>,
+    host: &mut dyn Host,
+) -> anyhow::Result<Primitive> {
+    match fn_type {
+        BuiltInFunctionType::Sqrt => Ok(v.sqrt()),
+        BuiltInFunctionType::Abs => Ok(v.abs()),
+        BuiltInFunctionType::Log => Ok(v.log()),
+        BuiltInFunctionType::Ln => Ok(v.ln()),
+        BuiltInFunctionType::Sin => Ok(v.sin()),
+        BuiltInFunctionType::Cos => Ok(v.cos()),
+        BuiltInFunctionType::Tan => Ok(v.tan()),
+        BuiltInFunctionType::Length => Ok(v.len()),
+        BuiltInFunctionType::Hex => Ok(to_radix(&v, 16)),
+        BuiltInFunctionType::Bin => Ok(to_radix(&v, 2)),
+        BuiltInFunctionType::Oct => Ok(to_radix(&v, 8)),
+        BuiltInFunctionType::Println => {
+            println!("{v}");
+            Ok(Primitive::Unit)
+        }
+        BuiltInFunctionType::Print => {
+            print!("{v}");
+            Ok(Primitive::Unit)
+        }
+        BuiltInFunctionType::Include => match v {
+            Primitive::String(file_path) => {
+                let curr_path = std::env::current_dir()
+                    .context("no current dir! wasn't expected")?;
+                let temp_path = Path::new(&file_path);
+                if temp_path.is_absolute() || temp_path.exists() {
+                    let parent =
+                        temp_path.parent().context("parent doesn't exist")?;
+
+                    std::env::set_current_dir(PathBuf::from(&parent))?;
+                }
+
+                let res = temp_path
+                    .file_name()
+                    .context("file name not found")
+                    .and_then(|p| read_to_string(p).map_err(anyhow::Error::new))
+                    .and_then(move |file| compute_with_host(&file, ctx, host));
+                std::env::set_current_dir(curr_path)?; // todo this might be quiet fragile
+                res
+            }
+            _ => Ok(Primitive::Error("wrong include statement")),
+        },
+        BuiltInFunctionType::CacheGet
+        | BuiltInFunctionType::CachePut
+        | BuiltInFunctionType::CacheDel
+        | BuiltInFunctionType::CacheList
+        | BuiltInFunctionType::CacheUse => {
+            Ok(compute_cache_fn(fn_type, v, host))
+        }
+        BuiltInFunctionType::Exec | super::BuiltInFunctionType::Sh => {
+            compute_exec_fn(fn_type, v)
+        }
+        BuiltInFunctionType::Assert | BuiltInFunctionType::AssertEq => {
+            compute_assert_fn(fn_type, v)
+        }
+        BuiltInFunctionType::Push
+        | BuiltInFunctionType::Pop
+        | BuiltInFunctionType::Insert
+        | BuiltInFunctionType::RemoveAt => {
+            Ok(Primitive::Error("first argument must be a variable"))
+        }
+        BuiltInFunctionType::Concat
+        | BuiltInFunctionType::Reverse
+        | BuiltInFunctionType::Sort
+        | BuiltInFunctionType::Unique
+        | BuiltInFunctionType::Contains
+        | BuiltInFunctionType::IndexOf
+        | BuiltInFunctionType::Zip
+        | BuiltInFunctionType::Flatten
+        | BuiltInFunctionType::Sum
+        | BuiltInFunctionType::Avg => Ok(compute_array_fn(fn_type, v)),
+        BuiltInFunctionType::Now
+        | BuiltInFunctionType::Timestamp
+        | BuiltInFunctionType::FormatDate
+        | BuiltInFunctionType::ParseDate
+        | BuiltInFunctionType::FormatDuration
+        | BuiltInFunctionType::Sleep => compute_date_fn(fn_type, v),
+        BuiltInFunctionType::Random
+        | BuiltInFunctionType::RandomInt
+        | BuiltInFunctionType::Shuffle
+        | BuiltInFunctionType::Choice
+        | BuiltInFunctionType::Seed => Ok(compute_random_fn(fn_type, v)),
+        BuiltInFunctionType::Matches
+        | BuiltInFunctionType::FindAll
+        | BuiltInFunctionType::Captures
+        | BuiltInFunctionType::ReplaceRe => Ok(compute_regex_fn(fn_type, v)),
+        BuiltInFunctionType::Env
+        | BuiltInFunctionType::EnvAll
+        | BuiltInFunctionType::SetEnv => Ok(compute_env_fn(fn_type, v)),
+        BuiltInFunctionType::Defined => {
+            Ok(Primitive::Bool(!matches!(v, Primitive::Null | Primitive::Unit)))
+        }
+    }
+}
+
+/// dates are milliseconds since the epoch, durations are milliseconds
+fn compute_date_fn(
+    fn_type: &BuiltInFunctionType,
+    args: Primitive,
+) -> anyhow::Result<Primitive> {
+    let args = if let Primitive::Array(args) = args {
+        args
+    } else {
+        return Ok(Primitive::Error("invalid arguments"));
+    };
+    let res = match (fn_type, &args[..]) {
+        (BuiltInFunctionType::Now, []) => Primitive::Int(date::now()),
+        (BuiltInFunctionType::Timestamp, []) => {
+            Primitive::Int(date::now() / 1000)
+        }
+        (BuiltInFunctionType::FormatDate, [Primitive::Int(ts)]) => {
+            date::format_date(*ts, date::DEFAULT_DATE_FORMAT)
+                .map_or(Primitive::Error("invalid date"), Primitive::String)
+        }
+        (
+            BuiltInFunctionType::FormatDate,
+            [Primitive::Int(ts), Primitive::String(fmt)],
+        ) => date::format_date(*ts, fmt).map_or(
+            Primitive::Error("invalid date or date format"),
+            Primitive::String,
+        ),
+        (BuiltInFunctionType::ParseDate, [Primitive::String(d)]) => {
+            date::parse_date(d, None).map_or(Primitive::Null, Primitive::Int)
+        }
+        (
+            BuiltInFunctionType::ParseDate,
+            [Primitive::String(d), Primitive::String(fmt)],
+        ) => date::parse_date(d, Some(fmt))
+            .map_or(Primitive::Null, Primitive::Int),
+        (BuiltInFunctionType::FormatDuration, [Primitive::Int(ms)]) => {
+            Primitive::String(date::format_duration(*ms))
+        }
+        (BuiltInFunctionType::Sleep, [Primitive::Int(ms)]) if *ms >= 0 => {
+            date::sleep(u64::try_from(*ms).unwrap_or(u64::MAX))?;
+            Primitive::Unit
+        }
+        (BuiltInFunctionType::Now, _) => {
+            Primitive::Error("invalid arguments. e.g now()")
+        }
+        (BuiltInFunctionType::Timestamp, _) => {
+            Primitive::Error("invalid arguments. e.g timestamp()")
+        }
+        (BuiltInFunctionType::FormatDate, _) => Primitive::Error(
+            r#"invalid arguments. e.g format_date(now(), "%Y-%m-%d")"#,
+        ),
+        (BuiltInFunctionType::ParseDate, _) => Primitive::Error(
+            r#"invalid arguments. e.g parse_date("01/12/2022", "%d/%m/%Y")"#,
+        ),
+        (BuiltInFunctionType::FormatDuration, _) => {
+            Primitive::Error("invalid arguments. e.g format_duration(1h30m)")
+        }
+        (BuiltInFunctionType::Sleep, _) => {
+            Primitive::Error("invalid arguments. e.g sleep(500ms)")
+        }
+        _ => Primitive::Error("not a date function"),
+    };
+    Ok(res)
+}
+
+fn compute_random_fn(
+    fn_type: &BuiltInFunctionType,
+    args: Primitive,
+) -> Primitive {
+    let args = if let Primitive::Array(args) = args {
+        args
+    } else {
+        return Primitive::Error("invalid arguments");
+    };
+    match (fn_type, &args[..]) {
+        (BuiltInFunctionType::Random, []) => {
+            Primitive::Double(random::random())
+        }
+        (
+            BuiltInFunctionType::RandomInt,
+            [Primitive::Int(min), Primitive::Int(max)],
+        ) if min <= max => Primitive::Int(random::random_int(*min, *max)),
+        (BuiltInFunctionType::Shuffle, [Primitive::Array(arr)]) => {
+            let mut arr = arr.clone();
+            random::shuffle(&mut arr);
+            Primitive::Array(arr)
+        }
+        (BuiltInFunctionType::Choice, [Primitive::Array(arr)]) => {
+            random::choice(arr).cloned().unwrap_or(Primitive::Null)
+        }
+        (BuiltInFunctionType::Seed, [Primitive::Int(seed)]) => {
+            // only the lowest bits are used for a negative or big seed
+            random::seed(*seed as u64);
+            Primitive::Unit
+        }
+        (BuiltInFunctionType::Random, _) => {
+            Primitive::Error("invalid arguments. e.g random()")
+        }
+        (BuiltInFunctionType::RandomInt, _) => {
+            Primitive::Error("invalid arguments. e.g random_int(1, 6)")
+        }
+        (BuiltInFunctionType::Shuffle, _) => {
+            Primitive::Error("invalid arguments. e.g shuffle([1, 2, 3])")
+        }
+        (BuiltInFunctionType::Choice, _) => {
+            Primitive::Error("invalid arguments. e.g choice([1, 2, 3])")
+        }
+        (BuiltInFunctionType::Seed, _) => {
+            Primitive::Error("invalid arguments. e.g seed(42)")
+        }
+        _ => Primitive::Error("not a random function"),
+    }
+}
+
+fn compute_regex_fn(
+    fn_type: &BuiltInFunctionType,
+    args: Primitive,
+) -> Primitive {
+    let args = if let Primitive::Array(args) = args {
+        args
+    } else {
+        return Primitive::Error("invalid arguments");
+    };
+    match (fn_type, &args[..]) {
+        (
+            BuiltInFunctionType::Matches,
+            [Primitive::String(s), Primitive::String(re)],
+        ) => pattern::is_match(s, re),
+        (
+            BuiltInFunctionType::FindAll,
+            [Primitive::String(s), Primitive::String(re)],
+        ) => pattern::find_all(s, re),
+        (
+            BuiltInFunctionType::Captures,
+            [Primitive::String(s), Primitive::String(re)],
+        ) => pattern::captures(s, re),
+        (
+            BuiltInFunctionType::ReplaceRe,
+            [Primitive::String(s), Primitive::String(re), Primitive::String(repl)],
+        ) => pattern::replace_all(s, re, repl),
+        (BuiltInFunctionType::Matches, _) => {
+            Primitive::Error(r#"invalid arguments. e.g matches("abc", "^a")"#)
+        }
+        (BuiltInFunctionType::FindAll, _) => Primitive::Error(
+            r#"invalid arguments. e.g find_all("a1b2", "[0-9]")"#,
+        ),
+        (BuiltInFunctionType::Captures, _) => Primitive::Error(
+            r#"invalid arguments. e.g captures("k=v", "(?P<key>\w+)=(\w+)")"#,
+        ),
+        (BuiltInFunctionType::ReplaceRe, _) => Primitive::Error(
+            r#"invalid arguments. e.g replace_re("a1b2", "[0-9]", "_")"#,
+        ),
+        _ => Primitive::Error("not a regex function"),
+    }
+}
+
+/// variables set with set_env are passed to every command of the session
+fn compute_env_fn(fn_type: &BuiltInFunctionType, args: Primitive) -> Primitive {
+    let args = if let Primitive::Array(args) = args {
+        args
+    } else {
+        return Primitive::Error("invalid arguments");
+    };
+    match (fn_type, &args[..]) {
+        (BuiltInFunctionType::Env, [Primitive::String(key)]) => {
+            get_env(key).map_or(Primitive::Null, Primitive::String)
+        }
+        (BuiltInFunctionType::EnvAll, []) => Primitive::Map(
+            get_all_envs()
+                .into_iter()
+                .map(|(k, v)| (k, Primitive::String(v)))
+                .collect(),
+        ),
+        (BuiltInFunctionType::SetEnv, [Primitive::String(key), value])
+            if !key.is_empty() && !key.contains('=') =>
+        {
+            set_session_env(key, &value.to_string());
+            Primitive::Unit
+        }
+        (BuiltInFunctionType::Env, _) => {
+            Primitive::Error(r#"invalid arguments. e.g env("HOME")"#)
+        }
+        (BuiltInFunctionType::EnvAll, _) => {
+            Primitive::Error("invalid arguments. e.g env_all()")
+        }
+        (BuiltInFunctionType::SetEnv, _) => Primitive::Error(
+            r#"invalid arguments. e.g set_env("AWS_PROFILE", "dev")"#,
+        ),
+        _ => Primitive::Error("not an env function"),
+    }
+}
+
+/// assign a value in nested arrays/maps e.g `m[i][j] = v`
+fn assign_at(
+    target: &mut Primitive,
+    indexes: &[Primitive],
+    mut value: Primitive,
+) -> Primitive {
+    match indexes {
+        [] => Primitive::Error("missing index"),
+        [index] => target.swap_mem(&mut value, index),
+        [index, rest @ ..] => {
+            let mut inner = target.index_at(index.clone());
+            if matches!(inner, Primitive::Error(_)) {
+                return inner;
+            }
+            let res = assign_at(&mut inner, rest, value);
+            if !matches!(res, Primitive::Error(_)) {
+                target.swap_mem(&mut inner, index);
+            }
+            res
+        }
+    }
+}
+
+/// update an array in place e.g `push(arr, 1)`
+fn compute_array_mut_fn(
+    fn_type: &BuiltInFunctionType,
+    target: &mut Primitive,
+    args: Primitive,
+) -> Primitive {
+    let (arr, args) = match (target, args) {
+        (Primitive::Array(arr), Primitive::Array(args)) => (arr, args),
+        _ => {
+            return Primitive::Error(
+                "call to a mutating function on a non array value",
+            )
+        }
+    };
+    match (fn_type, &args[..]) {
+        (_, [Primitive::Unit | Primitive::Error(_), ..])
+        | (_, [_, Primitive::Unit | Primitive::Error(_)]) => {
+            Primitive::Error("invalid value for array")
+        }
+        (BuiltInFunctionType::Push | BuiltInFunctionType::Insert, _)
+            if limits::exceeds_array_len(arr.len() + 1) =>
+        {
+            Primitive::Error(limits::ARRAY_TOO_LONG)
+        }
+        (BuiltInFunctionType::Push, [v]) => {
+            arr.push(v.clone());
+            Primitive::Int(arr.len() as i128)
+        }
+        (BuiltInFunctionType::Pop, []) => arr
+            .pop()
+            .unwrap_or(Primitive::Error("call to pop() on an empty array")),
+        (BuiltInFunctionType::Insert, [Primitive::Int(idx), v]) => {
+            // inserting at the end is allowed
+            let len = arr.len();
+            let idx = if *idx == len as i128 {
+                Some(len)
+            } else {
+                normalize_index(*idx, len)
+            };
+            match idx {
+                Some(idx) => {
+                    arr.insert(idx, v.clone());
+                    Primitive::Int(arr.len() as i128)
+                }
+                None => Primitive::Error("index out of range"),
+            }
+        }
+        (BuiltInFunctionType::RemoveAt, [Primitive::Int(idx)]) => {
+            match normalize_index(*idx, arr.len()) {
+                Some(idx) => arr.remove(idx),
+                None => Primitive::Error("index out of range"),
+            }
+        }
+        (BuiltInFunctionType::Push, _) => {
+            Primitive::Error("invalid arguments. e.g push(arr, 1)")
+        }
+        (BuiltInFunctionType::Pop, _) => {
+            Primitive::Error("invalid arguments. e.g pop(arr)")
+        }
+        (BuiltInFunctionType::Insert, _) => {
+            Primitive::Error("invalid arguments. e.g insert(arr, 0, 1)")
+        }
+        (BuiltInFunctionType::RemoveAt, _) => {
+            Primitive::Error("invalid arguments. e.g remove_at(arr, 0)")
+        }
+        _ => Primitive::Error("not a mutating function"),
+    }
+}
+
+fn compute_array_fn(
+    fn_type: &BuiltInFunctionType,
+    args: Primitive,
+) -> Primitive {
+    let is_number = |p: &Primitive| {
+        matches!(
+            p,
+            Primitive::Int(_)
+                | Primitive::Double(_)
+                | Primitive::BigInt(_)
+                | Primitive::Rational(_)
+        )
+    };
+    let is_equal = |l: &Primitive, r: &Primitive| {
+        matches!(l.is_equal(r), Primitive::Bool(true))
+    };
+    let args = if let Primitive::Array(args) = args {
+        args
+    } else {
+        return Primitive::Error("invalid arguments");
+    };
+    match (fn_type, &args[..]) {
+        (
+            BuiltInFunctionType::Concat,
+            [l @ Primitive::Array(_), r @ Primitive::Array(_)]
+            | [l @ Primitive::String(_), r @ Primitive::String(_)],
+        ) => l.clone() + r.clone(),
+        (BuiltInFunctionType::Reverse, [Primitive::Array(arr)]) => {
+            Primitive::Array(arr.iter().rev().cloned().collect())
+        }
+        (BuiltInFunctionType::Reverse, [Primitive::String(s)]) => {
+            Primitive::String(s.chars().rev().collect())
+        }
+        (BuiltInFunctionType::Sort, [Primitive::Array(arr)]) => {
+            let mut arr = arr.clone();
+            let mut comparable = true;
+            arr.sort_by(|l, r| {
+                l.partial_cmp(r).unwrap_or_else(|| {
+                    comparable = false;
+                    std::cmp::Ordering::Equal
+                })
+            });
+            if comparable {
+                Primitive::Array(arr)
+            } else {
+                Primitive::Error("cannot sort values of different types")
+            }
+        }
+        (BuiltInFunctionType::Unique, [Primitive::Array(arr)]) => {
+            let mut unique: Vec<Primitive> = vec![];
+            for p in arr {
+                if !unique.iter().any(|u| is_equal(u, p)) {
+                    unique.push(p.clone());
+                }
+            }
+            Primitive::Array(unique)
+        }
+        (BuiltInFunctionType::Contains, [Primitive::Array(arr), v]) => {
+            Primitive::Bool(arr.iter().any(|p| is_equal(p, v)))
+        }
+        (
+            BuiltInFunctionType::Contains,
+            [Primitive::String(s), Primitive::String(sub)],
+        ) => Primitive::Bool(s.contains(sub.as_str())),
+        (
+            BuiltInFunctionType::Contains,
+            [Primitive::Map(map), Primitive::String(key)],
+        ) => Primitive::Bool(map.contains_key(key)),
+        (BuiltInFunctionType::IndexOf, [Primitive::Array(arr), v]) => {
+            Primitive::Int(
+                arr.iter()
+                    .position(|p| is_equal(p, v))
+                    .map_or(-1, |i| i as i128),
+            )
+        }
+        (
+            BuiltInFunctionType::IndexOf,
+            [Primitive::String(s), Primitive::String(sub)],
+        ) => Primitive::Int(
+            s.find(sub.as_str()).map_or(-1, |i| s[..i].chars().count() as i128),
+        ),
+        (
+            BuiltInFunctionType::Zip,
+            [Primitive::Array(l), Primitive::Array(r)],
+        ) => Primitive::Array(
+            l.iter()
+                .zip(r.iter())
+                .map(|(l, r)| Primitive::Array(vec![l.clone(), r.clone()]))
+                .collect(),
+        ),
+        (BuiltInFunctionType::Flatten, [Primitive::Array(arr)])
+            if limits::exceeds_array_len(
+                arr.iter()
+                    .map(|p| match p {
+                        Primitive::Array(inner) => inner.len(),
+                        _ => 1,
+                    })
+                    .sum(),
+            ) =>
+        {
+            Primitive::Error(limits::ARRAY_TOO_LONG)
+        }
+        (BuiltInFunctionType::Flatten, [Primitive::Array(arr)]) => {
+            Primitive::Array(
+                arr.iter()
+                    .flat_map(|p| match p {
+                        Primitive::Array(inner) => inner.clone(),
+                        p => vec![p.clone()],
+                    })
+                    .collect(),
+            )
+        }
+        (BuiltInFunctionType::Sum, [Primitive::Array(arr)])
+            if arr.iter().all(is_number) =>
+        {
+            arr.iter().cloned().sum()
+        }
+        (BuiltInFunctionType::Avg, [Primitive::Array(arr)])
+            if !arr.is_empty() && arr.iter().all(is_number) =>
+        {
+            let sum: Primitive = arr.iter().cloned().sum();
+            if number_mode() == NumberMode::Rational {
+                sum / Primitive::Int(arr.len() as i128)
+            } else {
+                Primitive::Double(
+                    to_f64(&sum).unwrap_or(f64::NAN) / arr.len() as f64,
+                )
+            }
+        }
+        (BuiltInFunctionType::Concat, _) => {
+            Primitive::Error("invalid arguments. e.g concat([1], [2])")
+        }
+        (BuiltInFunctionType::Reverse, _) => {
+            Primitive::Error("invalid arguments. e.g reverse([1, 2])")
+        }
+        (BuiltInFunctionType::Sort, _) => {
+            Primitive::Error("invalid arguments. e.g sort([2, 1])")
+        }
+        (BuiltInFunctionType::Unique, _) => {
+            Primitive::Error("invalid arguments. e.g unique([1, 1])")
+        }
+        (BuiltInFunctionType::Contains, _) => {
+            Primitive::Error("invalid arguments. e.g contains([1, 2], 1)")
+        }
+        (BuiltInFunctionType::IndexOf, _) => {
+            Primitive::Error("invalid arguments. e.g index_of([1, 2], 2)")
+        }
+        (BuiltInFunctionType::Zip, _) => {
+            Primitive::Error("invalid arguments. e.g zip([1, 2], [3, 4])")
+        }
+        (BuiltInFunctionType::Flatten, _) => {
+            Primitive::Error("invalid arguments. e.g flatten([[1], [2]])")
+        }
+        (BuiltInFunctionType::Sum, _) => {
+            Primitive::Error("invalid arguments. e.g sum([1, 2])")
+        }
+        (BuiltInFunctionType::Avg, _) => {
+            Primitive::Error("invalid arguments. e.g avg([1, 2])")
+        }
+        _ => Primitive::Error("not an array function"),
+    }
+}
+
+fn compute_cache_fn(
+    fn_type: &BuiltInFunctionType,
+    args: Primitive,
+    host: &mut dyn Host,
+) -> Primitive {
+    let args = if let Primitive::Array(args) = args {
+        args
+    } else {
+        return Primitive::Error("invalid arguments");
+    };
+    match (fn_type, &args[..]) {
+        (BuiltInFunctionType::CacheGet, [Primitive::String(key)]) => host
+            .cache_get(key)
+            .map(Primitive::String)
+            .unwrap_or(Primitive::Error("key not found in cache")),
+        (BuiltInFunctionType::CacheGet, _) => {
+            Primitive::Error("invalid arguments. e.g cache_get(\"drc\")")
+        }
+        (
+            BuiltInFunctionType::CachePut,
+            [Primitive::String(key), value, aliases @ ..],
+        ) => {
+            let mut keys = vec![key.as_str()];
+            match aliases {
+                [] => (),
+                [Primitive::Array(aliases)] => {
+                    for alias in aliases {
+                        if let Primitive::String(alias) = alias {
+                            keys.push(alias.as_str());
+                        } else {
+                            return Primitive::Error("alias must be a string");
+                        }
+                    }
+                }
+                _ => return Primitive::Error("aliases must be an array"),
+            }
+            keys.retain(|k| !k.is_empty());
+            host.cache_put(keys, &value.to_string())
+                .map(Primitive::String)
+                .unwrap_or(Primitive::Error("could not insert!"))
+        }
+        (BuiltInFunctionType::CachePut, _) => Primitive::Error(
+            "invalid arguments. e.g cache_put(\"drc\", \"docker-compose\", [\"drcomp\"])",
+        ),
+        (BuiltInFunctionType::CacheDel, [Primitive::String(key)]) => host
+            .cache_del(key)
+            .map(Primitive::String)
+            .unwrap_or(Primitive::Error("key not found in cache")),
+        (BuiltInFunctionType::CacheDel, _) => {
+            Primitive::Error("invalid arguments. e.g cache_del(\"drc\")")
+        }
+        (BuiltInFunctionType::CacheList, [] | [Primitive::String(_)]) => {
+            let cache_name = match &args[..] {
+                [Primitive::String(cache_name)] => Some(cache_name.as_str()),
+                _ => None,
+            };
+            host.cache_list(cache_name)
+                .map(|values| {
+                    Primitive::Map(
+                        values
+                            .into_iter()
+                            .map(|(k, v)| (k, Primitive::String(v)))
+                            .collect(),
+                    )
+                })
+                .unwrap_or(Primitive::Error("cache not found"))
+        }
+        (BuiltInFunctionType::CacheList, _) => {
+            Primitive::Error("invalid arguments. e.g cache_list(\"linux\")")
+        }
+        (BuiltInFunctionType::CacheUse, [Primitive::String(cache_name)]) => {
+            host.cache_use(cache_name)
+                .map(Primitive::String)
+                .unwrap_or(Primitive::Error("could not use cache"))
+        }
+        (BuiltInFunctionType::CacheUse, _) => {
+            Primitive::Error("invalid arguments. e.g cache_use(\"linux\")")
+        }
+        _ => Primitive::Error("not a cache function"),
+    }
+}
+
+fn exec_options(
+    options: &BTreeMap<String, Primitive>,
+) -> Result<ExecOptions, &'static str> {
+    let mut exec_options = ExecOptions::default();
+    for (key, value) in options {
+        match (key.as_str(), value) {
+            ("env", Primitive::Map(envs)) => {
+                exec_options.envs =
+                    envs.iter().map(|(k, v)| (k.clone(), v.to_string())).collect();
+            }
+            ("cwd", Primitive::String(cwd)) => {
+                exec_options.cwd = Some(cwd.clone())
+            }
+            ("stdin", Primitive::String(input)) => {
+                exec_options.stdin = Some(input.clone())
+            }
+            ("timeout", Primitive::Int(ms)) if *ms >= 0 => {
+                exec_options.timeout = Some(Duration::from_millis(*ms as u64))
+            }
+            _ => return Err("invalid option. valid options are env (map), cwd (string), stdin (string) and timeout (ms)"),
+        }
+    }
+    Ok(exec_options)
+}
+
+fn compute_exec_fn(
+    fn_type: &BuiltInFunctionType,
+    args: Primitive,
+) -> anyhow::Result<Primitive> {
+    let (command, options) = match &args {
+        Primitive::Array(args) => match &args[..] {
+            [Primitive::String(command)] => (command, ExecOptions::default()),
+            [Primitive::String(command), Primitive::Map(options)] => {
+                match exec_options(options) {
+                    Ok(options) => (command, options),
+                    Err(e) => return Ok(Primitive::Error(e)),
+                }
+            }
+            _ => return Ok(Primitive::Error(
+                "invalid arguments. e.g exec(\"ls -la\", {\"cwd\": \"/tmp\"})",
+            )),
+        },
+        _ => return Ok(Primitive::Error("invalid arguments")),
+    };
+    let output = if let BuiltInFunctionType::Sh = fn_type {
+        capture_shell(command, &options)?
+    } else {
+        capture_command(command, &options)?
+    };
+    Ok(Primitive::Map(BTreeMap::from([
+        ("code".to_string(), Primitive::Int(output.code as i128)),
+        ("stdout".to_string(), Primitive::String(output.stdout)),
+        ("stderr".to_string(), Primitive::String(output.stderr)),
+    ])))
+}
+
+fn compute_assert_fn(
+    fn_type: &BuiltInFunctionType,
+    args: Primitive,
+) -> anyhow::Result<Primitive> {
+    let args = if let Primitive::Array(args) = args {
+        args
+    } else {
+        anyhow::bail!("invalid arguments");
+    };
+    match (fn_type, &args[..]) {
+        (BuiltInFunctionType::Assert, [Primitive::Bool(cond), msg @ ..])
+            if msg.len() <= 1 =>
+        {
+            if !cond {
+                let msg = msg.first().map(|m| m.to_string()).unwrap_or_default();
+                anyhow::bail!("assertion failed: {msg}");
+            }
+            Ok(Primitive::Unit)
+        }
+        (BuiltInFunctionType::Assert, _) => anyhow::bail!(
+            "invalid arguments. e.g assert(x > 1, \"x should be greater than 1\")"
+        ),
+        (BuiltInFunctionType::AssertEq, [left, right, msg @ ..])
+            if msg.len() <= 1 =>
+        {
+            if !matches!(left.is_equal(right), Primitive::Bool(true)) {
+                let msg = msg.first().map(|m| m.to_string()).unwrap_or_default();
+                anyhow::bail!(
+                    "assertion failed: {msg}\n  left: {left}\n right: {right}"
+                );
+            }
+            Ok(Primitive::Unit)
+        }
+        (BuiltInFunctionType::AssertEq, _) => {
+            anyhow::bail!("invalid arguments. e.g assert_eq(x, 1)")
+        }
+        _ => Ok(Primitive::Error("not an assert function")),
+    }
+}
+
 fn value_to_tree(
     value: Value,
     ctx: &mut BTreeMap<String, Primitive>,
@@ -307,46 +1191,79 @@ fn value_to_tree(
 fn compute_instructions(
     instructions: Vec<Value>,
     ctx: &mut BTreeMap<String, Primitive>,
+    host: &mut dyn Host,
 ) -> anyhow::Result<Primitive> {
     let mut result = Primitive::Unit;
 
     fn compute(
         instruction: Value,
         ctx: &mut BTreeMap<String, Primitive>,
+        host: &mut dyn Host,
     ) -> anyhow::Result<Primitive> {
         let tree = value_to_tree(instruction, ctx)?;
 
         let root = tree.root();
 
-        compute_recur(root, ctx)
+        let result = compute_recur(root, ctx, host)?;
+        limits::check_size(&result)?;
+        limits::check_error(&result)?;
+        Ok(result)
     }
 
     for instruction in instructions {
         match instruction {
             Value::IfExpr { cond, exprs, else_expr } => {
-                let cond = compute(*cond, ctx)?;
+                let cond = compute(*cond, ctx, host)?;
                 if matches!(cond, Primitive::Bool(true)) {
                     for instruction in exprs {
-                        result = compute(instruction, ctx)?;
+                        result = compute(instruction, ctx, host)?;
                     }
                 } else if let Some(else_expr) = else_expr {
                     for instruction in else_expr {
-                        result = compute(instruction, ctx)?;
+                        result = compute(instruction, ctx, host)?;
+                    }
+                } else {
+                    result = Primitive::Unit;
+                }
+            }
+            Value::MatchExpr { value, arms } => {
+                let value = compute(*value, ctx, host)?;
+                result = Primitive::Unit;
+                for MatchArm { patterns, exprs } in arms {
+                    let mut matched = patterns.is_empty();
+                    for pattern in patterns {
+                        let pattern = compute(pattern, ctx, host)?;
+                        if matches!(
+                            pattern.is_equal(&value),
+                            Primitive::Bool(true)
+                        ) {
+                            matched = true;
+                            break;
+                        }
+                    }
+                    if matched {
+                        for instruction in exprs {
+                            result = compute(instruction, ctx, host)?;
+                        }
+                        break;
                     }
                 }
             }
             Value::WhileExpr { cond, exprs } => {
+                let mut iteration = 0;
                 while matches!(
-                    compute(*cond.clone(), ctx)?,
+                    compute(*cond.clone(), ctx, host)?,
                     Primitive::Bool(true)
                 ) {
+                    iteration += 1;
+                    limits::check_loop(iteration)?;
                     for instruction in &exprs {
-                        result = compute(instruction.clone(), ctx)?;
+                        result = compute(instruction.clone(), ctx, host)?;
                     }
                 }
             }
             _ => {
-                result = compute(instruction, ctx)?;
+                result = compute(instruction, ctx, host)?;
             }
         }
     }
@@ -357,16 +1274,27 @@ fn compute_instructions(
 pub fn compute(
     s: &str,
     ctx: &mut BTreeMap<String, Primitive>,
+) -> anyhow::Result<Primitive> {
+    compute_with_host(s, ctx, &mut ())
+}
+
+pub fn compute_with_host(
+    s: &str,
+    ctx: &mut BTreeMap<String, Primitive>,
+    host: &mut dyn Host,
 ) -> anyhow::Result<Primitive> {
     let (rest, instructions) = parse_instructions(s).map_err(|e| {
-        anyhow::Error::msg(format!("could not parse instructions. {e}"))
+        NotAScript(format!("could not parse instructions. {e}"))
     })?;
 
     if cfg!(test) {
         dbg!(rest);
         dbg!(&instructions);
     }
-    anyhow::ensure!(rest.trim().is_empty(), "Invalid operation!");
+    if !rest.trim().is_empty() {
+        return Err(NotAScript("Invalid operation!".into()).into());
+    }
 
-    compute_instructions(instructions, ctx)
+    let _guard = limits::RunGuard::enter()?;
+    compute_instructions(instructions, ctx, host)
 }
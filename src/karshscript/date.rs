@@ -0,0 +1,159 @@
+use chrono::{
+    format::{Item, StrftimeItems},
+    DateTime, Local, NaiveDate, NaiveDateTime, TimeZone,
+};
+
+use crate::prelude::{all_consuming, Duration};
+
+use super::{limits, parser::parse_duration, Value};
+
+pub(super) const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+const SECOND: i128 = 1000;
+const MINUTE: i128 = 60 * SECOND;
+const HOUR: i128 = 60 * MINUTE;
+const DAY: i128 = 24 * HOUR;
+const WEEK: i128 = 7 * DAY;
+
+/// milliseconds in a duration unit e.g `h` in `1h30m`
+pub(super) fn unit_to_millis(unit: &str) -> Option<i128> {
+    match unit {
+        "ms" => Some(1),
+        "s" => Some(SECOND),
+        "m" => Some(MINUTE),
+        "h" => Some(HOUR),
+        "d" => Some(DAY),
+        "w" => Some(WEEK),
+        _ => None,
+    }
+}
+
+/// current time in milliseconds since the epoch
+pub(super) fn now() -> i128 {
+    Local::now().timestamp_millis() as i128
+}
+
+/// e.g `format_date(0, "%Y")` gives "1970" (in local time)
+pub(super) fn format_date(millis: i128, fmt: &str) -> Option<String> {
+    let items = StrftimeItems::new(fmt).collect::<Vec<_>>();
+    if items.iter().any(|i| matches!(i, Item::Error)) {
+        return None;
+    }
+    let date =
+        Local.timestamp_millis_opt(i64::try_from(millis).ok()?).single()?;
+    Some(date.format_with_items(items.into_iter()).to_string())
+}
+
+/// milliseconds since the epoch. without a format, rfc3339 and
+/// "%Y-%m-%d %H:%M:%S" / "%Y-%m-%d" in local time are tried
+pub(super) fn parse_date(date: &str, fmt: Option<&str>) -> Option<i128> {
+    let from_local =
+        |naive: NaiveDateTime| Local.from_local_datetime(&naive).earliest();
+    let parsed = match fmt {
+        Some(fmt) => NaiveDateTime::parse_from_str(date, fmt)
+            .ok()
+            .or_else(|| {
+                NaiveDate::parse_from_str(date, fmt).ok()?.and_hms_opt(0, 0, 0)
+            })
+            .and_then(from_local)?,
+        None => DateTime::parse_from_rfc3339(date)
+            .map(|d| d.with_timezone(&Local))
+            .ok()
+            .or_else(|| {
+                NaiveDateTime::parse_from_str(date, DEFAULT_DATE_FORMAT)
+                    .ok()
+                    .and_then(from_local)
+            })
+            .or_else(|| {
+                NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                    .ok()?
+                    .and_hms_opt(0, 0, 0)
+                    .and_then(from_local)
+            })?,
+    };
+    Some(parsed.timestamp_millis() as i128)
+}
+
+/// e.g 5400000 gives "1h 30m"
+pub(super) fn format_duration(millis: i128) -> String {
+    if millis == 0 {
+        return "0ms".to_string();
+    }
+    let sign = if millis < 0 { "-" } else { "" };
+    let mut rest = millis.abs();
+    let mut parts = vec![];
+    for (unit, size) in
+        [("d", DAY), ("h", HOUR), ("m", MINUTE), ("s", SECOND), ("ms", 1)]
+    {
+        if rest >= size {
+            parts.push(format!("{}{unit}", rest / size));
+            rest %= size;
+        }
+    }
+    format!("{sign}{}", parts.join(" "))
+}
+
+/// e.g `1h30m`, used outside of scripts e.g the ttl of a cache entry
+pub fn duration_from_str(s: &str) -> Option<Duration> {
+    match all_consuming(parse_duration)(s.trim()) {
+        Ok((_, Value::Integer(millis))) => {
+            u64::try_from(millis).ok().map(Duration::from_millis)
+        }
+        _ => None,
+    }
+}
+
+pub fn duration_to_string(duration: Duration) -> String {
+    format_duration(duration.as_millis() as i128)
+}
+
+/// sleep, but stop early if the script is interrupted
+pub(super) fn sleep(millis: u64) -> anyhow::Result<()> {
+    let step = Duration::from_millis(50);
+    let mut remaining = Duration::from_millis(millis);
+    while !remaining.is_zero() {
+        limits::check_running()?;
+        let next = remaining.min(step);
+        std::thread::sleep(next);
+        remaining -= next;
+    }
+    limits::check_running()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::prelude::Duration;
+
+    use super::{
+        duration_from_str, duration_to_string, format_duration, parse_date,
+    };
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!("1h 30m", format_duration(5_400_000));
+        assert_eq!("1d 1s 5ms", format_duration(86_401_005));
+        assert_eq!("-2s", format_duration(-2000));
+        assert_eq!("0ms", format_duration(0));
+        assert_eq!("1h 30m", duration_to_string(Duration::from_secs(5400)));
+        assert_eq!(Some(Duration::from_secs(5400)), duration_from_str("1h30m"));
+        assert_eq!(None, duration_from_str("1h 30m"));
+        assert_eq!(None, duration_from_str("soon"));
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(
+            Some(1_704_067_200_000),
+            parse_date("2024-01-01T00:00:00Z", None)
+        );
+        assert_eq!(
+            Some(1_704_067_200_000),
+            parse_date("2024-01-01T01:00:00+01:00", None)
+        );
+        assert_eq!(None, parse_date("not a date", None));
+        assert_eq!(
+            parse_date("2024-01-01", None),
+            parse_date("01/01/2024", Some("%d/%m/%Y"))
+        );
+    }
+}
@@ -0,0 +1,36 @@
+/// Gives karshscript access to what lives outside of the script context,
+/// e.g. the cache database of the REPL.
+/// Every method has a default implementation, so `()` can be used as a
+/// host when a script runs without any database.
+pub trait Host {
+    fn cache_get(&mut self, _key: &str) -> Option<String> {
+        None
+    }
+
+    fn cache_put(
+        &mut self,
+        _aliases: Vec<&str>,
+        _value: &str,
+    ) -> Option<String> {
+        None
+    }
+
+    fn cache_del(&mut self, _key: &str) -> Option<String> {
+        None
+    }
+
+    /// list the values of a cache. None means the current cache
+    fn cache_list(
+        &mut self,
+        _cache_name: Option<&str>,
+    ) -> Option<Vec<(String, String)>> {
+        None
+    }
+
+    /// switch to another cache, returns the previous one
+    fn cache_use(&mut self, _cache_name: &str) -> Option<String> {
+        None
+    }
+}
+
+impl Host for () {}
@@ -0,0 +1,161 @@
+use std::{cell::Cell, time::Instant};
+
+use crate::prelude::{AtomicBool, Duration, Ordering};
+
+use super::Primitive;
+
+/// set by the ctrl+c handler, checked by running scripts
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Limits applied to every script run, to avoid hanging the REPL
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// max iterations for a single while loop
+    pub loop_iterations: Option<u64>,
+    /// max nested script executions e.g. include inside include
+    pub depth: Option<usize>,
+    /// max duration of a script run
+    pub time: Option<Duration>,
+    /// max number of elements of an array
+    pub array_len: Option<usize>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            loop_iterations: None,
+            depth: Some(64),
+            time: None,
+            array_len: Some(1_000_000),
+        }
+    }
+}
+
+impl Limits {
+    /// set a limit by name, e.g `set("loop", 100)`. 0 means no limit.
+    /// time is in milliseconds
+    pub fn set(&mut self, name: &str, value: u64) -> anyhow::Result<()> {
+        let value = Some(value).filter(|v| *v != 0);
+        match name {
+            "loop" => self.loop_iterations = value,
+            "depth" => self.depth = value.map(|v| v as usize),
+            "time" => self.time = value.map(Duration::from_millis),
+            "array" => self.array_len = value.map(|v| v as usize),
+            _ => anyhow::bail!(
+                "unknown limit {name}. possible values: loop, depth, time, array"
+            ),
+        }
+        Ok(())
+    }
+}
+
+thread_local! {
+    static LIMITS: Cell<Limits> = Cell::new(Limits::default());
+    static DEPTH: Cell<usize> = const { Cell::new(0) };
+    static STARTED_AT: Cell<Option<Instant>> = const { Cell::new(None) };
+    static FAIL_ON_ERROR: Cell<bool> = const { Cell::new(false) };
+}
+
+pub fn set_limits(limits: Limits) {
+    LIMITS.with(|l| l.set(limits));
+}
+
+pub fn get_limits() -> Limits {
+    LIMITS.with(|l| l.get())
+}
+
+/// fail the run on any statement giving an error instead of keeping the
+/// error as a value. used by the test runner
+pub fn set_fail_on_error(fail: bool) {
+    FAIL_ON_ERROR.with(|f| f.set(fail));
+}
+
+/// ask the running script to stop
+pub fn interrupt() {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+pub fn reset_interrupt() {
+    INTERRUPTED.store(false, Ordering::SeqCst);
+}
+
+/// Keeps track of the nesting of a script run. The wall time is measured
+/// from the outermost run
+pub(super) struct RunGuard;
+
+impl RunGuard {
+    pub(super) fn enter() -> anyhow::Result<RunGuard> {
+        let depth = DEPTH.with(|d| {
+            d.set(d.get() + 1);
+            d.get()
+        });
+        // guard is created right away so depth is decremented on error
+        let guard = RunGuard;
+        if depth == 1 {
+            STARTED_AT.with(|s| s.set(Some(Instant::now())));
+        }
+        if let Some(max) = get_limits().depth {
+            anyhow::ensure!(depth <= max, "max depth of {max} reached");
+        }
+        check_time()?;
+        Ok(guard)
+    }
+}
+
+impl Drop for RunGuard {
+    fn drop(&mut self) {
+        DEPTH.with(|d| d.set(d.get().saturating_sub(1)));
+    }
+}
+
+fn check_time() -> anyhow::Result<()> {
+    if let (Some(max), Some(started_at)) =
+        (get_limits().time, STARTED_AT.with(|s| s.get()))
+    {
+        anyhow::ensure!(
+            started_at.elapsed() <= max,
+            "script took longer than {}ms",
+            max.as_millis()
+        );
+    }
+    Ok(())
+}
+
+/// fails if the script was interrupted or took too long
+pub(super) fn check_running() -> anyhow::Result<()> {
+    anyhow::ensure!(!INTERRUPTED.load(Ordering::SeqCst), "interrupted");
+    check_time()
+}
+
+/// called on every loop iteration
+pub(super) fn check_loop(iteration: u64) -> anyhow::Result<()> {
+    if let Some(max) = get_limits().loop_iterations {
+        anyhow::ensure!(
+            iteration <= max,
+            "max loop iterations of {max} reached"
+        );
+    }
+    check_running()
+}
+
+pub(super) const ARRAY_TOO_LONG: &str = "max array length reached";
+
+/// checked before building an array of len elements
+pub(super) fn exceeds_array_len(len: usize) -> bool {
+    get_limits().array_len.filter(|max| len > *max).is_some()
+}
+
+pub(super) fn check_size(value: &Primitive) -> anyhow::Result<()> {
+    if let (Some(max), Primitive::Array(arr)) = (get_limits().array_len, value)
+    {
+        anyhow::ensure!(arr.len() <= max, "max array length of {max} reached");
+    }
+    Ok(())
+}
+
+pub(super) fn check_error(value: &Primitive) -> anyhow::Result<()> {
+    if let Primitive::Error(e) = value {
+        anyhow::ensure!(!FAIL_ON_ERROR.with(|f| f.get()), "{e}");
+    }
+    Ok(())
+}
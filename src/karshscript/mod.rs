@@ -1,9 +1,21 @@
 mod ast;
 mod compute;
+mod date;
+mod host;
+mod limits;
+mod number;
 mod parser;
+mod pattern;
 mod primitive;
+mod random;
 
-pub use compute::compute;
+pub use compute::{compute, compute_with_host};
+pub use date::{duration_from_str, duration_to_string};
+pub use host::Host;
+pub use limits::{
+    interrupt, reset_interrupt, set_fail_on_error, set_limits, Limits,
+};
+pub use number::{set_number_mode, set_precision, NumberMode};
 pub use primitive::Primitive;
 use strum::EnumCount;
 
@@ -29,6 +41,8 @@ pub mod constants {
     pub const IF: &str = "if";
     pub const ELSE: &str = "else";
     pub const WHILE: &str = "while";
+    pub const MATCH: &str = "match";
+    pub const STRUCT: &str = "struct";
     pub const TAU: &str = concat!(tau!());
     pub const PI: &str = concat!(pi!());
     pub const EULER_NUMBER: &str = concat!(euler_number!());
@@ -44,6 +58,55 @@ pub mod constants {
     pub const PRINT: &str = "print";
     pub const INCLUDE: &str = "include";
     pub const MULTILINE: &str = "multiline";
+    pub const CACHE_GET: &str = "cache_get";
+    pub const CACHE_PUT: &str = "cache_put";
+    pub const CACHE_DEL: &str = "cache_del";
+    pub const CACHE_LIST: &str = "cache_list";
+    pub const CACHE_USE: &str = "cache_use";
+    pub const EXEC: &str = "exec";
+    pub const SH: &str = "sh";
+    pub const ASSERT: &str = "assert";
+    pub const ASSERT_EQ: &str = "assert_eq";
+    pub const HEX: &str = "hex";
+    pub const BIN: &str = "bin";
+    pub const OCT: &str = "oct";
+    pub const XOR: &str = "xor";
+    pub const PUSH: &str = "push";
+    pub const POP: &str = "pop";
+    pub const INSERT: &str = "insert";
+    pub const REMOVE_AT: &str = "remove_at";
+    pub const CONCAT: &str = "concat";
+    pub const REVERSE: &str = "reverse";
+    pub const SORT: &str = "sort";
+    pub const UNIQUE: &str = "unique";
+    pub const CONTAINS: &str = "contains";
+    pub const INDEX_OF: &str = "index_of";
+    pub const ZIP: &str = "zip";
+    pub const FLATTEN: &str = "flatten";
+    pub const SUM: &str = "sum";
+    pub const AVG: &str = "avg";
+    pub const NULL: &str = "null";
+    pub const DEFINED: &str = "defined";
+    pub const NOW: &str = "now";
+    pub const TIMESTAMP: &str = "timestamp";
+    pub const FORMAT_DATE: &str = "format_date";
+    pub const PARSE_DATE: &str = "parse_date";
+    pub const FORMAT_DURATION: &str = "format_duration";
+    pub const SLEEP: &str = "sleep";
+    pub const RANDOM: &str = "random";
+    pub const RANDOM_INT: &str = "random_int";
+    pub const SHUFFLE: &str = "shuffle";
+    pub const CHOICE: &str = "choice";
+    pub const SEED: &str = "seed";
+    pub const MATCHES: &str = "matches";
+    pub const FIND_ALL: &str = "find_all";
+    pub const CAPTURES: &str = "captures";
+    pub const REPLACE_RE: &str = "replace_re";
+    pub const ENV: &str = "env";
+    pub const ENV_ALL: &str = "env_all";
+    pub const SET_ENV: &str = "set_env";
+    pub const LAST_STATUS: &str = "$?";
+    pub const LAST_OUTPUT: &str = "last_output";
 }
 
 #[derive(Debug, EnumCount)]
@@ -81,8 +144,10 @@ pub(super) enum Value {
     },
     Decimal(f64),
     Integer(i128),
+    BigInteger(num_bigint::BigInt),
     Bool(bool),
     String(String),
+    Null,
     BlockParen(Vec<Value>),
     Variable(String),
     Const(char),
@@ -90,6 +155,8 @@ pub(super) enum Value {
     VariableExpr {
         name: Box<Value>,
         expr: Box<Value>,
+        /// e.g `+` for `x += 1`
+        operator: Option<Operator>,
     },
     IfExpr {
         cond: Box<Value>,
@@ -100,12 +167,41 @@ pub(super) enum Value {
         cond: Box<Value>,
         exprs: Vec<Value>,
     },
+    MatchExpr {
+        value: Box<Value>,
+        arms: Vec<MatchArm>,
+    },
     Array(Vec<Value>),
+    Map(Vec<(String, Value)>),
+    /// e.g `struct Server { host, port }`
+    StructDef {
+        name: String,
+        fields: Vec<String>,
+    },
+    /// e.g `Server { host: "a", port: 22 }`
+    Struct {
+        name: String,
+        fields: Vec<(String, Value)>,
+    },
+    /// safe is true for `?[]` and `?.`, it gives null instead of an error
     ArrayAccess {
+        safe: bool,
         arr: Box<Value>,
         index: Box<Value>,
     },
+    /// e.g `[1:3]`, only valid as an index
+    Slice {
+        start: Option<Box<Value>>,
+        end: Option<Box<Value>>,
+    },
+}
+/// e.g `"a" | "b" => 1`. no patterns means `_`, it matches everything
+#[derive(Debug, PartialEq, Clone)]
+pub(super) struct MatchArm {
+    patterns: Vec<Value>,
+    exprs: Vec<Value>,
 }
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub(super) enum BuiltInFunctionType {
     Sqrt,
@@ -119,6 +215,88 @@ pub(super) enum BuiltInFunctionType {
     Print,
     Length,
     Include,
+    CacheGet,
+    CachePut,
+    CacheDel,
+    CacheList,
+    CacheUse,
+    Exec,
+    Sh,
+    Assert,
+    AssertEq,
+    Hex,
+    Bin,
+    Oct,
+    Push,
+    Pop,
+    Insert,
+    RemoveAt,
+    Concat,
+    Reverse,
+    Sort,
+    Unique,
+    Contains,
+    IndexOf,
+    Zip,
+    Flatten,
+    Sum,
+    Avg,
+    Defined,
+    Now,
+    Timestamp,
+    FormatDate,
+    ParseDate,
+    FormatDuration,
+    Sleep,
+    Random,
+    RandomInt,
+    Shuffle,
+    Choice,
+    Seed,
+    Matches,
+    FindAll,
+    Captures,
+    ReplaceRe,
+    Env,
+    EnvAll,
+    SetEnv,
+}
+
+impl BuiltInFunctionType {
+    /// true if the function updates its first argument in place,
+    /// e.g `push(arr, 1)`
+    pub(super) const fn mutates(&self) -> bool {
+        matches!(
+            self,
+            BuiltInFunctionType::Push
+                | BuiltInFunctionType::Pop
+                | BuiltInFunctionType::Insert
+                | BuiltInFunctionType::RemoveAt
+        )
+    }
+
+    /// true if the function takes a list of arguments e.g `f(a, b)`,
+    /// false if it takes a single expression e.g `sqrt(2 * 4)`
+    pub(super) const fn takes_args(&self) -> bool {
+        !matches!(
+            self,
+            BuiltInFunctionType::Sqrt
+                | BuiltInFunctionType::Abs
+                | BuiltInFunctionType::Log
+                | BuiltInFunctionType::Ln
+                | BuiltInFunctionType::Sin
+                | BuiltInFunctionType::Cos
+                | BuiltInFunctionType::Tan
+                | BuiltInFunctionType::Println
+                | BuiltInFunctionType::Print
+                | BuiltInFunctionType::Length
+                | BuiltInFunctionType::Include
+                | BuiltInFunctionType::Hex
+                | BuiltInFunctionType::Bin
+                | BuiltInFunctionType::Oct
+                | BuiltInFunctionType::Defined
+        )
+    }
 }
 
 #[derive(Debug, PartialEq, Copy, Clone)]
@@ -138,20 +316,66 @@ pub(super) enum Operator {
     NotEqual,
     And,
     Or,
+    BitAnd,
+    BitOr,
+    BitXor,
+    BitNot,
+    ShiftLeft,
+    ShiftRight,
+    /// `a ?? b` gives b when a is null
+    NullCoalesce,
+    /// `s =~ "[0-9]+"` is true when s matches the regex
+    RegexMatch,
 }
 
 #[derive(Debug)]
 pub(super) enum TreeNodeValue {
     VariableAssign(String),
-    VariableArrayAssign { name: String, index: Primitive },
+    /// children are the value, then the indexes e.g `m[i][j] = v`.
+    /// with an operator the value is combined with the current one
+    /// e.g `m[i][j] += v`
+    VariableArrayAssign {
+        name: String,
+        operator: Option<Operator>,
+    },
     Ops(Operator),
     Primitive(Primitive),
     BuiltInFunction(BuiltInFunctionType),
     IfExpr(Value),
     WhileExpr(Value),
+    MatchExpr(Value),
     Array(Vec<Value>),
-    ArrayAccess { index: Primitive, array: Value },
+    Map(Vec<(String, Value)>),
+    /// every field of the struct, null if not specified
+    Struct {
+        name: String,
+        fields: Vec<(String, Value)>,
+    },
+    /// children are the array, then the index
+    ArrayAccess,
+    /// same as ArrayAccess but null instead of an error e.g `m?["a"]`
+    SafeArrayAccess,
+    /// children are the array, the start and the end, unit if not specified
+    ArraySlice,
+    /// children are the remaining arguments e.g `push(arr, 1)`
+    MutatingFunction {
+        fn_type: BuiltInFunctionType,
+        name: String,
+    },
 }
 
+/// the line is not karshscript, e.g it doesn't parse or refers to an
+/// unknown variable. anything else is an error of the script itself
+#[derive(Debug)]
+pub struct NotAScript(String);
+
+impl std::fmt::Display for NotAScript {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for NotAScript {}
+
 #[cfg(test)]
 mod tests;
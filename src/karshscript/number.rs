@@ -0,0 +1,322 @@
+use std::cell::Cell;
+
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{Signed, ToPrimitive, Zero};
+
+use super::Primitive;
+
+/// above that, pow() gives up instead of eating all the memory
+const MAX_BITS: u64 = 1 << 20;
+
+/// How a division of two integers that isn't exact is computed
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum NumberMode {
+    /// integer division, decimals are f64
+    #[default]
+    Float,
+    /// exact fractions, e.g `1/3` stays exact until displayed
+    Rational,
+}
+
+thread_local! {
+    static NUMBER_MODE: Cell<NumberMode> = const { Cell::new(NumberMode::Float) };
+    static PRECISION: Cell<usize> = const { Cell::new(10) };
+}
+
+pub fn set_number_mode(mode: NumberMode) {
+    NUMBER_MODE.with(|m| m.set(mode));
+}
+
+pub(super) fn number_mode() -> NumberMode {
+    NUMBER_MODE.with(|m| m.get())
+}
+
+/// number of decimals used to display a rational
+pub fn set_precision(precision: usize) {
+    PRECISION.with(|p| p.set(precision));
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(super) enum BigOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+}
+
+impl BigOp {
+    const fn error(&self) -> &'static str {
+        match self {
+            BigOp::Add => {
+                "call to add() on a big number and a non numeric value"
+            }
+            BigOp::Sub => {
+                "call to sub() on a big number and a non numeric value"
+            }
+            BigOp::Mul => {
+                "call to mul() on a big number and a non numeric value"
+            }
+            BigOp::Div => {
+                "call to div() on a big number and a non numeric value"
+            }
+            BigOp::Rem => {
+                "call to rem() on a big number and a non numeric value"
+            }
+            BigOp::Pow => {
+                "call to pow() on a big number and a non numeric value"
+            }
+        }
+    }
+}
+
+fn to_rational(p: &Primitive) -> Option<BigRational> {
+    match p {
+        Primitive::Int(i) => Some(BigRational::from_integer(BigInt::from(*i))),
+        Primitive::BigInt(i) => Some(BigRational::from_integer(i.clone())),
+        Primitive::Rational(r) => Some(r.clone()),
+        _ => None,
+    }
+}
+
+pub(super) fn to_f64(p: &Primitive) -> Option<f64> {
+    match p {
+        Primitive::Int(i) => Some(*i as f64),
+        Primitive::Double(d) => Some(*d),
+        Primitive::BigInt(i) => i.to_f64(),
+        Primitive::Rational(r) => r.to_f64(),
+        _ => None,
+    }
+}
+
+/// smallest representation of an integer
+pub(super) fn from_big_int(i: BigInt) -> Primitive {
+    i.to_i128().map_or(Primitive::BigInt(i), Primitive::Int)
+}
+
+/// smallest representation of a fraction
+pub(super) fn from_rational(r: BigRational) -> Primitive {
+    if r.is_integer() {
+        from_big_int(r.to_integer())
+    } else {
+        Primitive::Rational(r)
+    }
+}
+
+/// in rational mode, a decimal literal is exact e.g 0.1 is 1/10
+pub(super) fn from_decimal(d: f64) -> Primitive {
+    if number_mode() == NumberMode::Float || !d.is_finite() {
+        return Primitive::Double(d);
+    }
+    // shortest representation that round trips, e.g 0.1 and not 0.1000000000000000055
+    let repr = d.to_string();
+    let (int_part, frac_part) = repr.split_once('.').unwrap_or((&repr, ""));
+    let numer = format!("{int_part}{frac_part}").parse::<BigInt>();
+    match numer {
+        Ok(numer) => from_rational(BigRational::new(
+            numer,
+            BigInt::from(10).pow(frac_part.len() as u32),
+        )),
+        Err(_) => Primitive::Double(d),
+    }
+}
+
+/// operation on numbers where at least one of them is too big for an i128
+/// or is a fraction
+pub(super) fn big_op(op: BigOp, l: Primitive, r: Primitive) -> Primitive {
+    if let (Some(l), Some(r)) = (to_rational(&l), to_rational(&r)) {
+        return exact_op(op, l, r);
+    }
+    if let (Some(l), Some(r)) = (to_f64(&l), to_f64(&r)) {
+        return Primitive::Double(match op {
+            BigOp::Add => l + r,
+            BigOp::Sub => l - r,
+            BigOp::Mul => l * r,
+            BigOp::Div => l / r,
+            BigOp::Rem => l % r,
+            BigOp::Pow => l.powf(r),
+        });
+    }
+    match (op, l, r) {
+        (BigOp::Add, l, Primitive::String(s)) => {
+            Primitive::String(format!("{l}{s}"))
+        }
+        (op, ..) => Primitive::Error(op.error()),
+    }
+}
+
+fn exact_op(op: BigOp, l: BigRational, r: BigRational) -> Primitive {
+    let both_integers = l.is_integer() && r.is_integer();
+    match op {
+        BigOp::Add => from_rational(l + r),
+        BigOp::Sub => from_rational(l - r),
+        BigOp::Mul => from_rational(l * r),
+        BigOp::Div | BigOp::Rem if r.is_zero() => {
+            let l = l.to_f64().unwrap_or(f64::NAN);
+            Primitive::Double(if matches!(op, BigOp::Div) {
+                l / 0.
+            } else {
+                f64::NAN
+            })
+        }
+        BigOp::Div if both_integers && number_mode() == NumberMode::Float => {
+            from_big_int(l.to_integer() / r.to_integer())
+        }
+        BigOp::Div => from_rational(l / r),
+        BigOp::Rem => from_rational(l % r),
+        BigOp::Pow => exact_pow(l, r),
+    }
+}
+
+fn exact_pow(l: BigRational, r: BigRational) -> Primitive {
+    let float_pow = |l: &BigRational, r: &BigRational| {
+        Primitive::Double(
+            l.to_f64().unwrap_or(f64::NAN).powf(r.to_f64().unwrap_or(f64::NAN)),
+        )
+    };
+    let exp = match r.to_integer().to_i32() {
+        Some(exp) if r.is_integer() => exp,
+        _ => return float_pow(&l, &r),
+    };
+    if exp < 0 && number_mode() == NumberMode::Float {
+        return float_pow(&l, &r);
+    }
+    if exp < 0 && l.is_zero() {
+        return Primitive::Double(f64::INFINITY);
+    }
+    let bits = l.numer().bits().max(l.denom().bits());
+    if bits.saturating_mul(exp.unsigned_abs() as u64) > MAX_BITS {
+        return Primitive::Error("call to pow() gives a number too big");
+    }
+    let res = num_traits::Pow::pow(&l, exp);
+    from_rational(res)
+}
+
+/// integer operation that doesn't fit in an i128
+pub(super) fn int_overflow(op: BigOp, l: i128, r: i128) -> Primitive {
+    big_op(op, Primitive::BigInt(BigInt::from(l)), Primitive::Int(r))
+}
+
+pub(super) fn big_neg(p: &Primitive) -> Primitive {
+    match p {
+        Primitive::Int(i) => from_big_int(-BigInt::from(*i)),
+        Primitive::BigInt(i) => from_big_int(-i),
+        Primitive::Rational(r) => from_rational(-r),
+        _ => Primitive::Error("call to neg() on a non numeric value"),
+    }
+}
+
+pub(super) fn big_abs(p: &Primitive) -> Primitive {
+    match p {
+        Primitive::Int(i) => from_big_int(BigInt::from(*i).abs()),
+        Primitive::BigInt(i) => from_big_int(i.abs()),
+        Primitive::Rational(r) => from_rational(r.abs()),
+        _ => Primitive::Error("call to abs() on a non numeric value"),
+    }
+}
+
+pub(super) fn big_cmp(
+    l: &Primitive,
+    r: &Primitive,
+) -> Option<std::cmp::Ordering> {
+    match (to_rational(l), to_rational(r)) {
+        (Some(l), Some(r)) => l.partial_cmp(&r),
+        _ => to_f64(l)?.partial_cmp(&to_f64(r)?),
+    }
+}
+
+/// display a fraction as a decimal number, rounded to the precision
+pub(super) fn display_rational(r: &BigRational) -> String {
+    let precision = PRECISION.with(|p| p.get());
+    let scale = BigInt::from(10).pow(precision as u32);
+    let scaled = (r * BigRational::from_integer(scale.clone())).round();
+    let scaled = scaled.to_integer();
+    let sign = if scaled.is_negative() { "-" } else { "" };
+    let scaled = scaled.abs();
+    let int_part = &scaled / &scale;
+    let frac_part = (&scaled % &scale).to_string();
+    let frac_part = format!("{frac_part:0>precision$}");
+    let frac_part = frac_part.trim_end_matches('0');
+    if frac_part.is_empty() {
+        format!("{sign}{int_part}")
+    } else {
+        format!("{sign}{int_part}.{frac_part}")
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(super) enum BitOp {
+    And,
+    Or,
+    Xor,
+    Shl,
+    Shr,
+}
+
+fn to_big_int(p: &Primitive) -> Option<BigInt> {
+    match p {
+        Primitive::Int(i) => Some(BigInt::from(*i)),
+        Primitive::BigInt(i) => Some(i.clone()),
+        _ => None,
+    }
+}
+
+pub(super) fn bit_op(op: BitOp, l: &Primitive, r: &Primitive) -> Primitive {
+    if let (Primitive::Bool(l), Primitive::Bool(r)) = (l, r) {
+        return match op {
+            BitOp::And => Primitive::Bool(l & r),
+            BitOp::Or => Primitive::Bool(l | r),
+            BitOp::Xor => Primitive::Bool(l ^ r),
+            BitOp::Shl | BitOp::Shr => {
+                Primitive::Error("cannot shift a boolean value")
+            }
+        };
+    }
+    let (l, r) = match (to_big_int(l), to_big_int(r)) {
+        (Some(l), Some(r)) => (l, r),
+        _ => {
+            return Primitive::Error("bitwise operation on a non integer value")
+        }
+    };
+    match op {
+        BitOp::And => from_big_int(l & r),
+        BitOp::Or => from_big_int(l | r),
+        BitOp::Xor => from_big_int(l ^ r),
+        BitOp::Shl | BitOp::Shr => match r.to_u64() {
+            Some(n) if matches!(op, BitOp::Shr) => from_big_int(l >> n),
+            Some(n) if l.bits().saturating_add(n) <= MAX_BITS => {
+                from_big_int(l << n)
+            }
+            Some(_) => Primitive::Error("shift gives a number too big"),
+            None => Primitive::Error("cannot shift by a negative value"),
+        },
+    }
+}
+
+pub(super) fn bit_not(p: &Primitive) -> Primitive {
+    match to_big_int(p) {
+        Some(i) => from_big_int(!i),
+        None => Primitive::Error("call to '~' on a non integer value"),
+    }
+}
+
+/// e.g `to_radix(255, 16)` gives "0xff"
+pub(super) fn to_radix(p: &Primitive, radix: u32) -> Primitive {
+    let prefix = match radix {
+        2 => "0b",
+        8 => "0o",
+        _ => "0x",
+    };
+    match to_big_int(p) {
+        Some(i) => {
+            let sign = if i.is_negative() { "-" } else { "" };
+            Primitive::String(format!(
+                "{sign}{prefix}{}",
+                i.abs().to_str_radix(radix)
+            ))
+        }
+        None => Primitive::Error("cannot convert a non integer value"),
+    }
+}
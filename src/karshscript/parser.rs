@@ -1,29 +1,40 @@
 use nom::{
-    bytes::complete::take_while1, combinator::rest, multi::separated_list0,
+    bytes::complete::take_while1,
+    character::complete::{digit1, satisfy},
+    combinator::{map_res, not, recognize, rest},
+    multi::{separated_list0, separated_list1},
     sequence::pair,
 };
+use num_bigint::BigInt;
 
 use crate::{
     karshscript::constants::{
-        ABS, COS, INCLUDE, LENGTH, LN, LOG, PRINT, PRINT_LN, SIN, SQRT, TAN,
+        ABS, ASSERT, ASSERT_EQ, AVG, BIN, CACHE_DEL, CACHE_GET, CACHE_LIST,
+        CACHE_PUT, CACHE_USE, CAPTURES, CHOICE, CONCAT, CONTAINS, COS, DEFINED,
+        ENV, ENV_ALL, EXEC, FIND_ALL, FLATTEN, FORMAT_DATE, FORMAT_DURATION,
+        HEX, INCLUDE, INDEX_OF, INSERT, LAST_STATUS, LENGTH, LN, LOG, MATCHES,
+        NOW, NULL, OCT, PARSE_DATE, POP, PRINT, PRINT_LN, PUSH, RANDOM,
+        RANDOM_INT, REMOVE_AT, REPLACE_RE, REVERSE, SEED, SET_ENV, SH, SHUFFLE,
+        SIN, SLEEP, SORT, SQRT, SUM, TAN, TIMESTAMP, UNIQUE, XOR, ZIP,
     },
     prelude::{
-        all_consuming, alt, cut, delimited, double, many0, many1, map,
-        map_parser, multispace0, one_of, opt, preceded, recognize_float,
-        separated_pair, tag, tag_no_case, take_until, terminated, tuple,
-        verify, Res, I128,
+        all_consuming, alt, cut, delimited, many0, many1, map, map_parser,
+        multispace0, one_of, opt, preceded, recognize_float, separated_pair,
+        tag, tag_no_case, take_until, take_while, terminated, tuple, verify,
+        Context, Res,
     },
     reserved_keywords::check_reserved_keyword,
 };
 
 use super::{
-    constants::{ELSE, IF, MULTILINE, WHILE},
-    BuiltInFunctionType, MathConstants, Operator, Value,
+    constants::{ELSE, IF, MATCH, MULTILINE, STRUCT, WHILE},
+    date::unit_to_millis,
+    BuiltInFunctionType, MatchArm, MathConstants, Operator, Value,
 };
 
 fn comments(s: &str) -> Res<Vec<&str>> {
     terminated(
-        many0(preceded(tag_no_space("#"), take_until("\n"))),
+        many0(preceded(tag_no_space("#"), alt((take_until("\n"), rest)))),
         multispace0,
     )(s)
 }
@@ -31,20 +42,115 @@ fn comments(s: &str) -> Res<Vec<&str>> {
 fn tag_no_space<'a>(t: &'a str) -> impl Fn(&'a str) -> Res<&'a str> {
     move |s: &str| delimited(multispace0, tag(t), multispace0)(s)
 }
+/// e.g `match` but not `matches`
+fn tag_keyword<'a>(t: &'a str) -> impl Fn(&'a str) -> Res<&'a str> {
+    move |s: &str| {
+        delimited(
+            multispace0,
+            terminated(
+                tag(t),
+                not(satisfy(|c| c.is_alphanumeric() || c == '_')),
+            ),
+            multispace0,
+        )(s)
+    }
+}
 fn tag_no_space_no_case<'a>(t: &'a str) -> impl Fn(&'a str) -> Res<&'a str> {
     move |s: &str| delimited(multispace0, tag_no_case(t), multispace0)(s)
 }
 
-fn parse_number(s: &str) -> Res<Value> {
-    map_parser(
-        recognize_float,
-        alt((
-            map(all_consuming(I128), Value::Integer),
-            map(all_consuming(double), Value::Decimal),
-        )),
+fn to_number(n: &str) -> anyhow::Result<Value> {
+    let n = n.replace('_', "");
+    if let Ok(i) = n.parse::<i128>() {
+        return Ok(Value::Integer(i));
+    }
+    // too big for an i128
+    if let Ok(i) = n.parse::<BigInt>() {
+        return Ok(Value::BigInteger(i));
+    }
+    let d = n.parse::<f64>()?;
+    anyhow::ensure!(d.is_finite(), "number {n} is too large");
+    Ok(Value::Decimal(d))
+}
+
+fn to_radix_number(digits: &str, radix: u32) -> anyhow::Result<Value> {
+    let digits = digits.replace('_', "");
+    if let Ok(i) = i128::from_str_radix(&digits, radix) {
+        return Ok(Value::Integer(i));
+    }
+    BigInt::parse_bytes(digits.as_bytes(), radix)
+        .map(Value::BigInteger)
+        .context("invalid number")
+}
+
+/// e.g 0xff, 0b1010_1010, 0o755. underscores may follow the prefix e.g 0x_ff
+fn parse_radix_number(s: &str) -> Res<Value> {
+    fn radix_digits<'a>(
+        prefix: &'a str,
+        radix: u32,
+    ) -> impl Fn(&'a str) -> Res<Value> {
+        move |s| {
+            map_res(
+                terminated(
+                    preceded(
+                        pair(tag_no_case(prefix), take_while(|c| c == '_')),
+                        recognize(pair(
+                            satisfy(|c| c.is_digit(radix)),
+                            take_while(|c: char| c.is_digit(radix) || c == '_'),
+                        )),
+                    ),
+                    not(satisfy(|c| c.is_alphanumeric())),
+                ),
+                |digits| to_radix_number(digits, radix),
+            )(s)
+        }
+    }
+    alt((radix_digits("0x", 16), radix_digits("0b", 2), radix_digits("0o", 8)))(
+        s,
+    )
+}
+
+/// e.g `1h30m`, `500ms` in milliseconds
+pub(super) fn parse_duration(s: &str) -> Res<Value> {
+    fn unit(s: &str) -> Res<&str> {
+        alt((tag("ms"), tag("s"), tag("m"), tag("h"), tag("d"), tag("w")))(s)
+    }
+    map_res(
+        terminated(
+            many1(pair(digit1, unit)),
+            not(satisfy(|c| c.is_alphanumeric() || c == '_')),
+        ),
+        |parts| {
+            parts
+                .into_iter()
+                .try_fold(0i128, |total, (n, unit)| {
+                    let n = n.parse::<i128>()?;
+                    let millis =
+                        unit_to_millis(unit).context("invalid unit")?;
+                    n.checked_mul(millis)
+                        .and_then(|n| total.checked_add(n))
+                        .context("duration is too large")
+                })
+                .map(Value::Integer)
+        },
     )(s)
 }
 
+fn parse_number(s: &str) -> Res<Value> {
+    // digits with separators e.g 1_000_000 or 1_000.5e3
+    let with_separators = recognize(tuple((
+        digit1,
+        many1(preceded(tag("_"), digit1)),
+        opt(pair(tag("."), separated_list1(tag("_"), digit1))),
+        opt(tuple((one_of("eE"), opt(one_of("+-")), digit1))),
+    )));
+    alt((
+        parse_radix_number,
+        parse_duration,
+        map_res(alt((with_separators, recognize_float)), to_number),
+    ))(s)
+}
+
 fn parse_bool(s: &str) -> Res<Value> {
     alt((
         map(tag_no_space("true"), |_| Value::Bool(true)),
@@ -52,6 +158,10 @@ fn parse_bool(s: &str) -> Res<Value> {
     ))(s)
 }
 
+fn parse_null(s: &str) -> Res<Value> {
+    map(tag_keyword(NULL), |_| Value::Null)(s)
+}
+
 fn parse_string(s: &str) -> Res<Value> {
     map(
         delimited(
@@ -63,6 +173,90 @@ fn parse_string(s: &str) -> Res<Value> {
     )(s)
 }
 
+/// e.g `port` in `config?.port`
+fn parse_field_name(s: &str) -> Res<Value> {
+    map(
+        verify(
+            take_while1(|c: char| c.is_alphanumeric() || c == '_'),
+            |s: &str| s.chars().next().filter(|c| c.is_alphabetic()).is_some(),
+        ),
+        |s: &str| Value::String(s.to_string()),
+    )(s)
+}
+
+/// e.g `Server`, a struct name starts with an uppercase letter
+fn parse_struct_name(s: &str) -> Res<String> {
+    map(
+        preceded(
+            multispace0,
+            verify(
+                take_while1(|c: char| c.is_alphanumeric() || c == '_'),
+                |s: &str| s.starts_with(|c: char| c.is_uppercase()),
+            ),
+        ),
+        String::from,
+    )(s)
+}
+
+fn parse_struct_field(s: &str) -> Res<String> {
+    map(preceded(multispace0, parse_field_name), |f| match f {
+        Value::String(f) => f,
+        _ => unreachable!("parse_field_name returns a string"),
+    })(s)
+}
+
+/// e.g `struct Server { host, port }`, assigned to a variable named after it
+fn parse_struct_definition(s: &str) -> Res<Value> {
+    map(
+        preceded(
+            tag_keyword(STRUCT),
+            pair(
+                parse_struct_name,
+                delimited(
+                    tag_no_space("{"),
+                    terminated(
+                        separated_list0(tag_no_space(","), parse_struct_field),
+                        opt(tag_no_space(",")),
+                    ),
+                    tag_no_space("}"),
+                ),
+            ),
+        ),
+        |(name, fields)| Value::VariableExpr {
+            name: Box::new(Value::Variable(name.clone())),
+            expr: Box::new(Value::StructDef { name, fields }),
+            operator: None,
+        },
+    )(s)
+}
+
+/// e.g `Server { host: "a", port: 22 }`
+fn parse_struct(s: &str) -> Res<Value> {
+    map(
+        pair(
+            parse_struct_name,
+            preceded(
+                tag_no_space("{"),
+                terminated(
+                    terminated(
+                        separated_list0(
+                            tag_no_space(","),
+                            separated_pair(
+                                parse_struct_field,
+                                tag_no_space(":"),
+                                parse_ternary,
+                            ),
+                        ),
+                        opt(tag_no_space(",")),
+                    ),
+                    preceded(multispace0, tag("}")),
+                ),
+            ),
+        ),
+        |(name, fields)| Value::Struct { name, fields },
+    )(s)
+}
+
 fn parse_variable(s: &str) -> Res<Value> {
     let allowed_values =
         |s| take_while1(|s: char| s.is_alphanumeric() || s == '_')(s);
@@ -78,20 +272,55 @@ fn parse_variable(s: &str) -> Res<Value> {
         ),
     )(s)
 }
+fn parse_last_status(s: &str) -> Res<Value> {
+    map(tag(LAST_STATUS), |s: &str| Value::Variable(s.to_string()))(s)
+}
 fn parse_constant(s: &str) -> Res<Value> {
     map(one_of(MathConstants::get_symbols()), Value::Const)(s)
 }
 
+fn to_block(v: Vec<Value>) -> Value {
+    if v.len() == 1 {
+        v.into_iter().next().unwrap()
+    } else {
+        Value::BlockParen(v)
+    }
+}
+
+fn to_ternary((cond, branches): (Value, Option<(Value, Value)>)) -> Value {
+    match branches {
+        Some((then, otherwise)) => Value::IfExpr {
+            cond: Box::new(cond),
+            exprs: vec![then],
+            else_expr: Some(vec![otherwise]),
+        },
+        None => cond,
+    }
+}
+
+fn parse_ternary_branches(s: &str) -> Res<(Value, Value)> {
+    preceded(
+        tag_no_space("?"),
+        cut(separated_pair(parse_ternary, tag_no_space(":"), parse_ternary)),
+    )(s)
+}
+
+/// e.g `a > b ? a : b`, same as `if (a > b) { a } else { b }`
+fn parse_ternary(s: &str) -> Res<Value> {
+    map(
+        pair(map(many1(parse_value), to_block), opt(parse_ternary_branches)),
+        to_ternary,
+    )(s)
+}
+
 fn parse_paren(s: &str) -> Res<Value> {
+    delimited(tag_no_space("("), parse_ternary, cut(tag_no_space(")")))(s)
+}
+
+fn parse_args(s: &str) -> Res<Vec<Value>> {
     delimited(
         tag_no_space("("),
-        map(many1(parse_value), |v| {
-            if v.len() == 1 {
-                v.into_iter().next().unwrap()
-            } else {
-                Value::BlockParen(v)
-            }
-        }),
+        separated_list0(tag_no_space(","), parse_ternary),
         cut(tag_no_space(")")),
     )(s)
 }
@@ -112,25 +341,135 @@ fn parse_builtin_fn(s: &str) -> Res<Value> {
             BuiltInFunctionType::Println => PRINT_LN,
             BuiltInFunctionType::Print => PRINT,
             BuiltInFunctionType::Include => INCLUDE,
+            BuiltInFunctionType::CacheGet => CACHE_GET,
+            BuiltInFunctionType::CachePut => CACHE_PUT,
+            BuiltInFunctionType::CacheDel => CACHE_DEL,
+            BuiltInFunctionType::CacheList => CACHE_LIST,
+            BuiltInFunctionType::CacheUse => CACHE_USE,
+            BuiltInFunctionType::Exec => EXEC,
+            BuiltInFunctionType::Sh => SH,
+            BuiltInFunctionType::Assert => ASSERT,
+            BuiltInFunctionType::AssertEq => ASSERT_EQ,
+            BuiltInFunctionType::Hex => HEX,
+            BuiltInFunctionType::Bin => BIN,
+            BuiltInFunctionType::Oct => OCT,
+            BuiltInFunctionType::Push => PUSH,
+            BuiltInFunctionType::Pop => POP,
+            BuiltInFunctionType::Insert => INSERT,
+            BuiltInFunctionType::RemoveAt => REMOVE_AT,
+            BuiltInFunctionType::Concat => CONCAT,
+            BuiltInFunctionType::Reverse => REVERSE,
+            BuiltInFunctionType::Sort => SORT,
+            BuiltInFunctionType::Unique => UNIQUE,
+            BuiltInFunctionType::Contains => CONTAINS,
+            BuiltInFunctionType::IndexOf => INDEX_OF,
+            BuiltInFunctionType::Zip => ZIP,
+            BuiltInFunctionType::Flatten => FLATTEN,
+            BuiltInFunctionType::Sum => SUM,
+            BuiltInFunctionType::Avg => AVG,
+            BuiltInFunctionType::Defined => DEFINED,
+            BuiltInFunctionType::Now => NOW,
+            BuiltInFunctionType::Timestamp => TIMESTAMP,
+            BuiltInFunctionType::FormatDate => FORMAT_DATE,
+            BuiltInFunctionType::ParseDate => PARSE_DATE,
+            BuiltInFunctionType::FormatDuration => FORMAT_DURATION,
+            BuiltInFunctionType::Sleep => SLEEP,
+            BuiltInFunctionType::Random => RANDOM,
+            BuiltInFunctionType::RandomInt => RANDOM_INT,
+            BuiltInFunctionType::Shuffle => SHUFFLE,
+            BuiltInFunctionType::Choice => CHOICE,
+            BuiltInFunctionType::Seed => SEED,
+            BuiltInFunctionType::Matches => MATCHES,
+            BuiltInFunctionType::FindAll => FIND_ALL,
+            BuiltInFunctionType::Captures => CAPTURES,
+            BuiltInFunctionType::ReplaceRe => REPLACE_RE,
+            BuiltInFunctionType::Env => ENV,
+            BuiltInFunctionType::EnvAll => ENV_ALL,
+            BuiltInFunctionType::SetEnv => SET_ENV,
         };
         move |s: &str| {
-            map(preceded(tag_no_space_no_case(fn_name), parse_paren), |expr| {
-                Value::BuiltInFunction { fn_type, expr: Box::new(expr) }
-            })(s)
+            let (s, _) = tag_no_space_no_case(fn_name)(s)?;
+            if fn_type.takes_args() {
+                // arguments are passed to the function as an array
+                map(parse_args, |args| Value::BuiltInFunction {
+                    fn_type,
+                    expr: Box::new(Value::Array(args)),
+                })(s)
+            } else {
+                map(parse_paren, |expr| Value::BuiltInFunction {
+                    fn_type,
+                    expr: Box::new(expr),
+                })(s)
+            }
         }
     }
     alt((
-        parse_fn(BuiltInFunctionType::Sqrt),
-        parse_fn(BuiltInFunctionType::Abs),
-        parse_fn(BuiltInFunctionType::Ln),
-        parse_fn(BuiltInFunctionType::Log),
-        parse_fn(BuiltInFunctionType::Sin),
-        parse_fn(BuiltInFunctionType::Cos),
-        parse_fn(BuiltInFunctionType::Tan),
-        parse_fn(BuiltInFunctionType::Println),
-        parse_fn(BuiltInFunctionType::Print),
-        parse_fn(BuiltInFunctionType::Length),
-        parse_fn(BuiltInFunctionType::Include),
+        alt((
+            parse_fn(BuiltInFunctionType::Sqrt),
+            parse_fn(BuiltInFunctionType::Abs),
+            parse_fn(BuiltInFunctionType::Ln),
+            parse_fn(BuiltInFunctionType::Log),
+            parse_fn(BuiltInFunctionType::Sin),
+            parse_fn(BuiltInFunctionType::Cos),
+            parse_fn(BuiltInFunctionType::Tan),
+            parse_fn(BuiltInFunctionType::Println),
+            parse_fn(BuiltInFunctionType::Print),
+            parse_fn(BuiltInFunctionType::Length),
+            parse_fn(BuiltInFunctionType::Include),
+        )),
+        alt((
+            parse_fn(BuiltInFunctionType::CacheGet),
+            parse_fn(BuiltInFunctionType::CachePut),
+            parse_fn(BuiltInFunctionType::CacheDel),
+            parse_fn(BuiltInFunctionType::CacheList),
+            parse_fn(BuiltInFunctionType::CacheUse),
+            parse_fn(BuiltInFunctionType::Exec),
+            parse_fn(BuiltInFunctionType::Sh),
+            parse_fn(BuiltInFunctionType::AssertEq),
+            parse_fn(BuiltInFunctionType::Assert),
+            parse_fn(BuiltInFunctionType::Hex),
+            parse_fn(BuiltInFunctionType::Bin),
+            parse_fn(BuiltInFunctionType::Oct),
+        )),
+        alt((
+            parse_fn(BuiltInFunctionType::Push),
+            parse_fn(BuiltInFunctionType::Pop),
+            parse_fn(BuiltInFunctionType::Insert),
+            parse_fn(BuiltInFunctionType::RemoveAt),
+            parse_fn(BuiltInFunctionType::Concat),
+            parse_fn(BuiltInFunctionType::Reverse),
+            parse_fn(BuiltInFunctionType::Sort),
+            parse_fn(BuiltInFunctionType::Unique),
+            parse_fn(BuiltInFunctionType::Contains),
+            parse_fn(BuiltInFunctionType::IndexOf),
+            parse_fn(BuiltInFunctionType::Zip),
+            parse_fn(BuiltInFunctionType::Flatten),
+            parse_fn(BuiltInFunctionType::Sum),
+            parse_fn(BuiltInFunctionType::Avg),
+            parse_fn(BuiltInFunctionType::Defined),
+        )),
+        alt((
+            parse_fn(BuiltInFunctionType::Now),
+            parse_fn(BuiltInFunctionType::Timestamp),
+            parse_fn(BuiltInFunctionType::FormatDate),
+            parse_fn(BuiltInFunctionType::ParseDate),
+            parse_fn(BuiltInFunctionType::FormatDuration),
+            parse_fn(BuiltInFunctionType::Sleep),
+            parse_fn(BuiltInFunctionType::RandomInt),
+            parse_fn(BuiltInFunctionType::Random),
+            parse_fn(BuiltInFunctionType::Shuffle),
+            parse_fn(BuiltInFunctionType::Choice),
+            parse_fn(BuiltInFunctionType::Seed),
+            parse_fn(BuiltInFunctionType::Matches),
+            parse_fn(BuiltInFunctionType::FindAll),
+            parse_fn(BuiltInFunctionType::Captures),
+            parse_fn(BuiltInFunctionType::ReplaceRe),
+        )),
+        alt((
+            parse_fn(BuiltInFunctionType::EnvAll),
+            parse_fn(BuiltInFunctionType::Env),
+            parse_fn(BuiltInFunctionType::SetEnv),
+        )),
     ))(s)
 }
 
@@ -146,18 +485,71 @@ fn parse_array(s: &str) -> Res<Value> {
         Value::Array,
     )(s)
 }
-fn parse_array_access(s: &str) -> Res<Value> {
+fn parse_map(s: &str) -> Res<Value> {
     map(
-        pair(
-            alt((parse_variable, parse_array)),
-            preceded(
-                terminated(tag("["), multispace0),
-                terminated(parse_value, preceded(multispace0, tag("]"))),
+        preceded(
+            tag_no_space("{"),
+            terminated(
+                separated_list0(
+                    tag_no_space(","),
+                    separated_pair(
+                        map(parse_string, |k| match k {
+                            Value::String(k) => k,
+                            _ => unreachable!("parse_string returns a string"),
+                        }),
+                        tag_no_space(":"),
+                        parse_ternary,
+                    ),
+                ),
+                preceded(multispace0, tag("}")),
             ),
         ),
-        |(arr, idx)| Value::ArrayAccess {
-            arr: Box::new(arr),
-            index: Box::new(idx),
+        Value::Map,
+    )(s)
+}
+
+/// e.g `1:3`, `:-1`
+fn parse_slice(s: &str) -> Res<Value> {
+    let bound = |s| opt(map(many1(parse_value), to_block))(s);
+    map(separated_pair(bound, tag_no_space(":"), bound), |(start, end)| {
+        Value::Slice { start: start.map(Box::new), end: end.map(Box::new) }
+    })(s)
+}
+
+/// e.g `arr[i + 1]`, `m["a"][0]`, `arr[1:3]`, `server.port` or `config?.port`
+fn parse_array_access(s: &str) -> Res<Value> {
+    let index = |s| {
+        alt((
+            map(preceded(tag("?."), parse_field_name), |idx| (true, idx)),
+            map(preceded(tag("."), parse_field_name), |idx| (false, idx)),
+            map(
+                delimited(
+                    terminated(tag("?["), multispace0),
+                    parse_ternary,
+                    preceded(multispace0, tag("]")),
+                ),
+                |idx| (true, idx),
+            ),
+            map(
+                delimited(
+                    terminated(tag("["), multispace0),
+                    alt((parse_slice, parse_ternary)),
+                    preceded(multispace0, tag("]")),
+                ),
+                |idx| (false, idx),
+            ),
+        ))(s)
+    };
+    map(
+        pair(alt((parse_variable, parse_array)), many1(index)),
+        |(arr, indexes)| {
+            indexes.into_iter().fold(arr, |arr, (safe, idx)| {
+                Value::ArrayAccess {
+                    safe,
+                    arr: Box::new(arr),
+                    index: Box::new(idx),
+                }
+            })
         },
     )(s)
 }
@@ -169,12 +561,18 @@ fn parse_value(s: &str) -> Res<Value> {
             alt((
                 parse_array_access,
                 parse_array,
+                parse_map,
                 parse_string,
                 parse_paren,
                 parse_operation,
                 parse_number,
                 parse_bool,
+                parse_null,
                 parse_builtin_fn,
+                parse_if_statement,
+                parse_match_expression,
+                parse_struct,
+                parse_last_status,
                 parse_variable,
                 parse_constant,
             )),
@@ -201,52 +599,161 @@ fn parse_operation(s: &str) -> Res<Value> {
             Operator::NotEqual => "!=",
             Operator::And => "&&",
             Operator::Or => "||",
+            Operator::BitAnd => "&",
+            Operator::BitOr => "|",
+            Operator::BitXor => XOR,
+            Operator::BitNot => "~",
+            Operator::ShiftLeft => "<<",
+            Operator::ShiftRight => ">>",
+            Operator::NullCoalesce => "??",
+            Operator::RegexMatch => "=~",
         };
-        move |s| map(tag_no_space(sep), |_| Value::Operation(operation))(s)
+        move |s| {
+            if operation == Operator::BitXor {
+                // e.g xor_flags is a variable
+                let (s, _) = tag_keyword(sep)(s)?;
+                return Ok((s, Value::Operation(operation)));
+            }
+            map(tag_no_space(sep), |_| Value::Operation(operation))(s)
+        }
     }
     alt((
-        parse_op(Operator::Pow),
-        parse_op(Operator::Mult),
-        parse_op(Operator::Mod),
-        parse_op(Operator::Div),
-        parse_op(Operator::Add),
-        parse_op(Operator::Subtr),
-        parse_op(Operator::LessOrEqual),
-        parse_op(Operator::GreaterOrEqual),
-        parse_op(Operator::Less),
-        parse_op(Operator::Greater),
-        parse_op(Operator::Equal),
-        parse_op(Operator::NotEqual),
-        parse_op(Operator::Not),
-        parse_op(Operator::And),
-        parse_op(Operator::Or),
+        parse_op(Operator::NullCoalesce),
+        parse_op(Operator::RegexMatch),
+        alt((
+            parse_op(Operator::ShiftLeft),
+            parse_op(Operator::ShiftRight),
+            parse_op(Operator::Pow),
+            parse_op(Operator::Mult),
+            parse_op(Operator::Mod),
+            parse_op(Operator::Div),
+            parse_op(Operator::Add),
+            parse_op(Operator::Subtr),
+            parse_op(Operator::LessOrEqual),
+            parse_op(Operator::GreaterOrEqual),
+            parse_op(Operator::Less),
+            parse_op(Operator::Greater),
+            parse_op(Operator::Equal),
+            parse_op(Operator::NotEqual),
+            parse_op(Operator::Not),
+            parse_op(Operator::And),
+            parse_op(Operator::Or),
+            parse_op(Operator::BitAnd),
+            parse_op(Operator::BitOr),
+            parse_op(Operator::BitXor),
+            parse_op(Operator::BitNot),
+        )),
     ))(s)
 }
 
-fn parse_expression(s: &str) -> Res<Value> {
-    map_parser(
-        parse_multiline,
-        map(many1(preceded(opt(comments), parse_value)), Value::Expression),
+/// values of an expression, e.g `1 + x` or `x > 1 ? "a" : "b"`
+fn parse_expression_values(s: &str) -> Res<Value> {
+    map(
+        pair(
+            map(many1(preceded(opt(comments), parse_value)), Value::Expression),
+            opt(parse_ternary_branches),
+        ),
+        to_ternary,
     )(s)
 }
 
+fn parse_expression(s: &str) -> Res<Value> {
+    alt((map_parser(parse_multiline_block, parse_expression_values), |s| {
+        let (_, line) = alt((take_until("\n"), rest))(s)?;
+        let (line_rest, expr) = parse_expression_values(line)?;
+        // what's left on the line is given back e.g `1 } else { 2 }`
+        Ok((&s[line.len() - line_rest.len()..], expr))
+    }))(s)
+}
+
+fn parse_assign_operator(s: &str) -> Res<Option<Operator>> {
+    alt((
+        map(tag_no_space("+="), |_| Some(Operator::Add)),
+        map(tag_no_space("-="), |_| Some(Operator::Subtr)),
+        map(tag_no_space("*="), |_| Some(Operator::Mult)),
+        map(tag_no_space("/="), |_| Some(Operator::Div)),
+        map(tag_no_space("%="), |_| Some(Operator::Mod)),
+        map(tag_no_space("^="), |_| Some(Operator::Pow)),
+        // not `==` or `=~`
+        map(
+            delimited(
+                multispace0,
+                terminated(tag("="), not(one_of("=~"))),
+                multispace0,
+            ),
+            |_| None,
+        ),
+    ))(s)
+}
+
 fn parse_simple_instruction(s: &str) -> Res<Value> {
     alt((
         map(
-            separated_pair(
+            tuple((
                 alt((parse_array_access, parse_variable)),
-                tag_no_space("="),
-                parse_expression,
-            ),
-            |(name, expr)| Value::VariableExpr {
+                parse_assign_operator,
+                alt((
+                    parse_expression,
+                    parse_if_statement,
+                    parse_match_expression,
+                )),
+            )),
+            |(name, operator, expr)| Value::VariableExpr {
                 name: Box::new(name),
                 expr: Box::new(expr),
+                operator,
             },
         ),
         parse_expression,
     ))(s)
 }
 
+/// e.g `"a" | "b" => 1` or `_ => { println("other") }`
+fn parse_match_arm(s: &str) -> Res<MatchArm> {
+    let wildcard = map(tag_keyword("_"), |_| vec![]);
+    let pattern = alt((
+        parse_number,
+        map(preceded(tag_no_space("-"), parse_number), |n| {
+            Value::Expression(vec![Value::Operation(Operator::Subtr), n])
+        }),
+        parse_string,
+        parse_bool,
+    ));
+    let patterns = alt((
+        wildcard,
+        separated_list1(tag_no_space("|"), preceded(multispace0, pattern)),
+    ));
+    map(
+        terminated(
+            separated_pair(
+                preceded(opt(comments), patterns),
+                tag_no_space("=>"),
+                alt((parse_block, map(parse_expression, |e| vec![e]))),
+            ),
+            opt(tag_no_space(",")),
+        ),
+        |(patterns, exprs)| MatchArm { patterns, exprs },
+    )(s)
+}
+
+/// e.g `match x { 1 => "one", "a" | "b" => "letter", _ => "other" }`
+fn parse_match_expression(s: &str) -> Res<Value> {
+    map(
+        preceded(
+            tag_keyword(MATCH),
+            pair(
+                map(many1(parse_value), to_block),
+                delimited(
+                    tag_no_space("{"),
+                    many1(parse_match_arm),
+                    preceded(opt(comments), tag_no_space("}")),
+                ),
+            ),
+        ),
+        |(value, arms)| Value::MatchExpr { value: Box::new(value), arms },
+    )(s)
+}
+
 fn parse_if_statement(s: &str) -> Res<Value> {
     map(
         preceded(
@@ -267,14 +774,16 @@ fn parse_if_statement(s: &str) -> Res<Value> {
         },
     )(s)
 }
+fn parse_multiline_block(s: &str) -> Res<&str> {
+    preceded(
+        tag_no_space(MULTILINE),
+        delimited(tag_no_space("{"), take_until("}"), tag_no_space("}")),
+    )(s)
+}
+
+#[cfg(test)]
 fn parse_multiline(s: &str) -> Res<&str> {
-    alt((
-        preceded(
-            tag_no_space(MULTILINE),
-            delimited(tag_no_space("{"), take_until("}"), tag_no_space("}")),
-        ),
-        alt((take_until("\n"), rest)),
-    ))(s)
+    alt((parse_multiline_block, alt((take_until("\n"), rest))))(s)
 }
 fn parse_while_statement(s: &str) -> Res<Value> {
     map(
@@ -295,8 +804,10 @@ pub(super) fn parse_instructions(instructions: &str) -> Res<Vec<Value>> {
         many1(preceded(
             opt(comments),
             alt((
+                parse_struct_definition,
                 parse_while_statement,
                 parse_if_statement,
+                parse_match_expression,
                 parse_simple_instruction,
             )),
         )),
@@ -306,7 +817,7 @@ pub(super) fn parse_instructions(instructions: &str) -> Res<Vec<Value>> {
 
 #[cfg(test)]
 mod test {
-    use super::parse_multiline;
+    use super::{parse_multiline, parse_radix_number, Value};
 
     #[test]
     fn test_parse_multiline() {
@@ -323,4 +834,21 @@ mod test {
         .unwrap();
         assert!(rest.is_empty());
     }
+
+    #[test]
+    fn test_parse_radix_number() {
+        for (s, expected) in [
+            ("0xff", 0xff),
+            ("0x_ff", 0xff),
+            ("0b_1010", 0b1010),
+            ("0b__1010_1010", 0b1010_1010),
+            ("0o_755", 0o755),
+        ] {
+            let (rest, value) = parse_radix_number(s).unwrap();
+            assert!(rest.is_empty(), "{s}");
+            assert_eq!(Value::Integer(expected), value, "{s}");
+        }
+        assert!(parse_radix_number("0x_").is_err());
+        assert!(parse_radix_number("0b_2").is_err());
+    }
 }
@@ -0,0 +1,73 @@
+use std::{cell::RefCell, collections::HashMap};
+
+use regex::Regex;
+
+use super::Primitive;
+
+/// compiled regexes are reused, e.g when matching inside a loop
+const CACHE_SIZE: usize = 64;
+
+thread_local! {
+    static REGEX_CACHE: RefCell<HashMap<String, Regex>> =
+        RefCell::new(HashMap::new());
+}
+
+fn compile(re: &str) -> Option<Regex> {
+    REGEX_CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        if let Some(regex) = cache.get(re) {
+            return Some(regex.clone());
+        }
+        let regex = Regex::new(re).ok()?;
+        if cache.len() >= CACHE_SIZE {
+            cache.clear();
+        }
+        cache.insert(re.to_string(), regex.clone());
+        Some(regex)
+    })
+}
+
+const INVALID_REGEX: Primitive = Primitive::Error("invalid regular expression");
+
+pub(super) fn is_match(s: &str, re: &str) -> Primitive {
+    compile(re).map_or(INVALID_REGEX, |re| Primitive::Bool(re.is_match(s)))
+}
+
+pub(super) fn find_all(s: &str, re: &str) -> Primitive {
+    compile(re).map_or(INVALID_REGEX, |re| {
+        Primitive::Array(
+            re.find_iter(s)
+                .map(|m| Primitive::String(m.as_str().to_string()))
+                .collect(),
+        )
+    })
+}
+
+/// groups of the first match by index and by name, null if there's no match
+pub(super) fn captures(s: &str, re: &str) -> Primitive {
+    let re = match compile(re) {
+        Some(re) => re,
+        None => return INVALID_REGEX,
+    };
+    let caps = match re.captures(s) {
+        Some(caps) => caps,
+        None => return Primitive::Null,
+    };
+    let group = |m: Option<regex::Match>| {
+        m.map_or(Primitive::Null, |m| Primitive::String(m.as_str().to_string()))
+    };
+    let mut groups = (0..caps.len())
+        .map(|i| (i.to_string(), group(caps.get(i))))
+        .collect::<std::collections::BTreeMap<_, _>>();
+    for name in re.capture_names().flatten() {
+        groups.insert(name.to_string(), group(caps.name(name)));
+    }
+    Primitive::Map(groups)
+}
+
+/// e.g `replace_re("a1b2", "[0-9]", "_")`. `$1` refers to a group
+pub(super) fn replace_all(s: &str, re: &str, replacement: &str) -> Primitive {
+    compile(re).map_or(INVALID_REGEX, |re| {
+        Primitive::String(re.replace_all(s, replacement).into_owned())
+    })
+}
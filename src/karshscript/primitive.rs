@@ -2,24 +2,53 @@ use std::{
     cmp::Ordering,
     fmt::Display,
     iter::Sum,
-    ops::{Add, Div, Mul, Rem, Sub},
+    ops::{Add, BitAnd, BitOr, BitXor, Div, Mul, Rem, Shl, Shr, Sub},
 };
 
 use anyhow::Result;
 
-use crate::prelude::{Deserialize, Serialize};
+use num_bigint::BigInt;
+use num_rational::BigRational;
+
+use crate::prelude::{BTreeMap, Deserialize, Serialize};
+
+use super::{
+    limits,
+    number::{
+        big_abs, big_cmp, big_neg, big_op, bit_not, bit_op, display_rational,
+        int_overflow, number_mode, to_f64, BigOp, BitOp, NumberMode,
+    },
+};
 
 const MAX_U32_AS_I128: i128 = u32::MAX as i128;
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum Primitive {
     Int(i128),
+    /// integer that doesn't fit in an i128
+    BigInt(BigInt),
+    /// exact fraction, only used in rational mode
+    Rational(BigRational),
     Bool(bool),
     Double(f64),
     String(String),
     Array(Vec<Primitive>),
-    Error(&'static str),
+    Map(BTreeMap<String, Primitive>),
+    /// record e.g `Server { host: "a", port: 22 }`
+    Struct {
+        name: String,
+        fields: BTreeMap<String, Primitive>,
+    },
+    /// declaration of a record e.g `struct Server { host, port }`
+    StructDef {
+        name: String,
+        fields: Vec<String>,
+    },
+    #[serde(skip_deserializing)]
+    Error(#[serde(skip_deserializing)] &'static str),
     Unit,
+    /// missing value e.g `m?["missing"]`
+    Null,
 }
 
 // region: traits
@@ -50,10 +79,16 @@ pub trait Sin {
 }
 pub trait Array {
     fn index_at(&self, rhs: Self) -> Self;
+    /// start and end are unit when not specified
+    fn slice(&self, start: &Primitive, end: &Primitive) -> Self;
     fn len(&self) -> Primitive;
     fn swap_mem(&mut self, rhs: &mut Self, index: &Primitive) -> Primitive;
 }
 
+pub trait BitNot {
+    fn bit_not(&self) -> Self;
+}
+
 pub trait Cos {
     fn cos(&self) -> Self;
 }
@@ -109,6 +144,9 @@ impl Primitive {
         }
     }
     pub fn is_equal(&self, other: &Primitive) -> Primitive {
+        if matches!(self, Primitive::Null) || matches!(other, Primitive::Null) {
+            return Primitive::Bool(self == other);
+        }
         match self.partial_cmp(other) {
             Some(Ordering::Equal) => Primitive::Bool(true),
             Some(Ordering::Less) | Some(Ordering::Greater) => {
@@ -128,24 +166,46 @@ impl Primitive {
     }
 }
 
+fn display_element(p: &Primitive) -> String {
+    match p {
+        Primitive::String(s) => format!(r#""{s}""#),
+        _ => p.to_string(),
+    }
+}
+
 impl Display for Primitive {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Primitive::Int(i) => write!(f, "{i}"),
+            Primitive::BigInt(i) => write!(f, "{i}"),
+            Primitive::Rational(r) => write!(f, "{}", display_rational(r)),
             Primitive::Double(d) => write!(f, "{d}"),
             Primitive::Bool(b) => write!(f, "{b}"),
             Primitive::Error(e) => write!(f, "{e}"),
             Primitive::String(s) => write!(f, "{s}"),
             Primitive::Unit => Ok(()),
+            Primitive::Null => write!(f, "null"),
             Primitive::Array(arr) => {
-                let joined_arr = arr
+                let joined_arr =
+                    arr.iter().map(display_element).collect::<Vec<_>>();
+                write!(f, "[{}]", joined_arr[..].join(", "))
+            }
+            Primitive::Map(map) => {
+                let joined_map = map
                     .iter()
-                    .map(|p| match p {
-                        Primitive::String(s) => format!(r#""{s}""#),
-                        _ => p.to_string(),
-                    })
+                    .map(|(k, v)| format!(r#""{k}": {}"#, display_element(v)))
                     .collect::<Vec<_>>();
-                write!(f, "[{}]", joined_arr[..].join(", "))
+                write!(f, "{{{}}}", joined_map[..].join(", "))
+            }
+            Primitive::Struct { name, fields } => {
+                let joined_fields = fields
+                    .iter()
+                    .map(|(k, v)| format!("{k}: {}", display_element(v)))
+                    .collect::<Vec<_>>();
+                write!(f, "{name} {{ {} }}", joined_fields[..].join(", "))
+            }
+            Primitive::StructDef { name, fields } => {
+                write!(f, "struct {name} {{ {} }}", fields[..].join(", "))
             }
         }
     }
@@ -156,6 +216,9 @@ impl Sin for Primitive {
         match self {
             Primitive::Int(i) => Primitive::Double((*i as f64).sin()),
             Primitive::Double(d) => Primitive::Double(d.sin()),
+            Primitive::BigInt(_) | Primitive::Rational(_) => {
+                Primitive::Double(to_f64(self).unwrap_or(f64::NAN).sin())
+            }
             Primitive::Bool(_b) => {
                 Primitive::Error("call to sin() on a boolean value")
             }
@@ -165,9 +228,18 @@ impl Sin for Primitive {
             Primitive::Unit => {
                 Primitive::Error("call to sin() on an unit value")
             }
+            Primitive::Null => {
+                Primitive::Error("call to sin() on a null value")
+            }
             Primitive::Array(_) => {
                 Primitive::Error("call to sin() on an array value")
             }
+            Primitive::Map(_) => {
+                Primitive::Error("call to sin() on a map value")
+            }
+            Primitive::Struct { .. } | Primitive::StructDef { .. } => {
+                Primitive::Error("call to sin() on a struct value")
+            }
             Primitive::Error(e) => panic!("call to sin() on an error. {e}"),
         }
     }
@@ -178,6 +250,9 @@ impl Cos for Primitive {
         match self {
             Primitive::Int(i) => Primitive::Double((*i as f64).cos()),
             Primitive::Double(d) => Primitive::Double(d.cos()),
+            Primitive::BigInt(_) | Primitive::Rational(_) => {
+                Primitive::Double(to_f64(self).unwrap_or(f64::NAN).cos())
+            }
             Primitive::Bool(_b) => {
                 Primitive::Error("call to cos() on a boolean value")
             }
@@ -187,9 +262,18 @@ impl Cos for Primitive {
             Primitive::Array(_s) => {
                 Primitive::Error("call to cos() on an array value")
             }
+            Primitive::Map(_) => {
+                Primitive::Error("call to cos() on a map value")
+            }
+            Primitive::Struct { .. } | Primitive::StructDef { .. } => {
+                Primitive::Error("call to cos() on a struct value")
+            }
             Primitive::Unit => {
                 Primitive::Error("call to cos() on an unit value")
             }
+            Primitive::Null => {
+                Primitive::Error("call to cos() on a null value")
+            }
             Primitive::Error(e) => panic!("call to cos() on an error. {e}"),
         }
     }
@@ -200,6 +284,9 @@ impl Tan for Primitive {
         match self {
             Primitive::Int(i) => Primitive::Double((*i as f64).tan()),
             Primitive::Double(d) => Primitive::Double(d.tan()),
+            Primitive::BigInt(_) | Primitive::Rational(_) => {
+                Primitive::Double(to_f64(self).unwrap_or(f64::NAN).tan())
+            }
             Primitive::Bool(_b) => {
                 Primitive::Error("call to tan() on a boolean value")
             }
@@ -209,9 +296,18 @@ impl Tan for Primitive {
             Primitive::Array(_s) => {
                 Primitive::Error("call to tan() on an array value")
             }
+            Primitive::Map(_) => {
+                Primitive::Error("call to tan() on a map value")
+            }
+            Primitive::Struct { .. } | Primitive::StructDef { .. } => {
+                Primitive::Error("call to tan() on a struct value")
+            }
             Primitive::Unit => {
                 Primitive::Error("call to tan() on an unit value")
             }
+            Primitive::Null => {
+                Primitive::Error("call to tan() on a null value")
+            }
             Primitive::Error(e) => panic!("call to tan() on an error. {e}"),
         }
     }
@@ -222,6 +318,9 @@ impl Logarithm for Primitive {
         match self {
             Primitive::Int(i) => Primitive::Double((*i as f64).log10()),
             Primitive::Double(d) => Primitive::Double(d.log10()),
+            Primitive::BigInt(_) | Primitive::Rational(_) => {
+                Primitive::Double(to_f64(self).unwrap_or(f64::NAN).log10())
+            }
             Primitive::Bool(_b) => {
                 Primitive::Error("call to log() on a boolean value")
             }
@@ -231,9 +330,18 @@ impl Logarithm for Primitive {
             Primitive::Array(_s) => {
                 Primitive::Error("call to log() on an array value")
             }
+            Primitive::Map(_) => {
+                Primitive::Error("call to log() on a map value")
+            }
+            Primitive::Struct { .. } | Primitive::StructDef { .. } => {
+                Primitive::Error("call to log() on a struct value")
+            }
             Primitive::Unit => {
                 Primitive::Error("call to log() on an unit value")
             }
+            Primitive::Null => {
+                Primitive::Error("call to log() on a null value")
+            }
             Primitive::Error(e) => panic!("call to log() on an error. {e}"),
         }
     }
@@ -241,6 +349,9 @@ impl Logarithm for Primitive {
         match self {
             Primitive::Int(i) => Primitive::Double((*i as f64).ln()),
             Primitive::Double(d) => Primitive::Double(d.ln()),
+            Primitive::BigInt(_) | Primitive::Rational(_) => {
+                Primitive::Double(to_f64(self).unwrap_or(f64::NAN).ln())
+            }
             Primitive::Bool(_b) => {
                 Primitive::Error("call to ln() on a boolean value")
             }
@@ -250,9 +361,16 @@ impl Logarithm for Primitive {
             Primitive::Array(_s) => {
                 Primitive::Error("call to ln() on an array value")
             }
+            Primitive::Map(_) => {
+                Primitive::Error("call to ln() on a map value")
+            }
+            Primitive::Struct { .. } | Primitive::StructDef { .. } => {
+                Primitive::Error("call to ln() on a struct value")
+            }
             Primitive::Unit => {
                 Primitive::Error("call to ln() on an unit value")
             }
+            Primitive::Null => Primitive::Error("call to ln() on a null value"),
             Primitive::Error(e) => panic!("call to ln() on an error. {e}"),
         }
     }
@@ -263,6 +381,9 @@ impl Sqrt for Primitive {
         match self {
             Primitive::Int(i) => Primitive::Double((*i as f64).sqrt()),
             Primitive::Double(d) => Primitive::Double(d.sqrt()),
+            Primitive::BigInt(_) | Primitive::Rational(_) => {
+                Primitive::Double(to_f64(self).unwrap_or(f64::NAN).sqrt())
+            }
             Primitive::Bool(_b) => {
                 Primitive::Error("call to sqrt() on a boolean value")
             }
@@ -272,9 +393,18 @@ impl Sqrt for Primitive {
             Primitive::Array(_s) => {
                 Primitive::Error("call to sqrt() on an array value")
             }
+            Primitive::Map(_) => {
+                Primitive::Error("call to sqrt() on a map value")
+            }
+            Primitive::Struct { .. } | Primitive::StructDef { .. } => {
+                Primitive::Error("call to sqrt() on a struct value")
+            }
             Primitive::Unit => {
                 Primitive::Error("call to sqrt() on an unit value")
             }
+            Primitive::Null => {
+                Primitive::Error("call to sqrt() on a null value")
+            }
             Primitive::Error(e) => panic!("call to sqrt() on an error. {e}"),
         }
     }
@@ -282,7 +412,10 @@ impl Sqrt for Primitive {
 impl Abs for Primitive {
     fn abs(&self) -> Self {
         match self {
-            Primitive::Int(i) => Primitive::Int(i.abs()),
+            Primitive::Int(i) => {
+                i.checked_abs().map_or_else(|| big_abs(self), Primitive::Int)
+            }
+            Primitive::BigInt(_) | Primitive::Rational(_) => big_abs(self),
             Primitive::Double(d) => Primitive::Double(d.abs()),
             Primitive::Bool(_b) => {
                 Primitive::Error("call to abs() on a boolean value")
@@ -293,9 +426,18 @@ impl Abs for Primitive {
             Primitive::Array(_s) => {
                 Primitive::Error("call to abs() on an array value")
             }
+            Primitive::Map(_) => {
+                Primitive::Error("call to abs() on a map value")
+            }
+            Primitive::Struct { .. } | Primitive::StructDef { .. } => {
+                Primitive::Error("call to abs() on a struct value")
+            }
             Primitive::Unit => {
                 Primitive::Error("call to abs() on an unit value")
             }
+            Primitive::Null => {
+                Primitive::Error("call to abs() on a null value")
+            }
             Primitive::Error(e) => panic!("call to abs() on an error. {e}"),
         }
     }
@@ -304,10 +446,22 @@ impl Abs for Primitive {
 impl Pow for Primitive {
     fn pow(&self, rhs: Self) -> Self {
         match self {
+            l @ (Primitive::BigInt(_) | Primitive::Rational(_)) => {
+                big_op(BigOp::Pow, l.clone(), rhs)
+            }
             Primitive::Int(l) => match rhs {
+                r @ (Primitive::BigInt(_) | Primitive::Rational(_)) => {
+                    big_op(BigOp::Pow, Primitive::Int(*l), r)
+                }
                 #[allow(clippy::manual_range_contains)]
                 Primitive::Int(r) if r >= 0 && r <= MAX_U32_AS_I128 => {
-                    Primitive::Int(l.pow(r as u32))
+                    l.checked_pow(r as u32).map_or_else(
+                        || int_overflow(BigOp::Pow, *l, r),
+                        Primitive::Int,
+                    )
+                }
+                Primitive::Int(r) if number_mode() == NumberMode::Rational => {
+                    big_op(BigOp::Pow, Primitive::Int(*l), Primitive::Int(r))
                 }
                 Primitive::Int(r) => {
                     Primitive::Double((*l as f64).powf(r as f64))
@@ -322,12 +476,24 @@ impl Pow for Primitive {
                 Primitive::Array(_s) => {
                     Primitive::Error("call to pow() on an array value")
                 }
+                Primitive::Map(_) => {
+                    Primitive::Error("call to pow() on a map value")
+                }
+                Primitive::Struct { .. } | Primitive::StructDef { .. } => {
+                    Primitive::Error("call to pow() on a struct value")
+                }
                 Primitive::Unit => {
                     Primitive::Error("call to pow() on an unit value")
                 }
+                Primitive::Null => {
+                    Primitive::Error("call to pow() on a null value")
+                }
                 Primitive::Error(e) => panic!("call to pow() on an error. {e}"),
             },
             Primitive::Double(l) => match rhs {
+                r @ (Primitive::BigInt(_) | Primitive::Rational(_)) => {
+                    big_op(BigOp::Pow, Primitive::Double(*l), r)
+                }
                 Primitive::Int(r) => Primitive::Double(l.powf(r as f64)),
                 Primitive::Double(r) => Primitive::Double((*l as f64).powf(r)),
                 Primitive::Bool(_b) => {
@@ -336,12 +502,21 @@ impl Pow for Primitive {
                 Primitive::Array(_s) => {
                     Primitive::Error("call to pow() on an array value")
                 }
+                Primitive::Map(_) => {
+                    Primitive::Error("call to pow() on a map value")
+                }
+                Primitive::Struct { .. } | Primitive::StructDef { .. } => {
+                    Primitive::Error("call to pow() on a struct value")
+                }
                 Primitive::String(_s) => {
                     Primitive::Error("call to pow() on a string value")
                 }
                 Primitive::Unit => {
                     Primitive::Error("call to pow() on an unit value")
                 }
+                Primitive::Null => {
+                    Primitive::Error("call to pow() on a null value")
+                }
                 Primitive::Error(e) => panic!("call to pow() on an error. {e}"),
             },
             Primitive::String(_s) => {
@@ -353,9 +528,18 @@ impl Pow for Primitive {
             Primitive::Array(_s) => {
                 Primitive::Error("call to pow() on an array value")
             }
+            Primitive::Map(_) => {
+                Primitive::Error("call to pow() on a map value")
+            }
+            Primitive::Struct { .. } | Primitive::StructDef { .. } => {
+                Primitive::Error("call to pow() on a struct value")
+            }
             Primitive::Unit => {
                 Primitive::Error("call to pow() on an unit value")
             }
+            Primitive::Null => {
+                Primitive::Error("call to pow() on a null value")
+            }
             Primitive::Error(e) => panic!("call to pow() on an error. {e}"),
         }
     }
@@ -376,8 +560,17 @@ impl Add for Primitive {
 
     fn add(self, rhs: Self) -> Self::Output {
         match self {
+            l @ (Primitive::BigInt(_) | Primitive::Rational(_)) => {
+                big_op(BigOp::Add, l, rhs)
+            }
             Primitive::Int(l) => match rhs {
-                Primitive::Int(r) => Primitive::Int(l + r),
+                r @ (Primitive::BigInt(_) | Primitive::Rational(_)) => {
+                    big_op(BigOp::Add, Primitive::Int(l), r)
+                }
+                Primitive::Int(r) => l.checked_add(r).map_or_else(
+                    || int_overflow(BigOp::Add, l, r),
+                    Primitive::Int,
+                ),
                 Primitive::Double(r) => Primitive::Double(l as f64 + r),
                 Primitive::Bool(_b) => {
                     Primitive::Error("call to add() on a boolean value")
@@ -385,13 +578,25 @@ impl Add for Primitive {
                 Primitive::Array(_s) => {
                     Primitive::Error("call to add() on an array value")
                 }
+                Primitive::Map(_) => {
+                    Primitive::Error("call to add() on a map value")
+                }
+                Primitive::Struct { .. } | Primitive::StructDef { .. } => {
+                    Primitive::Error("call to add() on a struct value")
+                }
                 Primitive::Unit => {
                     Primitive::Error("call to add() on an unit value")
                 }
+                Primitive::Null => {
+                    Primitive::Error("call to add() on a null value")
+                }
                 Primitive::String(s) => Primitive::String(format!("{l}{s}")),
                 Primitive::Error(e) => panic!("call to add() on an error. {e}"),
             },
             Primitive::Double(l) => match rhs {
+                r @ (Primitive::BigInt(_) | Primitive::Rational(_)) => {
+                    big_op(BigOp::Add, Primitive::Double(l), r)
+                }
                 Primitive::Int(r) => Primitive::Double(l + r as f64),
                 Primitive::Double(r) => Primitive::Double(l as f64 + r),
                 Primitive::Bool(_b) => {
@@ -400,9 +605,18 @@ impl Add for Primitive {
                 Primitive::Array(_s) => {
                     Primitive::Error("call to add() on an array value")
                 }
+                Primitive::Map(_) => {
+                    Primitive::Error("call to add() on a map value")
+                }
+                Primitive::Struct { .. } | Primitive::StructDef { .. } => {
+                    Primitive::Error("call to add() on a struct value")
+                }
                 Primitive::Unit => {
                     Primitive::Error("call to add() on an unit value")
                 }
+                Primitive::Null => {
+                    Primitive::Error("call to add() on a null value")
+                }
                 Primitive::String(s) => Primitive::String(format!("{l}{s}")),
                 Primitive::Error(e) => panic!("call to add() on an error. {e}"),
             },
@@ -412,15 +626,32 @@ impl Add for Primitive {
             }
             Primitive::Array(mut l) => match rhs {
                 Primitive::Array(mut r) => {
+                    if limits::exceeds_array_len(l.len() + r.len()) {
+                        return Primitive::Error(limits::ARRAY_TOO_LONG);
+                    }
                     l.append(&mut r);
                     Primitive::Array(l)
                 }
                 _ => Primitive::Error("illegal call to add() on an array"),
             },
+            Primitive::Map(mut l) => match rhs {
+                Primitive::Map(r) => {
+                    l.extend(r);
+                    Primitive::Map(l)
+                }
+                _ => Primitive::Error("illegal call to add() on a map"),
+            },
+            Primitive::Struct { .. } | Primitive::StructDef { .. } => {
+                Primitive::Error("call to add() on a struct value")
+            }
 
             Primitive::Unit => {
                 Primitive::Error("call to add() on an unit value")
             }
+
+            Primitive::Null => {
+                Primitive::Error("call to add() on a null value")
+            }
             Primitive::Error(e) => panic!("call to add() on an error. {e}"),
         }
     }
@@ -431,8 +662,17 @@ impl Sub for Primitive {
 
     fn sub(self, rhs: Self) -> Self::Output {
         match self {
+            l @ (Primitive::BigInt(_) | Primitive::Rational(_)) => {
+                big_op(BigOp::Sub, l, rhs)
+            }
             Primitive::Int(l) => match rhs {
-                Primitive::Int(r) => Primitive::Int(l - r),
+                r @ (Primitive::BigInt(_) | Primitive::Rational(_)) => {
+                    big_op(BigOp::Sub, Primitive::Int(l), r)
+                }
+                Primitive::Int(r) => l.checked_sub(r).map_or_else(
+                    || int_overflow(BigOp::Sub, l, r),
+                    Primitive::Int,
+                ),
                 Primitive::Double(r) => Primitive::Double(l as f64 - r),
                 Primitive::Bool(_) | Primitive::String(_) => Primitive::Error(
                     "call to sub() on a boolean or string value",
@@ -440,12 +680,24 @@ impl Sub for Primitive {
                 Primitive::Array(_s) => {
                     Primitive::Error("call to sub() on an array value")
                 }
+                Primitive::Map(_) => {
+                    Primitive::Error("call to sub() on a map value")
+                }
+                Primitive::Struct { .. } | Primitive::StructDef { .. } => {
+                    Primitive::Error("call to sub() on a struct value")
+                }
                 Primitive::Error(e) => panic!("call to sub() on an error. {e}"),
                 Primitive::Unit => {
                     Primitive::Error("call to sub() on an unit value")
                 }
+                Primitive::Null => {
+                    Primitive::Error("call to sub() on a null value")
+                }
             },
             Primitive::Double(l) => match rhs {
+                r @ (Primitive::BigInt(_) | Primitive::Rational(_)) => {
+                    big_op(BigOp::Sub, Primitive::Double(l), r)
+                }
                 Primitive::Int(r) => Primitive::Double(l - r as f64),
                 Primitive::Double(r) => Primitive::Double(l as f64 - r),
                 Primitive::Bool(_) | Primitive::String(_) => Primitive::Error(
@@ -454,9 +706,18 @@ impl Sub for Primitive {
                 Primitive::Array(_s) => {
                     Primitive::Error("call to sub() on an array value")
                 }
+                Primitive::Map(_) => {
+                    Primitive::Error("call to sub() on a map value")
+                }
+                Primitive::Struct { .. } | Primitive::StructDef { .. } => {
+                    Primitive::Error("call to sub() on a struct value")
+                }
                 Primitive::Unit => {
                     Primitive::Error("call to sub() on an unit value")
                 }
+                Primitive::Null => {
+                    Primitive::Error("call to sub() on a null value")
+                }
                 Primitive::Error(e) => panic!("call to sub() on an error. {e}"),
             },
             Primitive::Bool(_) | Primitive::String(_) => {
@@ -465,9 +726,18 @@ impl Sub for Primitive {
             Primitive::Unit => {
                 Primitive::Error("call to sub() on an unit value")
             }
+            Primitive::Null => {
+                Primitive::Error("call to sub() on a null value")
+            }
             Primitive::Array(_s) => {
                 Primitive::Error("call to sub() on an array value")
             }
+            Primitive::Map(_) => {
+                Primitive::Error("call to sub() on a map value")
+            }
+            Primitive::Struct { .. } | Primitive::StructDef { .. } => {
+                Primitive::Error("call to sub() on a struct value")
+            }
             Primitive::Error(e) => panic!("call to sub() on an error. {e}"),
         }
     }
@@ -478,8 +748,17 @@ impl Rem for Primitive {
 
     fn rem(self, rhs: Self) -> Self::Output {
         match self {
+            l @ (Primitive::BigInt(_) | Primitive::Rational(_)) => {
+                big_op(BigOp::Rem, l, rhs)
+            }
             Primitive::Int(l) => match rhs {
-                Primitive::Int(r) if r != 0 => Primitive::Int(l % r),
+                r @ (Primitive::BigInt(_) | Primitive::Rational(_)) => {
+                    big_op(BigOp::Rem, Primitive::Int(l), r)
+                }
+                Primitive::Int(r) if r != 0 => l.checked_rem(r).map_or_else(
+                    || int_overflow(BigOp::Rem, l, r),
+                    Primitive::Int,
+                ),
                 Primitive::Double(r) => Primitive::Double(l as f64 % r),
                 Primitive::Bool(_) | Primitive::String(_) => Primitive::Error(
                     "call to rem() on a boolean or string value",
@@ -487,13 +766,25 @@ impl Rem for Primitive {
                 Primitive::Array(_s) => {
                     Primitive::Error("call to rem() on an array value")
                 }
+                Primitive::Map(_) => {
+                    Primitive::Error("call to rem() on a map value")
+                }
+                Primitive::Struct { .. } | Primitive::StructDef { .. } => {
+                    Primitive::Error("call to rem() on a struct value")
+                }
                 Primitive::Unit => {
                     Primitive::Error("call to rem() on an unit value")
                 }
+                Primitive::Null => {
+                    Primitive::Error("call to rem() on a null value")
+                }
                 Primitive::Error(e) => panic!("call to rem() on an error. {e}"),
                 _ => Primitive::Double(f64::NAN),
             },
             Primitive::Double(l) => match rhs {
+                r @ (Primitive::BigInt(_) | Primitive::Rational(_)) => {
+                    big_op(BigOp::Rem, Primitive::Double(l), r)
+                }
                 Primitive::Int(r) => Primitive::Double(l % r as f64),
                 Primitive::Double(r) => Primitive::Double(l as f64 % r),
                 Primitive::Bool(_) | Primitive::String(_) => Primitive::Error(
@@ -502,9 +793,18 @@ impl Rem for Primitive {
                 Primitive::Array(_s) => {
                     Primitive::Error("call to rem() on an array value")
                 }
+                Primitive::Map(_) => {
+                    Primitive::Error("call to rem() on a map value")
+                }
+                Primitive::Struct { .. } | Primitive::StructDef { .. } => {
+                    Primitive::Error("call to rem() on a struct value")
+                }
                 Primitive::Unit => {
                     Primitive::Error("call to rem() on an unit value")
                 }
+                Primitive::Null => {
+                    Primitive::Error("call to rem() on a null value")
+                }
                 Primitive::Error(e) => panic!("call to rem() on an error. {e}"),
             },
             Primitive::Bool(_) | Primitive::String(_) => {
@@ -513,9 +813,18 @@ impl Rem for Primitive {
             Primitive::Array(_s) => {
                 Primitive::Error("call to rem() on an array value")
             }
+            Primitive::Map(_) => {
+                Primitive::Error("call to rem() on a map value")
+            }
+            Primitive::Struct { .. } | Primitive::StructDef { .. } => {
+                Primitive::Error("call to rem() on a struct value")
+            }
             Primitive::Unit => {
                 Primitive::Error("call to rem() on an unit value")
             }
+            Primitive::Null => {
+                Primitive::Error("call to rem() on a null value")
+            }
             Primitive::Error(e) => panic!("call to rem() on an error. {e}"),
         }
     }
@@ -524,13 +833,25 @@ impl Mul for Primitive {
     type Output = Primitive;
 
     fn mul(self, rhs: Self) -> Self::Output {
-        fn multiply_array(arr: Vec<Primitive>, n: i128) -> Vec<Primitive> {
-            let arr_size = arr.len();
-            arr.into_iter().cycle().take(n as usize * arr_size).collect()
+        fn multiply_array(arr: Vec<Primitive>, n: i128) -> Primitive {
+            let len = (n as usize).saturating_mul(arr.len());
+            if limits::exceeds_array_len(len) {
+                return Primitive::Error(limits::ARRAY_TOO_LONG);
+            }
+            Primitive::Array(arr.into_iter().cycle().take(len).collect())
         }
         match self {
+            l @ (Primitive::BigInt(_) | Primitive::Rational(_)) => {
+                big_op(BigOp::Mul, l, rhs)
+            }
             Primitive::Int(l) => match rhs {
-                Primitive::Int(r) => Primitive::Int(l.wrapping_mul(r)),
+                r @ (Primitive::BigInt(_) | Primitive::Rational(_)) => {
+                    big_op(BigOp::Mul, Primitive::Int(l), r)
+                }
+                Primitive::Int(r) => l.checked_mul(r).map_or_else(
+                    || int_overflow(BigOp::Mul, l, r),
+                    Primitive::Int,
+                ),
                 Primitive::Double(r) => Primitive::Double(l as f64 * r),
                 Primitive::Bool(_) | Primitive::String(_) => Primitive::Error(
                     "call to mul() on a boolean or string value",
@@ -538,10 +859,22 @@ impl Mul for Primitive {
                 Primitive::Unit => {
                     Primitive::Error("call to mul() on an unit value")
                 }
+                Primitive::Null => {
+                    Primitive::Error("call to mul() on a null value")
+                }
                 Primitive::Error(e) => panic!("call to mul() on an error. {e}"),
-                Primitive::Array(r) => Primitive::Array(multiply_array(r, l)),
+                Primitive::Array(r) => multiply_array(r, l),
+                Primitive::Map(_) => {
+                    Primitive::Error("call to mul() on a map value")
+                }
+                Primitive::Struct { .. } | Primitive::StructDef { .. } => {
+                    Primitive::Error("call to mul() on a struct value")
+                }
             },
             Primitive::Double(l) => match rhs {
+                r @ (Primitive::BigInt(_) | Primitive::Rational(_)) => {
+                    big_op(BigOp::Mul, Primitive::Double(l), r)
+                }
                 Primitive::Int(r) => Primitive::Double(l * r as f64),
                 Primitive::Double(r) => Primitive::Double(l as f64 * r),
                 Primitive::Bool(_) | Primitive::String(_) => Primitive::Error(
@@ -550,9 +883,18 @@ impl Mul for Primitive {
                 Primitive::Array(_s) => {
                     Primitive::Error("call to mul() on an array value")
                 }
+                Primitive::Map(_) => {
+                    Primitive::Error("call to mul() on a map value")
+                }
+                Primitive::Struct { .. } | Primitive::StructDef { .. } => {
+                    Primitive::Error("call to mul() on a struct value")
+                }
                 Primitive::Unit => {
                     Primitive::Error("call to mul() on an unit value")
                 }
+                Primitive::Null => {
+                    Primitive::Error("call to mul() on a null value")
+                }
                 Primitive::Error(e) => panic!("call to mul() on an error. {e}"),
             },
             Primitive::String(l) => match rhs {
@@ -567,10 +909,19 @@ impl Mul for Primitive {
             Primitive::Unit => {
                 Primitive::Error("call to mul() on an unit value")
             }
+            Primitive::Null => {
+                Primitive::Error("call to mul() on a null value")
+            }
             Primitive::Array(l) => match rhs {
-                Primitive::Int(n) => Primitive::Array(multiply_array(l, n)),
+                Primitive::Int(n) => multiply_array(l, n),
                 _ => Primitive::Error("invalid call to mul() on an array"),
             },
+            Primitive::Map(_) => {
+                Primitive::Error("call to mul() on a map value")
+            }
+            Primitive::Struct { .. } | Primitive::StructDef { .. } => {
+                Primitive::Error("call to mul() on a struct value")
+            }
             Primitive::Error(e) => panic!("call to mul() on an error. {e}"),
         }
     }
@@ -580,8 +931,29 @@ impl Div for Primitive {
 
     fn div(self, rhs: Self) -> Self::Output {
         match self {
+            l @ (Primitive::BigInt(_) | Primitive::Rational(_)) => {
+                big_op(BigOp::Div, l, rhs)
+            }
             Primitive::Int(l) => match rhs {
-                Primitive::Int(r) if r != 0 => Primitive::Int(l / r),
+                r @ (Primitive::BigInt(_) | Primitive::Rational(_)) => {
+                    big_op(BigOp::Div, Primitive::Int(l), r)
+                }
+                Primitive::Int(r) if r != 0 => {
+                    match (l.checked_rem(r), number_mode()) {
+                        // not exact, e.g 1/3
+                        (Some(rem), NumberMode::Rational) if rem != 0 => {
+                            big_op(
+                                BigOp::Div,
+                                Primitive::Int(l),
+                                Primitive::Int(r),
+                            )
+                        }
+                        _ => l.checked_div(r).map_or_else(
+                            || int_overflow(BigOp::Div, l, r),
+                            Primitive::Int,
+                        ),
+                    }
+                }
                 Primitive::Double(r) => Primitive::Double(l as f64 / r),
                 Primitive::Int(_) if l >= 1 => Primitive::Double(f64::INFINITY),
                 Primitive::Bool(_) | Primitive::String(_) => Primitive::Error(
@@ -590,13 +962,25 @@ impl Div for Primitive {
                 Primitive::Array(_) => {
                     Primitive::Error("call to div() on an array value")
                 }
+                Primitive::Map(_) => {
+                    Primitive::Error("call to div() on a map value")
+                }
+                Primitive::Struct { .. } | Primitive::StructDef { .. } => {
+                    Primitive::Error("call to div() on a struct value")
+                }
                 Primitive::Unit => {
                     Primitive::Error("call to div() on an unit value")
                 }
+                Primitive::Null => {
+                    Primitive::Error("call to div() on a null value")
+                }
                 Primitive::Error(e) => panic!("call to div() on an error. {e}"),
                 _ => Primitive::Double(f64::NAN),
             },
             Primitive::Double(l) => match rhs {
+                r @ (Primitive::BigInt(_) | Primitive::Rational(_)) => {
+                    big_op(BigOp::Div, Primitive::Double(l), r)
+                }
                 Primitive::Int(r) => Primitive::Double(l / r as f64),
                 Primitive::Double(r) => Primitive::Double(l as f64 / r),
                 Primitive::Bool(_) | Primitive::String(_) => Primitive::Error(
@@ -605,9 +989,18 @@ impl Div for Primitive {
                 Primitive::Array(_) => {
                     Primitive::Error("call to div() on an array value")
                 }
+                Primitive::Map(_) => {
+                    Primitive::Error("call to div() on a map value")
+                }
+                Primitive::Struct { .. } | Primitive::StructDef { .. } => {
+                    Primitive::Error("call to div() on a struct value")
+                }
                 Primitive::Unit => {
                     Primitive::Error("call to div() on an unit value")
                 }
+                Primitive::Null => {
+                    Primitive::Error("call to div() on a null value")
+                }
                 Primitive::Error(e) => panic!("call to div() on an error. {e}"),
             },
             Primitive::Bool(_) | Primitive::String(_) => {
@@ -616,9 +1009,18 @@ impl Div for Primitive {
             Primitive::Array(_) => {
                 Primitive::Error("call to div() on an array value")
             }
+            Primitive::Map(_) => {
+                Primitive::Error("call to div() on a map value")
+            }
+            Primitive::Struct { .. } | Primitive::StructDef { .. } => {
+                Primitive::Error("call to div() on a struct value")
+            }
             Primitive::Unit => {
                 Primitive::Error("call to div() on an unit value")
             }
+            Primitive::Null => {
+                Primitive::Error("call to div() on a null value")
+            }
             Primitive::Error(e) => panic!("call to div() on an error. {e}"),
         }
     }
@@ -629,7 +1031,10 @@ impl std::ops::Neg for Primitive {
 
     fn neg(self) -> Self::Output {
         match self {
-            Primitive::Int(n) => Primitive::Int(-n),
+            Primitive::Int(n) => n
+                .checked_neg()
+                .map_or_else(|| big_neg(&Primitive::Int(n)), Primitive::Int),
+            n @ (Primitive::BigInt(_) | Primitive::Rational(_)) => big_neg(&n),
             Primitive::Double(n) => Primitive::Double(-n),
             Primitive::Bool(_) | Primitive::String(_) => {
                 Primitive::Error("call to neg() on a boolean or string value")
@@ -637,9 +1042,18 @@ impl std::ops::Neg for Primitive {
             Primitive::Array(_) => {
                 Primitive::Error("call to neg() on an array value")
             }
+            Primitive::Map(_) => {
+                Primitive::Error("call to neg() on a map value")
+            }
+            Primitive::Struct { .. } | Primitive::StructDef { .. } => {
+                Primitive::Error("call to neg() on a struct value")
+            }
             Primitive::Unit => {
                 Primitive::Error("call to neg() on an unit value")
             }
+            Primitive::Null => {
+                Primitive::Error("call to neg() on a null value")
+            }
             Primitive::Error(e) => panic!("call to div() on an error. {e}"),
         }
     }
@@ -654,18 +1068,30 @@ impl std::ops::Not for Primitive {
             Primitive::Int(_) => {
                 Primitive::Error("call to not() on an int value")
             }
+            Primitive::BigInt(_) | Primitive::Rational(_) => {
+                Primitive::Error("call to not() on a number value")
+            }
             Primitive::Double(_) => {
                 Primitive::Error("call to not() on a double value")
             }
             Primitive::Array(_) => {
                 Primitive::Error("call to not() on an array value")
             }
+            Primitive::Map(_) => {
+                Primitive::Error("call to not() on a map value")
+            }
+            Primitive::Struct { .. } | Primitive::StructDef { .. } => {
+                Primitive::Error("call to not() on a struct value")
+            }
             Primitive::String(_) => {
                 Primitive::Error("call to not() on a string value")
             }
             Primitive::Unit => {
                 Primitive::Error("call to not() on an unit value")
             }
+            Primitive::Null => {
+                Primitive::Error("call to not() on a null value")
+            }
             Primitive::Error(e) => panic!("call to div() on an error. {e}"),
         }
     }
@@ -676,23 +1102,39 @@ impl Or for Primitive {
             Primitive::Bool(l) => match n {
                 Primitive::Bool(r) => Primitive::Bool(*l || r),
                 Primitive::Int(_) => Primitive::Error("'or' on an int value"),
+                Primitive::BigInt(_) | Primitive::Rational(_) => {
+                    Primitive::Error("'or' on a number value")
+                }
                 Primitive::Double(_) => {
                     Primitive::Error("'or'on a double value")
                 }
                 Primitive::Array(_) => {
                     Primitive::Error("'or' on an array value")
                 }
+                Primitive::Map(_) => Primitive::Error("'or' on a map value"),
+                Primitive::Struct { .. } | Primitive::StructDef { .. } => {
+                    Primitive::Error("'or' on a struct value")
+                }
                 Primitive::String(_) => {
                     Primitive::Error("'or'on a string value")
                 }
                 Primitive::Unit => Primitive::Error("'or' on an unit value"),
+                Primitive::Null => Primitive::Error("'or' on a null value"),
                 Primitive::Error(e) => panic!("'or' on an error. {e}"),
             },
             Primitive::Array(_) => Primitive::Error("'or' on an array value"),
+            Primitive::Map(_) => Primitive::Error("'or' on a map value"),
+            Primitive::Struct { .. } | Primitive::StructDef { .. } => {
+                Primitive::Error("'or' on a struct value")
+            }
             Primitive::Int(_) => Primitive::Error("'or' on an int value"),
+            Primitive::BigInt(_) | Primitive::Rational(_) => {
+                Primitive::Error("'or' on a number value")
+            }
             Primitive::String(_) => Primitive::Error("'or' on an string value"),
             Primitive::Double(_) => Primitive::Error("'or'on a double value"),
             Primitive::Unit => Primitive::Error("'or' on an unit value"),
+            Primitive::Null => Primitive::Error("'or' on a null value"),
             Primitive::Error(e) => panic!("'or' on an error. {e}"),
         }
     }
@@ -703,24 +1145,40 @@ impl And for Primitive {
             Primitive::Bool(l) => match n {
                 Primitive::Bool(r) => Primitive::Bool(*l && r),
                 Primitive::Int(_) => Primitive::Error("'and' on an int value"),
+                Primitive::BigInt(_) | Primitive::Rational(_) => {
+                    Primitive::Error("'and' on a number value")
+                }
                 Primitive::Double(_) => {
                     Primitive::Error("'and'on a double value")
                 }
                 Primitive::Array(_) => {
                     Primitive::Error("'and' on an array value")
                 }
+                Primitive::Map(_) => Primitive::Error("'and' on a map value"),
+                Primitive::Struct { .. } | Primitive::StructDef { .. } => {
+                    Primitive::Error("'and' on a struct value")
+                }
                 Primitive::String(_) => {
                     Primitive::Error("'and'on a string value")
                 }
                 Primitive::Unit => Primitive::Error("'and' on an unit value"),
+                Primitive::Null => Primitive::Error("'and' on a null value"),
                 Primitive::Error(e) => panic!("'and' on an error. {e}"),
             },
             Primitive::Int(_) => Primitive::Error("'and' on an int value"),
+            Primitive::BigInt(_) | Primitive::Rational(_) => {
+                Primitive::Error("'and' on a number value")
+            }
             Primitive::String(_) => {
                 Primitive::Error("'and' on an string value")
             }
             Primitive::Array(_) => Primitive::Error("'and' on an array value"),
+            Primitive::Map(_) => Primitive::Error("'and' on a map value"),
+            Primitive::Struct { .. } | Primitive::StructDef { .. } => {
+                Primitive::Error("'and' on a struct value")
+            }
             Primitive::Unit => Primitive::Error("'and' on an unit value"),
+            Primitive::Null => Primitive::Error("'and' on a null value"),
             Primitive::Double(_) => Primitive::Error("'and'on a double value"),
             Primitive::Error(e) => panic!("'and' on an error. {e}"),
         }
@@ -742,6 +1200,8 @@ impl PartialOrd for Primitive {
 
             (Primitive::String(l), Primitive::String(r)) => l.partial_cmp(r),
             (Primitive::Unit, Primitive::Unit) => Some(Ordering::Equal),
+            (Primitive::Null, Primitive::Null) => Some(Ordering::Equal),
+            (Primitive::Null, _) | (_, Primitive::Null) => None,
             (Primitive::Array(l), Primitive::Array(r)) => l.partial_cmp(r),
 
             (Primitive::String(_), Primitive::Error(_)) => None,
@@ -787,19 +1247,105 @@ impl PartialOrd for Primitive {
             (Primitive::Array(_), Primitive::Unit) => None,
             (Primitive::Error(_), Primitive::Array(_)) => None,
             (Primitive::Unit, Primitive::Array(_)) => None,
+
+            (Primitive::Map(l), Primitive::Map(r)) => l.partial_cmp(r),
+            (Primitive::Map(_), _) => None,
+            (_, Primitive::Map(_)) => None,
+
+            (
+                Primitive::Struct { name: l_name, fields: l },
+                Primitive::Struct { name: r_name, fields: r },
+            ) if l_name == r_name => l.partial_cmp(r),
+            (Primitive::Struct { .. } | Primitive::StructDef { .. }, _)
+            | (_, Primitive::Struct { .. } | Primitive::StructDef { .. }) => {
+                None
+            }
+
+            (Primitive::BigInt(_) | Primitive::Rational(_), _)
+            | (_, Primitive::BigInt(_) | Primitive::Rational(_)) => {
+                big_cmp(self, other)
+            }
         }
     }
 }
 
+impl BitAnd for Primitive {
+    type Output = Primitive;
+
+    fn bitand(self, rhs: Self) -> Self::Output {
+        bit_op(BitOp::And, &self, &rhs)
+    }
+}
+
+impl BitOr for Primitive {
+    type Output = Primitive;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        bit_op(BitOp::Or, &self, &rhs)
+    }
+}
+
+impl BitXor for Primitive {
+    type Output = Primitive;
+
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        bit_op(BitOp::Xor, &self, &rhs)
+    }
+}
+
+impl Shl for Primitive {
+    type Output = Primitive;
+
+    fn shl(self, rhs: Self) -> Self::Output {
+        bit_op(BitOp::Shl, &self, &rhs)
+    }
+}
+
+impl Shr for Primitive {
+    type Output = Primitive;
+
+    fn shr(self, rhs: Self) -> Self::Output {
+        bit_op(BitOp::Shr, &self, &rhs)
+    }
+}
+
+impl BitNot for Primitive {
+    fn bit_not(&self) -> Self {
+        bit_not(self)
+    }
+}
+
+/// negative index starts from the end, e.g -1 is the last element
+pub(super) fn normalize_index(idx: i128, len: usize) -> Option<usize> {
+    let idx = if idx < 0 { idx + len as i128 } else { idx };
+    (0..len as i128).contains(&idx).then_some(idx as usize)
+}
+
+/// bounds of a slice, clamped to the length
+fn slice_bounds(
+    start: &Primitive,
+    end: &Primitive,
+    len: usize,
+) -> Option<(usize, usize)> {
+    let bound = |p: &Primitive, default: usize| match p {
+        Primitive::Unit => Some(default),
+        Primitive::Int(i) => {
+            let i = if *i < 0 { *i + len as i128 } else { *i };
+            Some(i.clamp(0, len as i128) as usize)
+        }
+        _ => None,
+    };
+    let (start, end) = (bound(start, 0)?, bound(end, len)?);
+    Some((start, end.max(start)))
+}
+
 impl Array for Primitive {
     fn index_at(&self, rhs: Primitive) -> Primitive {
         match (self, rhs) {
             (Primitive::Array(arr), Primitive::Int(idx)) => {
-                let idx = idx as usize;
-                if idx < arr.len() {
-                    arr[idx].clone()
-                } else {
-                    Primitive::Error("index out of range")
+                match normalize_index(idx, arr.len()) {
+                    Some(idx) => arr[idx].clone(),
+                    None => Primitive::Error("index out of range"),
                 }
             }
             (Primitive::String(s), Primitive::Int(idx)) => {
@@ -810,14 +1356,48 @@ impl Array for Primitive {
                     Primitive::Error("index out of range")
                 }
             }
+            (Primitive::Map(map), Primitive::String(key)) => map
+                .get(&key)
+                .cloned()
+                .unwrap_or(Primitive::Error("key not found")),
+            (Primitive::Struct { fields, .. }, Primitive::String(field)) => {
+                fields
+                    .get(&field)
+                    .cloned()
+                    .unwrap_or(Primitive::Error("field not found in struct"))
+            }
             _ => Primitive::Error("illegal access to array!!!"),
         }
     }
 
+    fn slice(&self, start: &Primitive, end: &Primitive) -> Primitive {
+        match self {
+            Primitive::Array(arr) => {
+                match slice_bounds(start, end, arr.len()) {
+                    Some((start, end)) => {
+                        Primitive::Array(arr[start..end].to_vec())
+                    }
+                    None => Primitive::Error("invalid slice"),
+                }
+            }
+            Primitive::String(s) => {
+                let chars = s.chars().collect::<Vec<_>>();
+                match slice_bounds(start, end, chars.len()) {
+                    Some((start, end)) => Primitive::String(
+                        chars[start..end].iter().collect::<String>(),
+                    ),
+                    None => Primitive::Error("invalid slice"),
+                }
+            }
+            _ => Primitive::Error("call to slice() on a non array value"),
+        }
+    }
+
     fn len(&self) -> Primitive {
         match self {
             Primitive::String(s) => Primitive::Int(s.len() as i128),
             Primitive::Array(a) => Primitive::Int(a.len() as i128),
+            Primitive::Map(m) => Primitive::Int(m.len() as i128),
             _ => Primitive::Error("call to len() on a non array value"),
         }
     }
@@ -829,14 +1409,42 @@ impl Array for Primitive {
     ) -> Primitive {
         match (self, index) {
             (Primitive::Array(arr), Primitive::Int(idx)) => {
-                let idx = *idx as usize;
-                if !matches!(rhs, Primitive::Error(_) | Primitive::Unit)
-                    && idx < arr.len()
-                {
-                    std::mem::swap(&mut arr[idx], rhs);
-                    arr[idx].clone()
+                match normalize_index(*idx, arr.len()) {
+                    Some(idx)
+                        if !matches!(
+                            rhs,
+                            Primitive::Error(_) | Primitive::Unit
+                        ) =>
+                    {
+                        std::mem::swap(&mut arr[idx], rhs);
+                        arr[idx].clone()
+                    }
+                    _ => Primitive::Error("index out of range"),
+                }
+            }
+            (Primitive::Map(map), Primitive::String(key)) => {
+                if !matches!(rhs, Primitive::Error(_) | Primitive::Unit) {
+                    map.insert(key.clone(), rhs.clone());
+                    rhs.clone()
                 } else {
-                    Primitive::Error("index out of range")
+                    Primitive::Error("invalid value for map")
+                }
+            }
+            (Primitive::Struct { fields, .. }, Primitive::String(field)) => {
+                match fields.get_mut(field) {
+                    Some(_)
+                        if matches!(
+                            rhs,
+                            Primitive::Error(_) | Primitive::Unit
+                        ) =>
+                    {
+                        Primitive::Error("invalid value for struct")
+                    }
+                    Some(value) => {
+                        *value = rhs.clone();
+                        rhs.clone()
+                    }
+                    None => Primitive::Error("field not found in struct"),
                 }
             }
             _ => Primitive::Error("invalid call to swap_mem()"),
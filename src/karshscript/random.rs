@@ -0,0 +1,30 @@
+use std::cell::RefCell;
+
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+thread_local! {
+    static RNG: RefCell<StdRng> = RefCell::new(StdRng::from_entropy());
+}
+
+/// makes the random built-ins reproducible e.g in a test file
+pub(super) fn seed(seed: u64) {
+    RNG.with(|rng| *rng.borrow_mut() = StdRng::seed_from_u64(seed));
+}
+
+/// between 0 (inclusive) and 1 (exclusive)
+pub(super) fn random() -> f64 {
+    RNG.with(|rng| rng.borrow_mut().gen())
+}
+
+/// between min and max, both inclusive
+pub(super) fn random_int(min: i128, max: i128) -> i128 {
+    RNG.with(|rng| rng.borrow_mut().gen_range(min..=max))
+}
+
+pub(super) fn shuffle<T>(values: &mut [T]) {
+    RNG.with(|rng| values.shuffle(&mut *rng.borrow_mut()));
+}
+
+pub(super) fn choice<T>(values: &[T]) -> Option<&T> {
+    RNG.with(|rng| values.choose(&mut *rng.borrow_mut()))
+}
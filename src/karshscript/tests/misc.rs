@@ -85,7 +85,8 @@ fn test_variable_expr() {
                 Value::Variable("y".to_string(),),
                 Value::Operation(Div,),
                 Value::Integer(8,),
-            ]))
+            ])),
+            operator: None,
         },]
     );
 }
@@ -107,7 +108,8 @@ fn test_variable_expr_2() {
                 Value::Variable("y_1_2".to_string(),),
                 Value::Operation(Div,),
                 Value::Integer(8,),
-            ]))
+            ])),
+            operator: None,
         },]
     );
 }
@@ -515,3 +517,654 @@ fn test_str() {
         compute(r#""a"+5.1"#, &mut ctx).unwrap()
     );
 }
+
+#[test]
+fn test_map() {
+    let mut ctx = BTreeMap::new();
+    assert_eq!(
+        Primitive::Int(3),
+        compute(r#"m = {"a": 1 + 2, "b": [1, 2], "c": {"d": "e"}}"#, &mut ctx)
+            .and_then(|_| compute(r#"m["a"]"#, &mut ctx))
+            .unwrap()
+    );
+    assert_eq!(Primitive::Int(5), compute(r#"m["f"] = 5"#, &mut ctx).unwrap());
+    assert_eq!(Primitive::Int(4), compute(r#"length(m)"#, &mut ctx).unwrap());
+    assert_eq!(
+        r#"{"a": 3, "b": [1, 2], "c": {"d": "e"}, "f": 5}"#,
+        compute("m", &mut ctx).unwrap().to_string()
+    );
+}
+
+#[test]
+fn test_exec() {
+    let mut ctx = BTreeMap::new();
+    compute(r#"r = exec("echo hello")"#, &mut ctx).unwrap();
+    assert_eq!(
+        Primitive::String("hello\n".to_string()),
+        compute(r#"r["stdout"]"#, &mut ctx).unwrap()
+    );
+    compute(
+        r#"r = sh("cat - | wc -c && exit 2", {"stdin": "abc", "cwd": "/tmp"})"#,
+        &mut ctx,
+    )
+    .unwrap();
+    assert_eq!(
+        Primitive::String("3\n".to_string()),
+        compute(r#"r["stdout"]"#, &mut ctx).unwrap()
+    );
+    assert_eq!(Primitive::Int(2), compute(r#"r["code"]"#, &mut ctx).unwrap());
+}
+
+#[test]
+fn test_assert() {
+    let mut ctx = BTreeMap::new();
+    assert_eq!(
+        Primitive::Unit,
+        compute(r#"assert(1 < 2, "math is broken")"#, &mut ctx).unwrap()
+    );
+    assert_eq!(
+        Primitive::Unit,
+        compute(r#"assert_eq([1, 2], [1, 2])"#, &mut ctx).unwrap()
+    );
+    let err = compute(r#"assert(1 > 2, "math is broken")"#, &mut ctx)
+        .unwrap_err()
+        .to_string();
+    assert_eq!("assertion failed: math is broken", err);
+    let err =
+        compute(r#"assert_eq(1 + 1, 3)"#, &mut ctx).unwrap_err().to_string();
+    assert!(err.contains("left: 2") && err.contains("right: 3"));
+    assert!(compute(r#"assert(5)"#, &mut ctx).is_err());
+    assert!(compute(r#"assert(1 < "a", "type mismatch")"#, &mut ctx).is_err());
+    assert!(compute(r#"assert_eq(1)"#, &mut ctx).is_err());
+}
+
+#[test]
+fn test_limits() {
+    use crate::karshscript::{set_limits, Limits};
+    let mut ctx = BTreeMap::new();
+    let mut limits = Limits::default();
+    limits.set("loop", 10).unwrap();
+    set_limits(limits);
+    compute("x = 0", &mut ctx).unwrap();
+    let err = compute("while(true) {\n x = x + 1\n}", &mut ctx).unwrap_err();
+    assert_eq!("max loop iterations of 10 reached", err.to_string());
+    assert_eq!(Some(&Primitive::Int(10)), ctx.get("x"));
+
+    limits.set("loop", 0).unwrap();
+    limits.set("time", 50).unwrap();
+    set_limits(limits);
+    let err = compute("while(true) {\n x = x + 1\n}", &mut ctx).unwrap_err();
+    assert_eq!("script took longer than 50ms", err.to_string());
+
+    limits.set("array", 3).unwrap();
+    set_limits(limits);
+    compute("arr = [1, 2, 3]", &mut ctx).unwrap();
+    let too_long = Primitive::Error("max array length reached");
+    for script in [
+        "arr + [4]",
+        "[1, 2, 3, 4]",
+        "[0] * 1000000000000",
+        "concat(arr, [4])",
+        "push(arr, 4)",
+        "insert(arr, 0, 4)",
+        "flatten([arr, [4]])",
+        r#"{"a": [0] * 4}"#,
+    ] {
+        assert_eq!(too_long, compute(script, &mut ctx).unwrap(), "{script}");
+    }
+    assert_eq!(Primitive::Int(3), compute("length(arr)", &mut ctx).unwrap());
+    assert!(limits.set("unknown", 3).is_err());
+    set_limits(Limits::default());
+    assert_eq!(None, Limits::default().loop_iterations);
+}
+
+#[test]
+fn test_big_int() {
+    let mut ctx = BTreeMap::new();
+    let max = i128::MAX;
+    assert_eq!(
+        "170141183460469231731687303715884105728",
+        compute(&format!("{max} + 1"), &mut ctx).unwrap().to_string()
+    );
+    assert_eq!(
+        "1267650600228229401496703205376",
+        compute("2^100", &mut ctx).unwrap().to_string()
+    );
+    compute("x = 2^200", &mut ctx).unwrap();
+    assert_eq!(Primitive::Int(1), compute("x / 2^199 - 1", &mut ctx).unwrap());
+    assert_eq!(
+        Primitive::Bool(true),
+        compute(
+            "x == 1606938044258990275541962092341162602522202993782792835301376",
+            &mut ctx
+        )
+        .unwrap()
+    );
+    assert_eq!(Primitive::Bool(true), compute("x > 2^199", &mut ctx).unwrap());
+    assert_eq!(
+        Primitive::Int(i128::MIN),
+        compute(&format!("-{max} - 1"), &mut ctx).unwrap()
+    );
+    assert!(matches!(
+        compute("2^99999999", &mut ctx).unwrap(),
+        Primitive::Error(_)
+    ));
+}
+
+#[test]
+fn test_rational_mode() {
+    use crate::karshscript::{set_number_mode, set_precision, NumberMode};
+    let mut ctx = BTreeMap::new();
+    assert_eq!(Primitive::Int(0), compute("1/3", &mut ctx).unwrap());
+    set_number_mode(NumberMode::Rational);
+    assert_eq!("0.3", compute("0.1 + 0.2", &mut ctx).unwrap().to_string());
+    assert_eq!(
+        Primitive::Bool(true),
+        compute("0.1 + 0.2 == 0.3", &mut ctx).unwrap()
+    );
+    assert_eq!(
+        "0.3333333333",
+        compute("x = 1/3", &mut ctx).unwrap().to_string()
+    );
+    assert_eq!(Primitive::Int(1), compute("x * 3", &mut ctx).unwrap());
+    assert_eq!("0.25", compute("2^-2", &mut ctx).unwrap().to_string());
+    set_precision(3);
+    assert_eq!("-0.667", compute("-2/3", &mut ctx).unwrap().to_string());
+    assert_eq!(
+        Primitive::Double(0.5),
+        compute("x * sqrt(2.25)", &mut ctx).unwrap()
+    );
+    set_precision(10);
+    set_number_mode(NumberMode::Float);
+}
+
+#[test]
+fn test_bitwise() {
+    let mut ctx = BTreeMap::new();
+    assert_eq!(Primitive::Int(255), compute("0xFF", &mut ctx).unwrap());
+    assert_eq!(Primitive::Int(10), compute("0b1010", &mut ctx).unwrap());
+    assert_eq!(Primitive::Int(493), compute("0o755", &mut ctx).unwrap());
+    assert_eq!(
+        Primitive::Int(1_000_000),
+        compute("1_000_000", &mut ctx).unwrap()
+    );
+    assert_eq!(
+        Primitive::Int(0b1111_0000),
+        compute("0b1111_0000", &mut ctx).unwrap()
+    );
+    assert_eq!(
+        Primitive::Double(1500.5),
+        compute("1_500.5", &mut ctx).unwrap()
+    );
+    assert_eq!(Primitive::Double(1.5e3), compute("1.5e3", &mut ctx).unwrap());
+    assert_eq!(Primitive::Double(2.5e-3), compute("2.5E-3", &mut ctx).unwrap());
+    assert!(compute("1e400", &mut ctx).is_err());
+
+    compute("flags = 0o644", &mut ctx).unwrap();
+    assert_eq!(Primitive::Int(4), compute("flags & 0o4", &mut ctx).unwrap());
+    assert_eq!(
+        Primitive::Int(0o755),
+        compute("flags | 0o111", &mut ctx).unwrap()
+    );
+    assert_eq!(Primitive::Int(6), compute("5 xor 3", &mut ctx).unwrap());
+    compute("xor_flags = 3", &mut ctx).unwrap();
+    assert_eq!(
+        Primitive::Int(0),
+        compute("xor_flags xor 3", &mut ctx).unwrap()
+    );
+    assert_eq!(Primitive::Int(16), compute("1 << 4", &mut ctx).unwrap());
+    assert_eq!(Primitive::Int(2), compute("32 >> 4", &mut ctx).unwrap());
+    assert_eq!(Primitive::Int(-6), compute("~5", &mut ctx).unwrap());
+    assert_eq!(Primitive::Int(5), compute("1 + 1 << 1 | 1", &mut ctx).unwrap());
+    assert_eq!(
+        Primitive::Bool(true),
+        compute("1 << 2 == 4", &mut ctx).unwrap()
+    );
+    assert_eq!(
+        "340282366920938463463374607431768211456",
+        compute("1 << 128", &mut ctx).unwrap().to_string()
+    );
+    assert_eq!(
+        Primitive::Bool(false),
+        compute("true & false", &mut ctx).unwrap()
+    );
+    assert!(matches!(
+        compute("1.5 & 1", &mut ctx).unwrap(),
+        Primitive::Error(_)
+    ));
+
+    assert_eq!(
+        Primitive::String("0x1a4".to_string()),
+        compute("hex(flags)", &mut ctx).unwrap()
+    );
+    assert_eq!(
+        Primitive::String("0b101".to_string()),
+        compute("bin(5)", &mut ctx).unwrap()
+    );
+    assert_eq!(
+        Primitive::String("0o644".to_string()),
+        compute("oct(flags)", &mut ctx).unwrap()
+    );
+    assert_eq!(
+        Primitive::String("-0xff".to_string()),
+        compute("hex(-255)", &mut ctx).unwrap()
+    );
+}
+
+#[test]
+fn test_if_expression() {
+    let mut ctx = BTreeMap::new();
+    compute("x = 5", &mut ctx).unwrap();
+    assert_eq!(
+        Primitive::String("big".to_string()),
+        compute(r#"x > 3 ? "big" : "small""#, &mut ctx).unwrap()
+    );
+    assert_eq!(
+        Primitive::Int(2),
+        compute("y = x < 3 ? 1 : x < 10 ? 2 : 3", &mut ctx).unwrap()
+    );
+    assert_eq!(Some(&Primitive::Int(2)), ctx.get("y"));
+    assert_eq!(
+        Primitive::Int(12),
+        compute("(x == 5 ? 10 : 20) + 2", &mut ctx).unwrap()
+    );
+    assert_eq!(
+        Primitive::Int(6),
+        compute("z = if (x > 3) { x + 1 } else { x - 1 }", &mut ctx).unwrap()
+    );
+    assert_eq!(
+        Primitive::Int(4),
+        compute(
+            r#"z = if (x > 10) {
+                    x + 1
+                } else {
+                    y = 1
+                    x - y
+                }"#,
+            &mut ctx
+        )
+        .unwrap()
+    );
+    assert_eq!(Some(&Primitive::Int(4)), ctx.get("z"));
+    // no branch taken
+    assert_eq!(
+        Primitive::Unit,
+        compute("if (x > 10) { x + 1 }", &mut ctx).unwrap()
+    );
+    assert!(compute("x > 3 ? 1", &mut ctx).is_err());
+}
+
+#[test]
+fn test_match_expression() {
+    let mut ctx = BTreeMap::new();
+    compute(r#"x = "b""#, &mut ctx).unwrap();
+    assert_eq!(
+        Primitive::Int(2),
+        compute(r#"match x { 1 => 1, "a" | "b" => 2, _ => 3 }"#, &mut ctx)
+            .unwrap()
+    );
+    assert_eq!(
+        Primitive::String("minus one".to_string()),
+        compute(
+            r#"r = match 2 - 3 {
+                0 => "zero"
+                -1 => "minus one" # comment
+                _ => {
+                    println("other")
+                    "other"
+                }
+            }"#,
+            &mut ctx
+        )
+        .unwrap()
+    );
+    assert_eq!(Some(&Primitive::String("minus one".to_string())), ctx.get("r"));
+    assert_eq!(
+        Primitive::Unit,
+        compute("match 4 { 1 => 1, true => 2 }", &mut ctx).unwrap()
+    );
+    assert_eq!(
+        Primitive::Int(42),
+        compute("match 4 { _ => { 42 } }", &mut ctx).unwrap()
+    );
+}
+
+#[test]
+fn test_null() {
+    let mut ctx = BTreeMap::new();
+    assert_eq!(Primitive::Null, compute("x = null", &mut ctx).unwrap());
+    assert_eq!("null", Primitive::Null.to_string());
+    assert_eq!(Primitive::Bool(true), compute("x == null", &mut ctx).unwrap());
+    assert_eq!(Primitive::Bool(false), compute("1 == null", &mut ctx).unwrap());
+    assert_eq!(Primitive::Bool(true), compute("1 != null", &mut ctx).unwrap());
+    assert_eq!(Primitive::Int(22), compute("x ?? 22", &mut ctx).unwrap());
+    assert_eq!(Primitive::Int(22), compute("port ?? 22", &mut ctx).unwrap());
+    assert_eq!(
+        Primitive::Int(3),
+        compute("port ?? x ?? 1 + 2", &mut ctx).unwrap()
+    );
+    assert_eq!(Primitive::Int(1), compute("y = 1 ?? 2", &mut ctx).unwrap());
+    assert_eq!(
+        Primitive::Error("call to add() on a null value"),
+        compute("x + 1", &mut ctx).unwrap()
+    );
+
+    assert_eq!(Primitive::Bool(true), compute("defined(y)", &mut ctx).unwrap());
+    assert_eq!(
+        Primitive::Bool(false),
+        compute("defined(x)", &mut ctx).unwrap()
+    );
+    assert_eq!(
+        Primitive::Bool(false),
+        compute("defined(missing)", &mut ctx).unwrap()
+    );
+}
+
+#[test]
+fn test_safe_access() {
+    let mut ctx = BTreeMap::new();
+    compute(r#"config = {"server": {"port": 8080}, "hosts": ["a"]}"#, &mut ctx)
+        .unwrap();
+    assert_eq!(
+        Primitive::Int(8080),
+        compute("config?.server?.port", &mut ctx).unwrap()
+    );
+    assert_eq!(
+        Primitive::Null,
+        compute("config?.client?.port", &mut ctx).unwrap()
+    );
+    assert_eq!(
+        Primitive::Int(22),
+        compute(r#"config?["client"]?["port"] ?? 22"#, &mut ctx).unwrap()
+    );
+    assert_eq!(
+        Primitive::Null,
+        compute(r#"config["hosts"]?[3]"#, &mut ctx).unwrap()
+    );
+    assert_eq!(
+        Primitive::String("a".to_string()),
+        compute(r#"config?.hosts?[0]"#, &mut ctx).unwrap()
+    );
+    assert_eq!(Primitive::Null, compute("other?.port", &mut ctx).unwrap());
+    assert_eq!(
+        Primitive::Bool(false),
+        compute("defined(config?.client)", &mut ctx).unwrap()
+    );
+    assert_eq!(
+        Primitive::Error("index out of range"),
+        compute(r#"config["hosts"][3]"#, &mut ctx).unwrap()
+    );
+    assert!(compute(r#"config?.port = 1"#, &mut ctx).is_err());
+    // a space before '?' is a ternary
+    compute("ok = true", &mut ctx).unwrap();
+    assert_eq!(
+        Primitive::Array(vec![Primitive::Int(1)]),
+        compute("ok ? [1] : [2]", &mut ctx).unwrap()
+    );
+}
+
+#[test]
+fn test_date_fns() {
+    let mut ctx = BTreeMap::new();
+    assert_eq!(Primitive::Int(5_400_000), compute("1h30m", &mut ctx).unwrap());
+    assert_eq!(Primitive::Int(500), compute("500ms", &mut ctx).unwrap());
+    assert_eq!(
+        Primitive::Int(2 * 86_400_000 + 1000),
+        compute("2d + 1s", &mut ctx).unwrap()
+    );
+    assert_eq!(
+        Primitive::String("1h 30m".to_string()),
+        compute("format_duration(1h + 30m)", &mut ctx).unwrap()
+    );
+
+    compute("start = now()", &mut ctx).unwrap();
+    assert_eq!(Primitive::Unit, compute("sleep(20ms)", &mut ctx).unwrap());
+    assert_eq!(
+        Primitive::Bool(true),
+        compute("now() - start >= 20ms", &mut ctx).unwrap()
+    );
+    assert_eq!(
+        Primitive::Bool(true),
+        compute("now() / 1000 - timestamp() <= 1", &mut ctx).unwrap()
+    );
+
+    assert_eq!(
+        Primitive::Int(1_704_067_200_000),
+        compute(r#"ts = parse_date("2024-01-01T00:00:00Z")"#, &mut ctx)
+            .unwrap()
+    );
+    assert_eq!(
+        Primitive::String("2024-03-01 10:20:30".to_string()),
+        compute(r#"format_date(parse_date("2024-03-01 10:20:30"))"#, &mut ctx)
+            .unwrap()
+    );
+    assert_eq!(
+        Primitive::String("02/03/2024".to_string()),
+        compute(
+            r#"format_date(parse_date("01/03/2024", "%d/%m/%Y") + 1d, "%d/%m/%Y")"#,
+            &mut ctx
+        )
+        .unwrap()
+    );
+    assert_eq!(
+        Primitive::Null,
+        compute(r#"parse_date("yesterday")"#, &mut ctx).unwrap()
+    );
+    assert_eq!(
+        Primitive::Error("invalid date or date format"),
+        compute(r#"format_date(ts, "%Q")"#, &mut ctx).unwrap()
+    );
+}
+
+#[test]
+fn test_random_fns() {
+    let mut ctx = BTreeMap::new();
+    compute("seed(42)", &mut ctx).unwrap();
+    let first = compute(
+        "[random(), random_int(1, 6), shuffle([1, 2, 3, 4])]",
+        &mut ctx,
+    )
+    .unwrap();
+    compute("seed(42)", &mut ctx).unwrap();
+    let second = compute(
+        "[random(), random_int(1, 6), shuffle([1, 2, 3, 4])]",
+        &mut ctx,
+    )
+    .unwrap();
+    assert_eq!(first, second);
+
+    compute("r = random()", &mut ctx).unwrap();
+    assert_eq!(
+        Primitive::Bool(true),
+        compute("r >= 0 && r < 1", &mut ctx).unwrap()
+    );
+    compute("i = random_int(-2, 2)", &mut ctx).unwrap();
+    assert_eq!(
+        Primitive::Bool(true),
+        compute("i >= -2 && i <= 2", &mut ctx).unwrap()
+    );
+    assert_eq!(
+        Primitive::Int(10),
+        compute("sum(shuffle([1, 2, 3, 4]))", &mut ctx).unwrap()
+    );
+    assert_eq!(
+        Primitive::Bool(true),
+        compute("contains([1, 2, 3], choice([1, 2, 3]))", &mut ctx).unwrap()
+    );
+    assert_eq!(Primitive::Null, compute("choice([])", &mut ctx).unwrap());
+    assert_eq!(
+        Primitive::Error("invalid arguments. e.g random_int(1, 6)"),
+        compute("random_int(6, 1)", &mut ctx).unwrap()
+    );
+}
+
+#[test]
+fn test_regex() {
+    let mut ctx = BTreeMap::new();
+    compute(r#"line = "web-1 up 10.0.0.12 port 8080""#, &mut ctx).unwrap();
+    assert_eq!(
+        Primitive::Bool(true),
+        compute(r#"matches(line, "^web-\d+ up")"#, &mut ctx).unwrap()
+    );
+    assert_eq!(
+        Primitive::Bool(false),
+        compute(r#"line =~ "down""#, &mut ctx).unwrap()
+    );
+    assert_eq!(
+        Primitive::String("up".to_string()),
+        compute(r#"line =~ "up" ? "up" : "down""#, &mut ctx).unwrap()
+    );
+    assert_eq!(
+        Primitive::Int(1),
+        compute(
+            r#"if (line =~ "\d+\.\d+\.\d+\.\d+") { 1 } else { 2 }"#,
+            &mut ctx
+        )
+        .unwrap()
+    );
+    assert_eq!(
+        Primitive::Array(vec![
+            Primitive::String("1".to_string()),
+            Primitive::String("8080".to_string()),
+        ]),
+        compute(r#"find_all("web-1 port 8080", "\d+")"#, &mut ctx).unwrap()
+    );
+    compute(r#"c = captures(line, "(?P<ip>[\d.]+) port (\d+)")"#, &mut ctx)
+        .unwrap();
+    assert_eq!(
+        Primitive::String("10.0.0.12".to_string()),
+        compute(r#"c["ip"]"#, &mut ctx).unwrap()
+    );
+    assert_eq!(
+        Primitive::String("8080".to_string()),
+        compute(r#"c["2"]"#, &mut ctx).unwrap()
+    );
+    assert_eq!(
+        Primitive::Null,
+        compute(r#"captures(line, "nope")"#, &mut ctx).unwrap()
+    );
+    assert_eq!(
+        Primitive::String("port=8080".to_string()),
+        compute(
+            r#"replace_re("port: 8080", "(\w+): (\d+)", "$1=$2")"#,
+            &mut ctx
+        )
+        .unwrap()
+    );
+    assert_eq!(
+        Primitive::Error("invalid regular expression"),
+        compute(r#"matches(line, "(")"#, &mut ctx).unwrap()
+    );
+    // = is still an assignment
+    assert_eq!(Primitive::Int(-3), compute("x = ~2", &mut ctx).unwrap());
+}
+
+#[test]
+fn test_env() {
+    let mut ctx = BTreeMap::new();
+    assert_eq!(
+        Primitive::String(std::env::var("PATH").unwrap()),
+        compute(r#"env("PATH")"#, &mut ctx).unwrap()
+    );
+    assert_eq!(
+        Primitive::Null,
+        compute(r#"env("KARSHER_UNDEFINED_VAR") ?? null"#, &mut ctx).unwrap()
+    );
+    compute(r#"set_env("KARSHER_PROFILE", "dev")"#, &mut ctx).unwrap();
+    assert_eq!(
+        Primitive::String("dev".to_string()),
+        compute(r#"env("KARSHER_PROFILE")"#, &mut ctx).unwrap()
+    );
+    compute(r#"all = env_all()"#, &mut ctx).unwrap();
+    assert_eq!(
+        Primitive::String("dev".to_string()),
+        compute(r#"all["KARSHER_PROFILE"]"#, &mut ctx).unwrap()
+    );
+    compute(r#"out = sh("echo $KARSHER_PROFILE")"#, &mut ctx).unwrap();
+    assert_eq!(
+        Primitive::String("dev\n".to_string()),
+        compute(r#"out["stdout"]"#, &mut ctx).unwrap()
+    );
+    assert_eq!(
+        Primitive::Error(
+            r#"invalid arguments. e.g set_env("AWS_PROFILE", "dev")"#
+        ),
+        compute(r#"set_env("A=B", "dev")"#, &mut ctx).unwrap()
+    );
+}
+
+#[test]
+fn test_struct() {
+    let mut ctx = BTreeMap::new();
+    compute(
+        r#"
+        struct Server {
+            host,
+            port,
+        }
+        s = Server { host: "a", port: 20 + 2 }
+        "#,
+        &mut ctx,
+    )
+    .unwrap();
+    assert_eq!(
+        Some(&Primitive::StructDef {
+            name: "Server".to_string(),
+            fields: vec!["host".to_string(), "port".to_string()],
+        }),
+        ctx.get("Server")
+    );
+    assert_eq!(
+        "Server { host: \"a\", port: 22 }",
+        ctx.get("s").unwrap().to_string()
+    );
+    assert_eq!(
+        "struct Server { host, port }",
+        ctx.get("Server").unwrap().to_string()
+    );
+    assert_eq!(
+        Primitive::String("a".to_string()),
+        compute("s.host", &mut ctx).unwrap()
+    );
+    compute("s.port = 2222", &mut ctx).unwrap();
+    assert_eq!(Primitive::Int(2223), compute("s.port + 1", &mut ctx).unwrap());
+    assert_eq!(
+        Primitive::Error("field not found in struct"),
+        compute("s.user = 1", &mut ctx).unwrap()
+    );
+    assert_eq!(Primitive::Null, compute("s?.user", &mut ctx).unwrap());
+
+    // missing fields are null
+    compute(r#"servers = [Server { host: "b" }, s]"#, &mut ctx).unwrap();
+    assert_eq!(
+        Primitive::Int(22),
+        compute("servers[0].port ?? 22", &mut ctx).unwrap()
+    );
+    assert_eq!(
+        Primitive::String("a".to_string()),
+        compute("servers[1].host", &mut ctx).unwrap()
+    );
+    assert_eq!(
+        Primitive::Bool(true),
+        compute(r#"s == Server { port: 2222, host: "a" }"#, &mut ctx).unwrap()
+    );
+
+    assert!(compute(r#"Server { user: "root" }"#, &mut ctx).is_err());
+    assert!(compute(r#"Client { host: "a" }"#, &mut ctx).is_err());
+    assert!(compute("struct Dup { a, a }", &mut ctx).is_err());
+
+    // persisted with the script context
+    let json = serde_json::to_string(&ctx).unwrap();
+    let restored: BTreeMap<String, Primitive> =
+        serde_json::from_str(&json).unwrap();
+    assert_eq!(ctx, restored);
+}
+
+#[test]
+fn test_last_status() {
+    let mut ctx = BTreeMap::new();
+    assert!(compute("$?", &mut ctx).is_err());
+    ctx.insert("$?".to_string(), Primitive::Int(2));
+    assert_eq!(Primitive::Bool(true), compute("$? != 0", &mut ctx).unwrap());
+    assert!(compute("$? = 0", &mut ctx).is_err());
+}
@@ -6,7 +6,7 @@ use crate::karshscript::{
     compute,
     primitive::{
         Array as Arr,
-        Primitive::{Array, Bool, Double, Int, String as Str},
+        Primitive::{self, Array, Bool, Double, Int, String as Str},
     },
 };
 
@@ -128,3 +128,194 @@ fn test_file_array() {
 
     assert_eq!(Str("bababa".to_string()), res)
 }
+
+#[test]
+fn test_index_assignment() {
+    let mut ctx = BTreeMap::new();
+    compute("m = [[1, 2], [3, 4]]", &mut ctx).unwrap();
+    compute("i = 1", &mut ctx).unwrap();
+    assert_eq!(Int(4), compute("m[i][i]", &mut ctx).unwrap());
+    assert_eq!(Int(3), compute("m[i][i - 1]", &mut ctx).unwrap());
+    assert_eq!(Int(9), compute("m[i][i - 1] = 9", &mut ctx).unwrap());
+    assert_eq!(Int(7), compute("m[0][i] = 7", &mut ctx).unwrap());
+    assert_eq!(
+        Some(&Array(vec![
+            Array(vec![Int(1), Int(7)]),
+            Array(vec![Int(9), Int(4)])
+        ])),
+        ctx.get("m")
+    );
+    assert_eq!(Int(2), compute("[1, 2, 3][i]", &mut ctx).unwrap());
+
+    compute(r#"servers = {"web": {"ports": [80, 443]}}"#, &mut ctx).unwrap();
+    compute(r#"servers["web"]["ports"][0] = 8080"#, &mut ctx).unwrap();
+    assert_eq!(
+        Int(8080),
+        compute(r#"servers["web"]["ports"][0]"#, &mut ctx).unwrap()
+    );
+    assert!(matches!(
+        compute("m[5][0] = 1", &mut ctx).unwrap(),
+        Primitive::Error(_)
+    ));
+}
+
+#[test]
+fn test_compound_assignment() {
+    let mut ctx = BTreeMap::new();
+    compute("x = 10", &mut ctx).unwrap();
+    assert_eq!(Int(15), compute("x += 5", &mut ctx).unwrap());
+    assert_eq!(Int(12), compute("x -= 1 + 2", &mut ctx).unwrap());
+    assert_eq!(Int(24), compute("x *= 2", &mut ctx).unwrap());
+    assert_eq!(Int(6), compute("x /= 2 * 2", &mut ctx).unwrap());
+    assert_eq!(Int(2), compute("x %= 4", &mut ctx).unwrap());
+    assert_eq!(Int(8), compute("x ^= 3", &mut ctx).unwrap());
+    assert_eq!(Some(&Int(8)), ctx.get("x"));
+
+    compute("arr = [1, [2, 3]]", &mut ctx).unwrap();
+    compute("i = 1", &mut ctx).unwrap();
+    assert_eq!(Int(5), compute("arr[i][0] += 3", &mut ctx).unwrap());
+    assert_eq!(
+        Some(&Array(vec![Int(1), Array(vec![Int(5), Int(3)])])),
+        ctx.get("arr")
+    );
+    compute(r#"s = "a""#, &mut ctx).unwrap();
+    assert_eq!(
+        Str("ab".to_string()),
+        compute(r#"s += "b""#, &mut ctx).unwrap()
+    );
+
+    // the index is evaluated once
+    compute("arr = [0, 0, 0]", &mut ctx).unwrap();
+    compute("idx = [0, 1, 2]", &mut ctx).unwrap();
+    assert_eq!(Int(10), compute("arr[pop(idx)] += 10", &mut ctx).unwrap());
+    assert_eq!(Some(&Array(vec![Int(0), Int(0), Int(10)])), ctx.get("arr"));
+    assert_eq!(Some(&Array(vec![Int(0), Int(1)])), ctx.get("idx"));
+    assert!(matches!(
+        compute("arr[5] += 1", &mut ctx).unwrap(),
+        Primitive::Error(_)
+    ));
+}
+
+#[test]
+fn test_slice_and_negative_index() {
+    let mut ctx = BTreeMap::new();
+    compute("arr = [1, 2, 3, 4, 5]", &mut ctx).unwrap();
+    assert_eq!(Int(5), compute("arr[-1]", &mut ctx).unwrap());
+    assert_eq!(
+        Array(vec![Int(2), Int(3)]),
+        compute("arr[1:3]", &mut ctx).unwrap()
+    );
+    assert_eq!(
+        Array(vec![Int(4), Int(5)]),
+        compute("arr[-2:]", &mut ctx).unwrap()
+    );
+    assert_eq!(
+        Array(vec![Int(1), Int(2)]),
+        compute("arr[:2]", &mut ctx).unwrap()
+    );
+    assert_eq!(Array(vec![]), compute("arr[3:1]", &mut ctx).unwrap());
+    assert_eq!(
+        Array(vec![Int(1), Int(2), Int(3), Int(4), Int(5)]),
+        compute("arr[:100]", &mut ctx).unwrap()
+    );
+    assert_eq!(Int(9), compute("arr[-1] = 9", &mut ctx).unwrap());
+    assert_eq!(
+        Primitive::Error("index out of range"),
+        compute("arr[-6]", &mut ctx).unwrap()
+    );
+    assert_eq!(
+        Str("ell".to_string()),
+        compute(
+            r#"s = "hello"
+            s[1:-1]"#,
+            &mut ctx
+        )
+        .unwrap()
+    );
+}
+
+#[test]
+fn test_array_mutation_fns() {
+    let mut ctx = BTreeMap::new();
+    compute("arr = [1, 2]", &mut ctx).unwrap();
+    assert_eq!(Int(3), compute("push(arr, 3)", &mut ctx).unwrap());
+    assert_eq!(Int(3), compute("pop(arr)", &mut ctx).unwrap());
+    assert_eq!(Int(3), compute("insert(arr, 0, 0)", &mut ctx).unwrap());
+    assert_eq!(Int(4), compute("insert(arr, -1, 9)", &mut ctx).unwrap());
+    assert_eq!(
+        Some(&Array(vec![Int(0), Int(1), Int(9), Int(2)])),
+        ctx.get("arr")
+    );
+    assert_eq!(Int(9), compute("remove_at(arr, 2)", &mut ctx).unwrap());
+    assert_eq!(Some(&Array(vec![Int(0), Int(1), Int(2)])), ctx.get("arr"));
+    assert_eq!(
+        Primitive::Error("index out of range"),
+        compute("remove_at(arr, 3)", &mut ctx).unwrap()
+    );
+    compute("e = []", &mut ctx).unwrap();
+    assert_eq!(
+        Primitive::Error("call to pop() on an empty array"),
+        compute("pop(e)", &mut ctx).unwrap()
+    );
+    assert!(compute("push([1], 2)", &mut ctx).is_err());
+}
+
+#[test]
+fn test_array_fns() {
+    let mut ctx = BTreeMap::new();
+    compute("arr = [3, 1, 2, 1]", &mut ctx).unwrap();
+    assert_eq!(
+        Array(vec![Int(3), Int(1), Int(2), Int(1), Int(4)]),
+        compute("concat(arr, [4])", &mut ctx).unwrap()
+    );
+    assert_eq!(
+        Array(vec![Int(1), Int(2), Int(1), Int(3)]),
+        compute("reverse(arr)", &mut ctx).unwrap()
+    );
+    assert_eq!(
+        Str("olleh".to_string()),
+        compute(r#"reverse("hello")"#, &mut ctx).unwrap()
+    );
+    assert_eq!(
+        Array(vec![Int(1), Int(1), Int(2), Int(3)]),
+        compute("sort(arr)", &mut ctx).unwrap()
+    );
+    assert_eq!(
+        Primitive::Error("cannot sort values of different types"),
+        compute(r#"sort([1, "a"])"#, &mut ctx).unwrap()
+    );
+    assert_eq!(
+        Array(vec![Int(3), Int(1), Int(2)]),
+        compute("unique(arr)", &mut ctx).unwrap()
+    );
+    assert_eq!(Bool(true), compute("contains(arr, 2)", &mut ctx).unwrap());
+    assert_eq!(Bool(false), compute("contains(arr, 5)", &mut ctx).unwrap());
+    assert_eq!(
+        Bool(true),
+        compute(r#"contains("hello", "ell")"#, &mut ctx).unwrap()
+    );
+    assert_eq!(Int(2), compute("index_of(arr, 2)", &mut ctx).unwrap());
+    assert_eq!(Int(-1), compute("index_of(arr, 5)", &mut ctx).unwrap());
+    assert_eq!(
+        Array(vec![
+            Array(vec![Int(3), Str("a".to_string())]),
+            Array(vec![Int(1), Str("b".to_string())])
+        ]),
+        compute(r#"zip(arr, ["a", "b"])"#, &mut ctx).unwrap()
+    );
+    assert_eq!(
+        Array(vec![Int(1), Int(2), Array(vec![Int(3)]), Int(4)]),
+        compute("flatten([[1, 2], [[3]], 4])", &mut ctx).unwrap()
+    );
+    assert_eq!(Int(7), compute("sum(arr)", &mut ctx).unwrap());
+    assert_eq!(Double(1.75), compute("avg(arr)", &mut ctx).unwrap());
+    assert_eq!(
+        Primitive::Error("invalid arguments. e.g avg([1, 2])"),
+        compute("avg([])", &mut ctx).unwrap()
+    );
+    // arr is left untouched
+    assert_eq!(
+        Some(&Array(vec![Int(3), Int(1), Int(2), Int(1)])),
+        ctx.get("arr")
+    );
+}
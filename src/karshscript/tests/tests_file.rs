@@ -205,3 +205,49 @@ fn test_if_else_file() {
     assert_eq!(Some(&Primitive::String("".to_string())), ctx.get("text"));
     assert_eq!(Some(&Primitive::Int(101)), ctx.get("count"));
 }
+
+#[test]
+#[serial]
+fn test_recursive_include() {
+    let mut ctx = BTreeMap::new();
+    let curr_dir = std::env::current_dir().unwrap();
+    let err = compute(
+        r#"include("file_tests/includes/recursive.karsher")"#,
+        &mut ctx,
+    )
+    .unwrap_err();
+    assert_eq!("max depth of 64 reached", err.to_string());
+    assert_eq!(curr_dir, std::env::current_dir().unwrap());
+}
+
+#[test]
+#[serial]
+fn test_fizzbuzz_match() {
+    let file_path = r#"
+    include("file_tests/test_fizzbuzz_match.karsher")
+"#;
+    let mut ctx = BTreeMap::new();
+    compute(file_path, &mut ctx).unwrap();
+    let fizz = |s: &str| Primitive::String(s.to_string());
+    assert_eq!(
+        Some(&Primitive::Array(vec![
+            Primitive::Int(1),
+            Primitive::Int(2),
+            fizz("Fizz"),
+            Primitive::Int(4),
+            fizz("Buzz"),
+            fizz("Fizz"),
+            Primitive::Int(7),
+            Primitive::Int(8),
+            fizz("Fizz"),
+            fizz("Buzz"),
+            Primitive::Int(11),
+            fizz("Fizz"),
+            Primitive::Int(13),
+            Primitive::Int(14),
+            fizz("FizzBuzz"),
+        ])),
+        ctx.get("result")
+    );
+    assert_eq!(Some(&fizz("odd")), ctx.get("last"));
+}
@@ -5,17 +5,23 @@ mod editor;
 mod karshscript;
 mod prelude;
 mod reserved_keywords;
+mod test_runner;
 
 use args::*;
 use db::DbOp;
-use karshscript::Primitive;
+use karshscript::{Host, Limits, NotAScript, NumberMode, Primitive};
 use rustyline::error::ReadlineError;
 use std::path::Path;
 
-use prelude::{colors::LightBlue, colors::Style, debug, warn, BTreeMap};
+use prelude::{
+    colors::LightBlue, colors::Red, colors::Style, debug, warn, BTreeMap,
+};
 
 use crate::{
-    cache_command::{clear_terminal, get_default_cache, process_command},
+    cache_command::{
+        clear_terminal, get_default_cache, load_script_context,
+        process_command, save_script_context, CacheHost,
+    },
     db::{Config, Db},
 };
 
@@ -25,28 +31,50 @@ const PKG_NAME: &str = env!("CARGO_PKG_NAME");
 fn main() -> anyhow::Result<()> {
     env_logger::init();
     // trap SIGINT when CTRL+C for e.g with docker-compose logs -f
-    ctrlc::set_handler(|| debug!("receive ctrl+c signal 2"))?;
+    // also stop the script being evaluated, if any
+    ctrlc::set_handler(|| {
+        debug!("receive ctrl+c signal 2");
+        karshscript::interrupt();
+    })?;
 
     let args = parse_args(std::env::args())?;
 
+    let mut limits = Limits::default();
+    for arg in &args {
+        if let Argument::Limit(name, value) = arg {
+            limits.set(name, *value)?;
+        }
+    }
+    karshscript::set_limits(limits);
+
+    for arg in &args {
+        match arg {
+            Argument::Rational => {
+                karshscript::set_number_mode(NumberMode::Rational)
+            }
+            Argument::Precision(p) => karshscript::set_precision(*p),
+            _ => (),
+        }
+    }
+
+    if let Some(path) = args.iter().find_map(|a| {
+        if let Argument::Test(path) = a {
+            Some(path)
+        } else {
+            None
+        }
+    }) {
+        let summary = test_runner::run_tests(path)?;
+        if summary.failed > 0 {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     clear_terminal();
     println!("{PKG_NAME} v{VERSION}");
 
-    let config = if args.is_empty() {
-        Config::default()
-    } else {
-        let in_memory = args.iter().any(|a| matches!(a, Argument::InMemory));
-        let fallback_in_memory =
-            args.iter().any(|a| matches!(a, Argument::FallbackInMemory));
-        let db_path = args.iter().find_map(|a| {
-            if let Argument::DbPath(path) = a {
-                Some(path)
-            } else {
-                None
-            }
-        });
-        Config::new(db_path, in_memory, fallback_in_memory)
-    };
+    let config = db_config(&args);
 
     let history_path = args.iter().find_map(|a| {
         if let Argument::HistoryPath(path) = a {
@@ -56,41 +84,76 @@ fn main() -> anyhow::Result<()> {
         }
     });
 
+    let autosave = args.iter().any(|a| matches!(a, Argument::AutoSave));
+
     println!();
 
     match Db::open(config) {
-        Ok(Db::InMemory(mut db)) => start_app(&mut db, history_path),
-        Ok(Db::FileBased(mut db)) => start_app(&mut db, history_path),
+        Ok(Db::InMemory(mut db)) => start_app(&mut db, history_path, autosave),
+        Ok(Db::FileBased(mut db)) => start_app(&mut db, history_path, autosave),
         Err(e) => Err(e),
     }
 }
 
+/// the default config unless a db related argument was given
+fn db_config(args: &[Argument]) -> Config {
+    let in_memory = args.iter().any(|a| matches!(a, Argument::InMemory));
+    let fallback_in_memory =
+        args.iter().any(|a| matches!(a, Argument::FallbackInMemory));
+    let db_path = args.iter().find_map(|a| {
+        if let Argument::DbPath(path) = a {
+            Some(path)
+        } else {
+            None
+        }
+    });
+    if !in_memory && !fallback_in_memory && db_path.is_none() {
+        Config::default()
+    } else {
+        Config::new(db_path, in_memory, fallback_in_memory)
+    }
+}
+
 fn start_app(
     db: &mut impl DbOp<String, String>,
     history_path: Option<impl AsRef<Path> + Copy>,
+    autosave: bool,
 ) -> anyhow::Result<()> {
     let mut current_cache = {
         get_default_cache(db).as_ref().map_or("DEFAULT".into(), |v| v.clone())
     };
     let mut rl = editor::build_editor(history_path);
-    let mut script_context = BTreeMap::new();
+    let mut script_context = if autosave {
+        load_script_context(db).unwrap_or_default()
+    } else {
+        BTreeMap::new()
+    };
     loop {
         let readline = editor::read_line(&mut rl, &current_cache);
 
         match readline {
             Ok(line) => {
                 rl.add_history_entry(line.as_str());
-                match process_repl(&line, &mut script_context) {
+                karshscript::reset_interrupt();
+                let mut host = CacheHost::new(db, &mut current_cache);
+                match process_repl(&line, &mut script_context, &mut host) {
+                    Ok(()) if autosave => autosave_context(db, &script_context),
                     Ok(()) => (),
-                    Err(e) => {
-                        warn!("{e}");
+                    Err(e) if e.is::<NotAScript>() => {
+                        debug!("{e}");
+                        // e.g exec --capture var or load_ctx
+                        let before = autosave.then(|| script_context.clone());
                         process_command(
                             db,
-                            &script_context,
+                            &mut script_context,
                             &mut current_cache,
                             &line,
                         )?;
+                        if before.is_some_and(|b| b != script_context) {
+                            autosave_context(db, &script_context);
+                        }
                     }
+                    Err(e) => eprintln!("{}", Red.paint(e.to_string())),
                 }
             }
             Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => {
@@ -109,11 +172,88 @@ fn start_app(
     Ok(())
 }
 
+fn autosave_context(
+    db: &mut impl DbOp<String, String>,
+    script_context: &BTreeMap<String, Primitive>,
+) {
+    if save_script_context(db, script_context).is_none() {
+        warn!("could not save script context");
+    }
+}
+
 fn process_repl(
     line: &str,
     ctx: &mut BTreeMap<String, Primitive>,
+    host: &mut impl Host,
 ) -> anyhow::Result<()> {
-    let calc = crate::karshscript::compute(line, ctx)?;
+    let calc = crate::karshscript::compute_with_host(line, ctx, host)?;
     println!("{calc}");
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        args::parse_args,
+        db::{Config, Db, InMemoryDb},
+        db_config,
+        karshscript::{set_limits, Limits, NotAScript},
+        prelude::BTreeMap,
+        process_repl, CacheHost,
+    };
+
+    fn open(config: Config) -> anyhow::Result<Db<String, String>> {
+        Db::open(config)
+    }
+
+    #[test]
+    fn test_db_config_without_db_argument() {
+        for args in [
+            vec!["--autosave"],
+            vec!["--rational"],
+            vec!["--precision", "5"],
+            vec!["--limit", "loop=10"],
+        ] {
+            let args = parse_args(args.into_iter().map(String::from)).unwrap();
+            assert_eq!(1, args.len());
+            assert!(open(db_config(&args)).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_db_config_in_memory() {
+        let args = parse_args(
+            ["--inmemory", "--autosave"].map(String::from).into_iter(),
+        )
+        .unwrap();
+        assert!(matches!(open(db_config(&args)), Ok(Db::InMemory(_))));
+    }
+
+    #[test]
+    fn test_process_repl_errors() {
+        let mut db: InMemoryDb<String, String> = InMemoryDb::default();
+        let mut current_cache = "DEFAULT".to_string();
+        let mut host = CacheHost::new(&mut db, &mut current_cache);
+        let mut ctx = BTreeMap::new();
+
+        let err = process_repl("help", &mut ctx, &mut host).unwrap_err();
+        assert!(err.is::<NotAScript>());
+        let err = process_repl("put a b", &mut ctx, &mut host).unwrap_err();
+        assert!(err.is::<NotAScript>());
+
+        let mut limits = Limits::default();
+        limits.set("loop", 10).unwrap();
+        set_limits(limits);
+        process_repl("x = 0", &mut ctx, &mut host).unwrap();
+        let err =
+            process_repl("while(true) {\n x = x + 1\n}", &mut ctx, &mut host)
+                .unwrap_err();
+        set_limits(Limits::default());
+        assert!(!err.is::<NotAScript>());
+        assert_eq!("max loop iterations of 10 reached", err.to_string());
+
+        let err =
+            process_repl("assert(1 == 2)", &mut ctx, &mut host).unwrap_err();
+        assert!(!err.is::<NotAScript>());
+    }
+}
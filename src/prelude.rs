@@ -9,7 +9,8 @@ pub use nom::{
         multispace1, none_of, one_of, space1,
     },
     combinator::{
-        all_consuming, cut, eof, map, map_parser, opt, peek, rest, verify,
+        all_consuming, cut, eof, map, map_opt, map_parser, opt, peek, rest,
+        verify,
     },
     multi::{many0, many1, separated_list0},
     number::complete::{double, recognize_float},
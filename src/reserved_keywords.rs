@@ -7,6 +7,7 @@ pub const FORBIDDEN_VARIABLE_NAME: &[&str] = &[
     FALSE,
     TAU,
     IF,
+    STRUCT,
     PI,
     PRINT_LN,
     PRINT,
@@ -20,8 +21,55 @@ pub const FORBIDDEN_VARIABLE_NAME: &[&str] = &[
     TAN,
     INCLUDE,
     WHILE,
+    MATCH,
     ELSE,
     MULTILINE,
+    CACHE_GET,
+    CACHE_PUT,
+    CACHE_DEL,
+    CACHE_LIST,
+    CACHE_USE,
+    SH,
+    ASSERT,
+    ASSERT_EQ,
+    HEX,
+    BIN,
+    OCT,
+    XOR,
+    PUSH,
+    POP,
+    INSERT,
+    REMOVE_AT,
+    CONCAT,
+    REVERSE,
+    SORT,
+    UNIQUE,
+    CONTAINS,
+    INDEX_OF,
+    ZIP,
+    FLATTEN,
+    SUM,
+    AVG,
+    NULL,
+    DEFINED,
+    NOW,
+    TIMESTAMP,
+    FORMAT_DATE,
+    PARSE_DATE,
+    FORMAT_DURATION,
+    SLEEP,
+    RANDOM,
+    RANDOM_INT,
+    SHUFFLE,
+    CHOICE,
+    SEED,
+    MATCHES,
+    FIND_ALL,
+    CAPTURES,
+    REPLACE_RE,
+    ENV,
+    ENV_ALL,
+    SET_ENV,
 ];
 
 pub fn check_reserved_keyword(aliases: &[&str]) -> bool {
@@ -0,0 +1,126 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+    cache_command::CacheHost,
+    db::InMemoryDb,
+    karshscript::{compute_with_host, set_fail_on_error},
+    prelude::{
+        colors::{Green, Red, Style},
+        BTreeMap,
+    },
+};
+
+const TEST_FILE_EXTENSION: &str = ".test.karsher";
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct TestSummary {
+    pub passed: usize,
+    pub failed: usize,
+}
+
+/// find every `*.test.karsher` file under path (or path itself if it's a file).
+/// karshscript has no functions, so there are no `test_*` functions to find
+fn discover(path: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let mut files = vec![];
+    for entry in std::fs::read_dir(path)? {
+        let entry_path = entry?.path();
+        if entry_path.is_dir() {
+            files.extend(discover(&entry_path)?);
+        } else if entry_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .filter(|n| n.ends_with(TEST_FILE_EXTENSION))
+            .is_some()
+        {
+            files.push(entry_path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// run a test file in its own script context, with its own in memory cache.
+/// like include, the file runs from its own directory.
+/// any statement giving an error fails the test
+fn run_file(path: &Path) -> anyhow::Result<()> {
+    let path = path.canonicalize()?;
+    let file = std::fs::read_to_string(&path)?;
+    let curr_path = std::env::current_dir()?;
+    if let Some(parent) = path.parent() {
+        std::env::set_current_dir(parent)?;
+    }
+    let mut db: InMemoryDb<String, String> = InMemoryDb::default();
+    let mut current_cache = "DEFAULT".to_string();
+    let mut host = CacheHost::new(&mut db, &mut current_cache);
+    let mut ctx = BTreeMap::new();
+    set_fail_on_error(true);
+    let res = compute_with_host(&file, &mut ctx, &mut host);
+    set_fail_on_error(false);
+    std::env::set_current_dir(curr_path)?;
+    res.map(|_| ())
+}
+
+pub fn run_tests(path: impl AsRef<Path>) -> anyhow::Result<TestSummary> {
+    let files = discover(path.as_ref())?;
+    let mut summary = TestSummary::default();
+    println!("running {} test file(s)", files.len());
+    for file in files {
+        match run_file(&file) {
+            Ok(()) => {
+                summary.passed += 1;
+                println!(
+                    "test {} ... {}",
+                    file.display(),
+                    Style::new().fg(Green).paint("ok")
+                );
+            }
+            Err(e) => {
+                summary.failed += 1;
+                println!(
+                    "test {} ... {}\n{e}",
+                    file.display(),
+                    Style::new().fg(Red).paint("FAILED")
+                );
+            }
+        }
+    }
+    let result = if summary.failed == 0 {
+        Style::new().fg(Green).paint("ok")
+    } else {
+        Style::new().fg(Red).paint("FAILED")
+    };
+    println!(
+        "\ntest result: {result}. {} passed; {} failed",
+        summary.passed, summary.failed
+    );
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod test {
+    use serial_test::serial;
+
+    use super::{run_file, run_tests, TestSummary};
+
+    #[test]
+    #[serial]
+    fn test_run_tests() {
+        let summary = run_tests("file_tests/runner").unwrap();
+        assert_eq!(TestSummary { passed: 2, failed: 2 }, summary);
+    }
+
+    #[test]
+    #[serial]
+    fn test_run_file_with_quote_in_path() {
+        let dir = std::env::temp_dir().join(r#"karsher "runner\ test"#);
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("quote.test.karsher");
+        std::fs::write(&file, "x = 1\nassert_eq(x, 1)\n").unwrap();
+        let res = run_file(&file);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(res.is_ok());
+    }
+}